@@ -0,0 +1,378 @@
+//! Headless uploader sharing the desktop app's config, providers and history.
+//!
+//! ```text
+//! opti-bridge-cli upload shot.png diagram.jpg --profile r2 --format markdown
+//! opti-bridge-cli upload shot.png --format asciidoc --copy
+//! opti-bridge-cli upload hero.jpg --responsive
+//! opti-bridge-cli history --limit 5
+//! opti-bridge-cli profiles
+//! opti-bridge-cli test r2 --round-trip
+//! opti-bridge-cli config export team.json --passphrase "$BUNDLE_PASSPHRASE"
+//! opti-bridge-cli config import team.json
+//! opti-bridge-cli config env
+//! ```
+//!
+//! `OPTIBRIDGE_*` variables override the stored settings without being saved,
+//! e.g. `OPTIBRIDGE_R2_SECRET_ACCESS_KEY`; `config env` lists those in effect.
+
+use clap::{Parser, Subcommand};
+use opti_bridge::error::{AppError, ErrorCode};
+use opti_bridge::models::ConnectionReport;
+use opti_bridge::modules::app_dirs;
+use opti_bridge::modules::config_bundle::ConfigBundle;
+use opti_bridge::modules::config_env;
+use opti_bridge::modules::config_manager::ConfigManager;
+use opti_bridge::modules::database::Database;
+use opti_bridge::modules::image_processor::ImageProcessor;
+use opti_bridge::modules::profiles::{self, UploadTarget};
+use opti_bridge::modules::{link_templates, responsive, upload_pipeline};
+use opti_bridge::uploaders::registry::UploaderRegistry;
+use opti_bridge::uploaders::UploadHooks;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+
+#[derive(Parser)]
+#[command(
+    name = "opti-bridge-cli",
+    version,
+    about = "Optimize and upload images from the command line"
+)]
+struct Cli {
+    /// Directory holding config.json and history.db (defaults to the desktop app's)
+    #[arg(long, global = true, env = "OPTIBRIDGE_DATA_DIR")]
+    data_dir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Process and upload images, printing one link per line
+    Upload {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Profile, account or provider id (see `profiles`); defaults to the configured default profile
+        #[arg(long, alias = "provider")]
+        profile: Option<String>,
+
+        /// Link template id: url, markdown, html, bbcode, rst, asciidoc or a custom one from the config
+        #[arg(long, default_value = "url")]
+        format: String,
+
+        /// Also copy the printed links to the clipboard
+        #[arg(long)]
+        copy: bool,
+
+        /// Do not record the uploads in history.db
+        #[arg(long)]
+        no_history: bool,
+
+        /// Upload the configured widths and formats as a set and print its markup
+        #[arg(long)]
+        responsive: bool,
+    },
+    /// List recent uploads
+    History {
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// List available providers
+    Providers,
+    /// List profiles, named accounts and providers that uploads can go through
+    Profiles,
+    /// Diagnose the connection of a profile, account or provider
+    Test {
+        /// Defaults to the configured default profile
+        profile: Option<String>,
+
+        /// Also write, read back and delete a small probe object
+        #[arg(long)]
+        round_trip: bool,
+    },
+    /// Share settings through a bundle file, or list environment overrides
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Write the stored settings to a bundle; secrets are left out without a passphrase
+    Export {
+        path: PathBuf,
+
+        /// Include the secrets, encrypted with this passphrase
+        #[arg(long, env = "OPTIBRIDGE_BUNDLE_PASSPHRASE")]
+        passphrase: Option<String>,
+    },
+    /// Replace the settings with those of a bundle
+    Import {
+        path: PathBuf,
+
+        /// Passphrase the bundle's secrets were encrypted with
+        #[arg(long, env = "OPTIBRIDGE_BUNDLE_PASSPHRASE")]
+        passphrase: Option<String>,
+    },
+    /// List the OPTIBRIDGE_* variables overriding settings
+    Env,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(cli).await {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error[{}]: {}", code(&e), e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// The stable `ErrorCode` name, as the webview sees it.
+fn code(error: &AppError) -> String {
+    serde_json::to_value(error.code)
+        .ok()
+        .and_then(|code| code.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Returns `Ok(false)` when some, but not necessarily all, items failed.
+async fn run(cli: Cli) -> Result<bool, AppError> {
+    let data_dir = cli
+        .data_dir
+        .or_else(app_dirs::default_data_dir)
+        .ok_or_else(|| {
+            AppError::new(
+                ErrorCode::Io,
+                "Failed to determine the data directory, pass --data-dir",
+            )
+        })?;
+
+    let registry = UploaderRegistry::with_defaults();
+
+    match cli.command {
+        Command::Upload {
+            files,
+            profile,
+            format,
+            copy,
+            no_history,
+            responsive,
+        } => {
+            let config = ConfigManager::open(&data_dir.join("config.json")).load_config()?;
+            let target = profiles::resolve(&registry, &config, profile.as_deref())?;
+            let template = link_templates::find(&config, &format)?;
+            let processor = ImageProcessor::from_config(&target.config);
+            let db = if no_history {
+                None
+            } else {
+                Some(Database::open(&data_dir.join("history.db"))?)
+            };
+
+            let mut all_ok = true;
+            let mut links = Vec::new();
+            for file in &files {
+                if responsive {
+                    match upload_set(&registry, &target, &processor, db.as_ref(), file).await {
+                        Ok(html) => {
+                            println!("{}", html);
+                            links.push(html);
+                        }
+                        Err(e) => {
+                            eprintln!("{}: error[{}]: {}", file.display(), code(&e), e);
+                            all_ok = false;
+                        }
+                    }
+                    continue;
+                }
+
+                let result = async {
+                    let image_data = processor.process_from_path(&file.to_string_lossy())?;
+                    if let Some(notice) = processor.format_notice(&image_data) {
+                        eprintln!("{}: {}", file.display(), notice);
+                    }
+                    let name = upload_name(file, &image_data);
+                    let label = file.display().to_string();
+                    let hooks = UploadHooks {
+                        on_retry: Arc::new(move |notice| {
+                            eprintln!(
+                                "{}: attempt {} failed ({}), retrying in {:.1}s",
+                                label,
+                                notice.attempt,
+                                notice.error,
+                                notice.delay.as_secs_f32()
+                            )
+                        }),
+                        ..UploadHooks::default()
+                    };
+
+                    let history_item = upload_pipeline::upload_processed_with(
+                        &registry,
+                        &target,
+                        &image_data,
+                        &name,
+                        &hooks,
+                    )
+                    .await?;
+
+                    if let Some(db) = &db {
+                        db.insert_history(&history_item)?;
+                    }
+
+                    Ok::<_, AppError>(link_templates::render(
+                        &template.template,
+                        &history_item,
+                        None,
+                    ))
+                }
+                .await;
+
+                match result {
+                    Ok(link) => {
+                        println!("{}", link);
+                        links.push(link);
+                    }
+                    Err(e) => {
+                        eprintln!("{}: error[{}]: {}", file.display(), code(&e), e);
+                        all_ok = false;
+                    }
+                }
+            }
+
+            if copy && !links.is_empty() {
+                link_templates::copy_to_clipboard(&links.join("\n"))?;
+            }
+
+            Ok(all_ok)
+        }
+        Command::History { limit } => {
+            let db = Database::open(&data_dir.join("history.db"))?;
+            for item in db.get_history()?.iter().take(limit) {
+                println!("{}\t{}\t{}", item.created_at, item.provider, item.url);
+            }
+
+            Ok(true)
+        }
+        Command::Providers => {
+            for provider in registry.providers() {
+                println!("{}\t{}", provider.id, provider.name);
+            }
+
+            Ok(true)
+        }
+        Command::Profiles => {
+            let config = ConfigManager::open(&data_dir.join("config.json")).load_config()?;
+            for profile in profiles::list(&registry, &config) {
+                let default = if profile.is_default { "\tdefault" } else { "" };
+                println!(
+                    "{}\t{}\t{}{}",
+                    profile.id, profile.name, profile.provider, default
+                );
+            }
+
+            Ok(true)
+        }
+        Command::Test {
+            profile,
+            round_trip,
+        } => {
+            let config = ConfigManager::open(&data_dir.join("config.json")).load_config()?;
+            let target = profiles::resolve(&registry, &config, profile.as_deref())?;
+            let uploader = registry.build(&target.provider, &target.config)?;
+
+            let report =
+                ConnectionReport::new(&target.provider, uploader.test_connection(round_trip).await);
+            for check in &report.checks {
+                println!("{:?}\t{:?}\t{}", check.status, check.kind, check.detail);
+            }
+
+            Ok(report.ok)
+        }
+        Command::Config { action } => {
+            let config_manager = ConfigManager::open(&data_dir.join("config.json"));
+            match action {
+                ConfigAction::Export { path, passphrase } => {
+                    let bundle = config_manager.export_bundle(passphrase.as_deref())?;
+                    std::fs::write(&path, bundle.to_json()?).map_err(|e| {
+                        AppError::io(&format!("Failed to write {}", path.display()), e)
+                    })?;
+                    let secrets = bundle.secrets.map_or(0, |sealed| sealed.keys.len());
+                    eprintln!(
+                        "Exported settings and {} secrets to {}",
+                        secrets,
+                        path.display()
+                    );
+                }
+                ConfigAction::Import { path, passphrase } => {
+                    let content = std::fs::read_to_string(&path).map_err(|e| {
+                        AppError::io(&format!("Failed to read {}", path.display()), e)
+                    })?;
+                    let imported = config_manager
+                        .import_bundle(ConfigBundle::parse(&content)?, passphrase.as_deref())?;
+                    eprintln!(
+                        "Imported settings and {} secrets from {}",
+                        imported,
+                        path.display()
+                    );
+                }
+                ConfigAction::Env => {
+                    for found in config_env::overrides(&config_manager.load_stored()?) {
+                        println!("{}\t{}", found.variable, found.field);
+                    }
+                }
+            }
+
+            Ok(true)
+        }
+    }
+}
+
+async fn upload_set(
+    registry: &UploaderRegistry,
+    target: &UploadTarget,
+    processor: &ImageProcessor,
+    db: Option<&Database>,
+    file: &Path,
+) -> Result<String, AppError> {
+    let config = &target.config;
+    let source = std::fs::read(file).map_err(|e| AppError::io("Failed to open image", e))?;
+    let widths = responsive::variant_widths(
+        &config.settings_variant_widths,
+        config.settings_variant_retina,
+    );
+    let variants =
+        processor.process_variants(&source, &widths, &config.settings_variant_formats)?;
+
+    let name = file
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "image".to_string());
+    let items =
+        upload_pipeline::upload_variants(registry, target, &variants, &name, |item| match db {
+            Some(db) => db.insert_history(item),
+            None => Ok(()),
+        })
+        .await?;
+
+    let alt = file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    responsive::render_html(&items, &config.settings_variant_sizes, &alt)
+}
+
+fn upload_name(file: &Path, image_data: &[u8]) -> String {
+    let stem = file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "image".to_string());
+
+    format!("{}.{}", stem, ImageProcessor::detect_extension(image_data))
+}
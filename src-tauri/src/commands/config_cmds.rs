@@ -1,15 +1,119 @@
-use crate::models::Config;
+use crate::error::{AppError, ErrorCode};
+use crate::models::{Config, EnvOverride, SecretStoreStatus, UploadServerStatus};
+use crate::modules::config_bundle::ConfigBundle;
 use crate::modules::config_manager::ConfigManager;
+use crate::modules::{config_env, upload_server};
+use crate::state::AppState;
+use std::sync::PoisonError;
+use tauri::State;
 
+/// Secrets that are set come back as `secret_store::REDACTED`.
 #[tauri::command]
-pub fn get_config(app_handle: tauri::AppHandle) -> Result<Config, String> {
+pub fn get_config(app_handle: tauri::AppHandle) -> Result<Config, AppError> {
     let config_manager = ConfigManager::new(&app_handle)?;
-    config_manager.load_config()
+    config_manager.load_redacted()
 }
 
 #[tauri::command]
-pub fn save_config(config: Config, app_handle: tauri::AppHandle) -> Result<(), String> {
+pub fn save_config(config: Config, app_handle: tauri::AppHandle) -> Result<(), AppError> {
     let config_manager = ConfigManager::new(&app_handle)?;
-    config_manager.save_config(&config)
+    config_manager.save_config(&config)?;
+
+    upload_server::sync(&app_handle, &config).map_err(|e| {
+        AppError::new(
+            ErrorCode::InvalidConfig,
+            format!(
+                "Settings saved, but the upload server could not be updated: {}",
+                e
+            ),
+        )
+        .with_fields(vec!["settings_server_port".to_string()])
+    })
+}
+
+/// Includes why the server is not running, such as a busy port at launch.
+#[tauri::command]
+pub fn get_upload_server_status(app_handle: tauri::AppHandle) -> UploadServerStatus {
+    upload_server::status(&app_handle)
+}
+
+/// Writes the stored settings to `path` as a bundle; secrets are included,
+/// encrypted, only when `passphrase` is given.
+#[tauri::command]
+pub fn export_config(
+    path: String,
+    passphrase: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), AppError> {
+    let config_manager = ConfigManager::new(&app_handle)?;
+    let bundle = config_manager.export_bundle(passphrase.as_deref())?;
+
+    std::fs::write(&path, bundle.to_json()?)
+        .map_err(|e| AppError::io("Failed to write config bundle", e))
+}
+
+/// Replaces the settings with the bundle at `path`; returns how many secrets it supplied.
+#[tauri::command]
+pub fn import_config(
+    path: String,
+    passphrase: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<usize, AppError> {
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| AppError::io("Failed to read config bundle", e))?;
+    let config_manager = ConfigManager::new(&app_handle)?;
+    let imported =
+        config_manager.import_bundle(ConfigBundle::parse(&content)?, passphrase.as_deref())?;
+
+    let config = config_manager.load_config()?;
+    upload_server::sync(&app_handle, &config).map_err(|e| {
+        AppError::new(
+            ErrorCode::InvalidConfig,
+            format!(
+                "Settings imported, but the upload server could not be updated: {}",
+                e
+            ),
+        )
+        .with_fields(vec!["settings_server_port".to_string()])
+    })?;
+
+    Ok(imported)
 }
 
+#[tauri::command]
+pub fn list_env_overrides(app_handle: tauri::AppHandle) -> Result<Vec<EnvOverride>, AppError> {
+    let config = ConfigManager::new(&app_handle)?.load_stored()?;
+    Ok(config_env::overrides(&config))
+}
+
+#[tauri::command]
+pub fn get_secret_store_status(
+    app_handle: tauri::AppHandle,
+) -> Result<SecretStoreStatus, AppError> {
+    let config_manager = ConfigManager::new(&app_handle)?;
+    let secrets = config_manager.secrets();
+
+    Ok(SecretStoreStatus {
+        backend: secrets.backend(),
+        locked: secrets.is_locked(),
+        vault_exists: secrets.vault_exists(),
+    })
+}
+
+/// Remembers the vault passphrase for this session, or sets it when there is
+/// no vault yet.
+#[tauri::command]
+pub fn unlock_vault(
+    passphrase: String,
+    state: State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), AppError> {
+    let mut config_manager = ConfigManager::new(&app_handle)?;
+    config_manager.unlock_vault(passphrase.clone())?;
+
+    *state
+        .vault_passphrase
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner) = Some(passphrase);
+    Ok(())
+}
@@ -1,26 +1,87 @@
+use crate::error::{AppError, ErrorCode};
 use crate::models::HistoryItem;
+use crate::modules::config_manager::ConfigManager;
 use crate::modules::database::Database;
+use crate::modules::{profiles, responsive};
+use crate::state::AppState;
+use tauri::State;
 
 #[tauri::command]
-pub fn get_history(app_handle: tauri::AppHandle) -> Result<Vec<HistoryItem>, String> {
+pub fn get_history(app_handle: tauri::AppHandle) -> Result<Vec<HistoryItem>, AppError> {
     let db = Database::new(&app_handle)?;
     db.get_history()
 }
 
+/// Markup for a responsive set recorded in history.
 #[tauri::command]
-pub fn delete_history_item(
+pub fn render_image_set(
+    group_id: String,
+    alt: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, AppError> {
+    let config = ConfigManager::new(&app_handle)?.load_config()?;
+    let db = Database::new(&app_handle)?;
+    let items = db.get_group(&group_id)?;
+
+    responsive::render_html(
+        &items,
+        &config.settings_variant_sizes,
+        alt.as_deref().unwrap_or(""),
+    )
+}
+
+/// Deletes the uploaded object from its provider, then the history row.
+/// If the remote delete fails the row is kept and marked `delete_failed`;
+/// pass `local_only` to drop the row without touching the provider.
+#[tauri::command]
+pub async fn delete_history_item(
     id: String,
-    _url: String,
-    _provider: String,
+    local_only: Option<bool>,
+    state: State<'_, AppState>,
     app_handle: tauri::AppHandle,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
+    let item = {
+        let db = Database::new(&app_handle)?;
+        db.get_history_item(&id)?
+            .ok_or_else(|| AppError::not_found("History item not found"))?
+    };
+
+    if !local_only.unwrap_or(false) {
+        if let Err(e) = delete_remote(&item, &state, &app_handle).await {
+            let db = Database::new(&app_handle)?;
+            db.set_status(&id, HistoryItem::STATUS_DELETE_FAILED)?;
+            return Err(e);
+        }
+    }
+
     // Delete from database
     let db = Database::new(&app_handle)?;
     db.delete_history(&id)?;
 
-    // TODO: Optionally delete from cloud provider
-    // This would require implementing delete methods in cloudinary.rs and r2.rs
-    
     Ok(())
 }
 
+async fn delete_remote(
+    item: &HistoryItem,
+    state: &AppState,
+    app_handle: &tauri::AppHandle,
+) -> Result<(), AppError> {
+    let config_manager = ConfigManager::new(app_handle)?;
+    let config = config_manager.load_config()?;
+    // Delete with the account the object was uploaded through
+    let account = item.account_id.as_deref().unwrap_or(&item.provider);
+    let target = profiles::resolve_account(&state.uploaders, &config, account)?;
+
+    let uploader = state.uploaders.build(&item.provider, &target.config)?;
+    if !uploader.capabilities().delete {
+        return Err(AppError::new(
+            ErrorCode::Unsupported,
+            format!("{} does not support remote deletion", item.provider),
+        ));
+    }
+
+    uploader
+        .delete(&item.remote_object())
+        .await
+        .map_err(|e| AppError::from(e).with_provider(&item.provider))
+}
@@ -1,41 +1,54 @@
+use crate::error::{AppError, ErrorCode};
 use crate::models::ProcessedImageResult;
 use crate::modules::config_manager::ConfigManager;
+use crate::modules::image_cache::CachedImage;
 use crate::modules::image_processor::ImageProcessor;
 use crate::state::AppState;
+use crate::uploaders::mime_type;
 use arboard::Clipboard;
+use base64::{engine::general_purpose, Engine as _};
 use tauri::State;
 use uuid::Uuid;
-use base64::{Engine as _, engine::general_purpose};
 
 #[tauri::command]
 pub fn process_image_from_file(
     path: String,
     state: State<AppState>,
     app_handle: tauri::AppHandle,
-) -> Result<ProcessedImageResult, String> {
-    // Load config to get max width
+) -> Result<ProcessedImageResult, AppError> {
+    // Load config for processing settings
     let config_manager = ConfigManager::new(&app_handle)?;
     let config = config_manager.load_config()?;
 
     // Process image
-    let processor = ImageProcessor::new(config.settings_max_width);
-    let processed_bytes = processor.process_from_path(&path)?;
+    let processor = ImageProcessor::from_config(&config);
+    let source_bytes = std::fs::read(&path).map_err(|e| AppError::io("Failed to open image", e))?;
+    let processed_bytes = processor.process_from_bytes(&source_bytes)?;
 
     // Generate preview (Base64)
     let preview_base64 = general_purpose::STANDARD.encode(&processed_bytes);
+    let mime_type = mime_type(ImageProcessor::detect_extension(&processed_bytes)).to_string();
 
     // Get size info
-    let size_info = processor.get_size_info(&processed_bytes);
+    let size_info = processor.get_size_info(&source_bytes, &processed_bytes);
+    let format_notice = processor.format_notice(&processed_bytes);
 
     // Generate temp ID and store in cache
     let temp_id = Uuid::new_v4().to_string();
-    let mut cache = state.image_cache.lock().unwrap();
-    cache.insert(temp_id.clone(), processed_bytes);
+    state.image_cache.insert(
+        temp_id.clone(),
+        CachedImage {
+            source: source_bytes,
+            processed: processed_bytes,
+        },
+    )?;
 
     Ok(ProcessedImageResult {
         preview_base64,
+        mime_type,
         size_info,
         temp_id,
+        format_notice,
     })
 }
 
@@ -43,13 +56,24 @@ pub fn process_image_from_file(
 pub fn process_image_from_clipboard(
     state: State<AppState>,
     app_handle: tauri::AppHandle,
-) -> Result<ProcessedImageResult, String> {
+) -> Result<ProcessedImageResult, AppError> {
     // Get image from clipboard
-    let mut clipboard = Clipboard::new().map_err(|e| format!("Failed to access clipboard: {}", e))?;
-    
-    let image = clipboard
-        .get_image()
-        .map_err(|e| format!("No image in clipboard: {}", e))?;
+    let mut clipboard = Clipboard::new().map_err(|e| {
+        AppError::new(
+            ErrorCode::ClipboardUnavailable,
+            format!("Failed to access clipboard: {}", e),
+        )
+    })?;
+
+    let image = clipboard.get_image().map_err(|e| match e {
+        arboard::Error::ContentNotAvailable => {
+            AppError::new(ErrorCode::ClipboardEmpty, "No image in clipboard")
+        }
+        e => AppError::new(
+            ErrorCode::ClipboardUnavailable,
+            format!("Failed to read clipboard: {}", e),
+        ),
+    })?;
 
     // Convert arboard ImageData to bytes
     let width = image.width;
@@ -58,45 +82,60 @@ pub fn process_image_from_clipboard(
 
     // Create image from raw RGBA data
     let img_buffer = image::RgbaImage::from_raw(width as u32, height as u32, rgba_data.to_vec())
-        .ok_or("Failed to create image from clipboard data")?;
-    
+        .ok_or_else(|| AppError::image("Failed to create image from clipboard data"))?;
+
     let dynamic_img = image::DynamicImage::ImageRgba8(img_buffer);
-    
+
     // Save to temporary buffer in PNG format
     let mut temp_buffer = Vec::new();
     let mut cursor = std::io::Cursor::new(&mut temp_buffer);
     dynamic_img
         .write_to(&mut cursor, image::ImageFormat::Png)
-        .map_err(|e| format!("Failed to encode clipboard image: {}", e))?;
+        .map_err(|e| AppError::image(format!("Failed to encode clipboard image: {}", e)))?;
 
-    // Load config to get max width
+    // Load config for processing settings
     let config_manager = ConfigManager::new(&app_handle)?;
     let config = config_manager.load_config()?;
 
     // Process image
-    let processor = ImageProcessor::new(config.settings_max_width);
+    let processor = ImageProcessor::from_config(&config);
     let processed_bytes = processor.process_from_bytes(&temp_buffer)?;
 
     // Generate preview (Base64)
     let preview_base64 = general_purpose::STANDARD.encode(&processed_bytes);
+    let mime_type = mime_type(ImageProcessor::detect_extension(&processed_bytes)).to_string();
 
     // Get size info
-    let size_info = processor.get_size_info(&processed_bytes);
+    let size_info = processor.get_size_info(&temp_buffer, &processed_bytes);
+    let format_notice = processor.format_notice(&processed_bytes);
 
     // Generate temp ID and store in cache
     let temp_id = Uuid::new_v4().to_string();
-    let mut cache = state.image_cache.lock().unwrap();
-    cache.insert(temp_id.clone(), processed_bytes);
+    state.image_cache.insert(
+        temp_id.clone(),
+        CachedImage {
+            source: temp_buffer,
+            processed: processed_bytes,
+        },
+    )?;
 
     Ok(ProcessedImageResult {
         preview_base64,
+        mime_type,
         size_info,
         temp_id,
+        format_notice,
     })
 }
 
+/// Drops a processed image that will not be uploaded, such as a preview the
+/// user cleared or replaced. Returns whether it was still cached.
+#[tauri::command]
+pub fn discard_image(temp_id: String, state: State<AppState>) -> bool {
+    state.image_cache.remove(&temp_id)
+}
+
 #[tauri::command]
 pub fn greet(name: String) -> String {
     format!("Hello, {}! Welcome to OptiBridge.", name)
 }
-
@@ -0,0 +1,37 @@
+use crate::error::AppError;
+use crate::models::LinkTemplate;
+use crate::modules::config_manager::ConfigManager;
+use crate::modules::database::Database;
+use crate::modules::link_templates;
+
+#[tauri::command]
+pub fn get_link_templates(app_handle: tauri::AppHandle) -> Result<Vec<LinkTemplate>, AppError> {
+    let config = ConfigManager::new(&app_handle)?.load_config()?;
+    Ok(link_templates::all(&config))
+}
+
+/// Renders a history item with `template` (the configured one by default),
+/// copies it to the clipboard and returns it.
+#[tauri::command]
+pub fn copy_history_link(
+    id: String,
+    template: Option<String>,
+    alt: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, AppError> {
+    let config = ConfigManager::new(&app_handle)?.load_config()?;
+    let item = Database::new(&app_handle)?
+        .get_history_item(&id)?
+        .ok_or_else(|| AppError::not_found("History item not found"))?;
+
+    let template = link_templates::find(
+        &config,
+        template
+            .as_deref()
+            .unwrap_or(&config.settings_link_template),
+    )?;
+    let text = link_templates::render(&template.template, &item, alt.as_deref());
+    link_templates::copy_to_clipboard(&text)?;
+
+    Ok(text)
+}
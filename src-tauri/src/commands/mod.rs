@@ -1,5 +1,8 @@
+pub mod config_cmds;
+pub mod history_cmds;
 pub mod image_cmds;
+pub mod link_cmds;
+pub mod pending_cmds;
 pub mod upload_cmds;
-pub mod history_cmds;
-pub mod config_cmds;
 
+pub mod provider_cmds;
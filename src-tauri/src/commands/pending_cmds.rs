@@ -0,0 +1,26 @@
+use crate::error::AppError;
+use crate::models::{PendingFlushResult, PendingUpload};
+use crate::modules::offline_queue;
+use crate::state::AppState;
+use tauri::State;
+
+/// Uploads waiting in the offline queue, oldest first.
+#[tauri::command]
+pub fn list_pending_uploads(app_handle: tauri::AppHandle) -> Result<Vec<PendingUpload>, AppError> {
+    offline_queue::list(&app_handle)
+}
+
+/// Retries every queued upload now, including ones that failed for reasons
+/// other than the network, such as credentials fixed since.
+#[tauri::command]
+pub async fn retry_pending_uploads(
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<PendingFlushResult, AppError> {
+    state.offline_queue.flush(&app_handle, true).await
+}
+
+#[tauri::command]
+pub fn discard_pending_upload(id: String, app_handle: tauri::AppHandle) -> Result<(), AppError> {
+    offline_queue::discard(&app_handle, &id)
+}
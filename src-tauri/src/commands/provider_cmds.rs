@@ -0,0 +1,67 @@
+use crate::error::{AppError, ErrorCode};
+use crate::models::{ConnectionReport, ProfileInfo, ProviderInfo};
+use crate::modules::config_manager::ConfigManager;
+use crate::modules::profiles;
+use crate::state::AppState;
+use tauri::State;
+
+#[tauri::command]
+pub fn list_providers(state: State<AppState>) -> Vec<ProviderInfo> {
+    state.uploaders.providers()
+}
+
+/// Providers, named accounts and profiles, in the order the upload view offers them.
+#[tauri::command]
+pub fn list_profiles(
+    state: State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<ProfileInfo>, AppError> {
+    let config = ConfigManager::new(&app_handle)?.load_config()?;
+    Ok(profiles::list(&state.uploaders, &config))
+}
+
+/// Checks the credentials of a named account, or of a provider's own settings.
+#[tauri::command]
+pub async fn verify_provider(
+    account: String,
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), AppError> {
+    let config_manager = ConfigManager::new(&app_handle)?;
+    let config = config_manager.load_config()?;
+    let target = profiles::resolve_account(&state.uploaders, &config, &account)?;
+    let provider = target.provider;
+
+    let uploader = state.uploaders.build(&provider, &target.config)?;
+    if !uploader.capabilities().verify {
+        return Err(AppError::new(
+            ErrorCode::Unsupported,
+            format!("{} does not support verification", provider),
+        ));
+    }
+
+    uploader
+        .verify()
+        .await
+        .map_err(|e| AppError::from(e).with_provider(&provider))
+}
+
+/// Diagnoses the connection of a named account, or of a provider's own
+/// settings, as saved. `round_trip` also writes, reads back and deletes a
+/// small probe object where the provider supports it.
+#[tauri::command]
+pub async fn test_provider(
+    account: String,
+    round_trip: Option<bool>,
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<ConnectionReport, AppError> {
+    let config_manager = ConfigManager::new(&app_handle)?;
+    let config = config_manager.load_config()?;
+    let target = profiles::resolve_account(&state.uploaders, &config, &account)?;
+
+    let uploader = state.uploaders.build(&target.provider, &target.config)?;
+    let checks = uploader.test_connection(round_trip.unwrap_or(false)).await;
+
+    Ok(ConnectionReport::new(&target.provider, checks))
+}
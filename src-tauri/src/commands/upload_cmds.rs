@@ -1,82 +1,204 @@
-use crate::models::{HistoryItem, UploadResult};
+use crate::error::{self, AppError};
+use crate::models::{Config, ImageSetResult, UploadBatch, UploadResult};
 use crate::modules::config_manager::ConfigManager;
 use crate::modules::database::Database;
 use crate::modules::image_processor::ImageProcessor;
+use crate::modules::{link_templates, offline_queue, profiles, responsive, upload_pipeline};
 use crate::state::AppState;
-use crate::uploaders::cloudinary::CloudinaryUploader;
-use crate::uploaders::r2::R2Uploader;
-use chrono::Utc;
+use crate::uploaders::UploadHooks;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
 use tauri::State;
-use uuid::Uuid;
-use base64::{Engine as _, engine::general_purpose};
 
+/// Uploads a cached image through `profile`, or the default profile, and
+/// renders its link with the configured template, copying it to the clipboard
+/// when `settings_copy_link` is on. A failed upload is moved to the offline
+/// queue; the error then carries `pending_id`. Failing to record a successful
+/// upload in history is returned beside it as `history_error`.
 #[tauri::command]
 pub async fn upload_image(
     temp_id: String,
-    provider: String,
+    profile: Option<String>,
+    original_name: Option<String>,
     state: State<'_, AppState>,
     app_handle: tauri::AppHandle,
-) -> Result<UploadResult, String> {
-    // Retrieve image from cache
-    let image_data = {
-        let cache = state.image_cache.lock().unwrap();
-        cache
-            .get(&temp_id)
-            .cloned()
-            .ok_or("Image not found in cache")?
-    };
-
+) -> Result<UploadResult, AppError> {
     // Load config
     let config_manager = ConfigManager::new(&app_handle)?;
     let config = config_manager.load_config()?;
+    let target = profiles::resolve(&state.uploaders, &config, profile.as_deref())?;
 
-    // Upload based on provider
-    let url = match provider.as_str() {
-        "cloudinary" => {
-            let uploader = CloudinaryUploader::new(
-                config.cloudinary_cloud_name,
-                config.cloudinary_api_key,
-                config.cloudinary_api_secret,
-            );
-            uploader.upload(&image_data, "image.webp").await?
-        }
-        "r2" => {
-            let uploader = R2Uploader::new(
-                config.r2_access_key_id,
-                config.r2_secret_access_key,
-                config.r2_bucket_name,
-                config.r2_endpoint,
-                config.r2_public_domain,
-            );
-            uploader.upload(&image_data, "image.webp").await?
+    // Retrieve image from cache; the preview was processed with the global
+    // settings, so a profile with its own settings starts from the source
+    let (cached, spill_error) = state.image_cache.get(&temp_id)?;
+    if let Some(e) = spill_error {
+        error::report(&app_handle, &e);
+    }
+    let image_data = if target.overrides_processing {
+        ImageProcessor::from_config(&target.config).process_from_bytes(&cached.source)?
+    } else {
+        cached.processed
+    };
+
+    let stem = original_name
+        .as_deref()
+        .and_then(|name| Path::new(name).file_stem())
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "image".to_string());
+    let filename = format!("{}.{}", stem, ImageProcessor::detect_extension(&image_data));
+    let attempts = Arc::new(AtomicU32::new(1));
+    let retries = attempts.clone();
+    let hooks = UploadHooks {
+        on_retry: Arc::new(move |notice| retries.store(notice.attempt + 1, Ordering::Relaxed)),
+        ..UploadHooks::default()
+    };
+
+    let history_item = match upload_pipeline::upload_processed_with(
+        &state.uploaders,
+        &target,
+        &image_data,
+        &filename,
+        &hooks,
+    )
+    .await
+    {
+        Ok(history_item) => history_item,
+        Err(e) => {
+            let error = offline_queue::park(&app_handle, &target, &filename, &image_data, e);
+            // The offline queue has its own copy now
+            if error.pending_id().is_some() {
+                state.image_cache.remove(&temp_id);
+            }
+            return Err(error);
         }
-        _ => return Err("Invalid provider".to_string()),
     };
 
-    // Create thumbnail for history
-    let processor = ImageProcessor::new(config.settings_max_width);
-    let thumbnail_bytes = processor.create_thumbnail(&image_data, 200)?;
-    let thumbnail_base64 = general_purpose::STANDARD.encode(&thumbnail_bytes);
+    // The image is online now; a retry would upload it a second time
+    state.image_cache.remove(&temp_id);
 
     // Save to history
-    let history_item = HistoryItem {
-        id: Uuid::new_v4().to_string(),
-        provider: provider.clone(),
-        original_name: "image.webp".to_string(),
-        url: url.clone(),
-        created_at: Utc::now().timestamp(),
-        thumbnail_base64,
-    };
+    let history_error = Database::new(&app_handle)
+        .and_then(|db| db.insert_history(&history_item))
+        .err();
 
-    let db = Database::new(&app_handle)?;
-    db.insert_history(&history_item)?;
+    let text = link_templates::render_default(&config, &history_item, None)?;
+    let (copied, copy_error) = copy_link(&config, &text);
 
-    // Clear from cache
-    {
-        let mut cache = state.image_cache.lock().unwrap();
-        cache.remove(&temp_id);
+    Ok(UploadResult {
+        url: history_item.url,
+        text,
+        copied,
+        copy_error,
+        history_error,
+        attempts: attempts.load(Ordering::Relaxed),
+    })
+}
+
+/// Uploads the configured widths and formats of a cached image as one
+/// responsive set through `profile`, or the default profile, and returns the
+/// markup referencing them.
+#[tauri::command]
+pub async fn upload_image_set(
+    temp_id: String,
+    profile: Option<String>,
+    alt: Option<String>,
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<ImageSetResult, AppError> {
+    let (cached, spill_error) = state.image_cache.get(&temp_id)?;
+    if let Some(e) = spill_error {
+        error::report(&app_handle, &e);
     }
+    let source = cached.source;
+
+    let config_manager = ConfigManager::new(&app_handle)?;
+    let config = config_manager.load_config()?;
+    let target = profiles::resolve(&state.uploaders, &config, profile.as_deref())?;
+
+    let processor = ImageProcessor::from_config(&target.config);
+    let widths = responsive::variant_widths(
+        &config.settings_variant_widths,
+        config.settings_variant_retina,
+    );
+    let variants =
+        processor.process_variants(&source, &widths, &config.settings_variant_formats)?;
+
+    let items =
+        upload_pipeline::upload_variants(&state.uploaders, &target, &variants, "image", |item| {
+            Database::new(&app_handle)?.insert_history(item)
+        })
+        .await?;
+
+    let html = responsive::render_html(
+        &items,
+        &config.settings_variant_sizes,
+        alt.as_deref().unwrap_or(""),
+    )?;
+    let (copied, copy_error) = copy_link(&config, &html);
+
+    state.image_cache.remove(&temp_id);
 
-    Ok(UploadResult { url })
+    Ok(ImageSetResult {
+        group_id: items
+            .first()
+            .and_then(|item| item.group_id.clone())
+            .unwrap_or_default(),
+        html,
+        urls: items.into_iter().map(|item| item.url).collect(),
+        copied,
+        copy_error,
+    })
 }
 
+/// Queues files for processing and upload in the background, through the
+/// default profile unless one is given. Progress is reported through
+/// `upload-progress` events.
+#[tauri::command]
+pub fn enqueue_uploads(
+    paths: Vec<String>,
+    profile: Option<String>,
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<UploadBatch, AppError> {
+    let config_manager = ConfigManager::new(&app_handle)?;
+    let config = config_manager.load_config()?;
+    let target = profiles::resolve(&state.uploaders, &config, profile.as_deref())?;
+
+    // Fail up front instead of once per item
+    state.uploaders.build(&target.provider, &target.config)?;
+
+    Ok(state.upload_queue.enqueue(&app_handle, &target, paths))
+}
+
+#[tauri::command]
+pub fn cancel_upload(
+    item_id: String,
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), AppError> {
+    state.upload_queue.cancel(&app_handle, &item_id)
+}
+
+/// Cancels the unfinished items of a batch and returns how many were cancelled.
+#[tauri::command]
+pub fn cancel_batch(
+    batch_id: String,
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> usize {
+    state.upload_queue.cancel_batch(&app_handle, &batch_id)
+}
+
+/// Copies `text` when `settings_copy_link` is on. The upload already
+/// succeeded, so a clipboard failure is returned beside it instead of failing it.
+fn copy_link(config: &Config, text: &str) -> (bool, Option<AppError>) {
+    if !config.settings_copy_link {
+        return (false, None);
+    }
+
+    match link_templates::copy_to_clipboard(text) {
+        Ok(()) => (true, None),
+        Err(e) => (false, Some(e)),
+    }
+}
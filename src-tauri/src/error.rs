@@ -0,0 +1,201 @@
+//! The error returned by every Tauri command. It serializes as
+//! `{ "code": "auth_failed", "message": "...", "details": { ... } }`: the code
+//! is stable for the webview to branch on, the message is meant for people.
+
+use crate::uploaders::error::{UploadError, UploadErrorKind};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use tauri::{AppHandle, Manager};
+
+/// Emitted with an `AppError` when work no command is waiting on fails, such
+/// as the upload server or a background retry, so the UI can show it.
+pub const BACKGROUND_ERROR_EVENT: &str = "background-error";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The clipboard holds no image.
+    ClipboardEmpty,
+    /// The system clipboard could not be opened or written.
+    ClipboardUnavailable,
+    FileNotFound,
+    /// The disk or the database ran out of space.
+    DiskFull,
+    /// Any other filesystem failure.
+    Io,
+    /// The image could not be decoded or encoded.
+    ImageProcessing,
+    /// A cached image, history item, upload or template that does not exist.
+    NotFound,
+    UnknownProvider,
+    /// Required provider settings are empty; `details.fields` names them.
+    ProviderNotConfigured,
+    /// The provider cannot do what was asked, such as deleting.
+    Unsupported,
+    /// The provider rejected the credentials (401, 403).
+    AuthFailed,
+    /// The provider refused the request itself (other 4xx).
+    RequestRejected,
+    /// Timeouts, dropped connections, throttling and 5xx, after retries ran out.
+    Network,
+    /// The provider answered with something unexpected.
+    ProviderError,
+    /// `config.json` could not be parsed or holds an invalid value.
+    InvalidConfig,
+    /// Secrets are in the encrypted vault and its passphrase is missing or wrong.
+    VaultLocked,
+    /// The OS keyring failed or the vault file is damaged.
+    SecretStore,
+    Database,
+    /// Anything not classified above.
+    Internal,
+}
+
+/// Optional context. Empty fields are left out of the JSON.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorDetails {
+    /// HTTP status of the provider response.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// The provider's own error text.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    /// Provider id the failure belongs to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// `Config` keys the user should look at, in settings order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<String>,
+    /// Why each of `fields` is invalid, for settings that failed validation.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub field_errors: BTreeMap<String, String>,
+    /// Set when the upload was saved to the offline queue instead of being lost.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pending_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
+    /// Boxed to keep `Result<_, AppError>` small.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<Box<ErrorDetails>>,
+}
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::NotFound, message)
+    }
+
+    pub fn image(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::ImageProcessing, message)
+    }
+
+    /// A failure that points at a bug rather than anything the user can fix.
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Internal, message)
+    }
+
+    /// A filesystem failure; `context` says what was being done.
+    pub fn io(context: &str, error: std::io::Error) -> Self {
+        let code = match error.kind() {
+            std::io::ErrorKind::NotFound => ErrorCode::FileNotFound,
+            std::io::ErrorKind::StorageFull | std::io::ErrorKind::QuotaExceeded => {
+                ErrorCode::DiskFull
+            }
+            _ => ErrorCode::Io,
+        };
+        Self::new(code, format!("{}: {}", context, error))
+    }
+
+    /// A SQLite failure; `context` says what was being done.
+    pub fn database(context: &str, error: rusqlite::Error) -> Self {
+        let code = match error.sqlite_error_code() {
+            Some(rusqlite::ErrorCode::DiskFull) => ErrorCode::DiskFull,
+            _ => ErrorCode::Database,
+        };
+        Self::new(code, format!("{}: {}", context, error))
+    }
+
+    /// Attributes the error to `provider`, so the UI knows which settings to open.
+    pub fn with_provider(mut self, provider: &str) -> Self {
+        self.details_mut().provider = Some(provider.to_string());
+        self
+    }
+
+    pub fn with_fields(mut self, fields: Vec<String>) -> Self {
+        self.details_mut().fields = fields;
+        self
+    }
+
+    /// Sets `fields` and a message for each, from `(field, message)` pairs in settings order.
+    pub fn with_field_errors(mut self, errors: Vec<(String, String)>) -> Self {
+        let details = self.details_mut();
+        details.fields = errors.iter().map(|(field, _)| field.clone()).collect();
+        details.field_errors = errors.into_iter().collect();
+        self
+    }
+
+    pub fn with_pending_id(mut self, pending_id: &str) -> Self {
+        self.details_mut().pending_id = Some(pending_id.to_string());
+        self
+    }
+
+    pub fn pending_id(&self) -> Option<&str> {
+        self.details.as_ref()?.pending_id.as_deref()
+    }
+
+    fn details_mut(&mut self) -> &mut ErrorDetails {
+        self.details.get_or_insert_with(Box::default)
+    }
+}
+
+/// Shows `error` in the UI; there is no caller to return it to.
+pub fn report(app_handle: &AppHandle, error: &AppError) {
+    let _ = app_handle.emit_all(BACKGROUND_ERROR_EVENT, error);
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<UploadError> for AppError {
+    fn from(error: UploadError) -> Self {
+        let code = match error.kind {
+            UploadErrorKind::Transient => ErrorCode::Network,
+            UploadErrorKind::Auth => ErrorCode::AuthFailed,
+            UploadErrorKind::Invalid => ErrorCode::RequestRejected,
+            UploadErrorKind::UnexpectedResponse | UploadErrorKind::Other => {
+                ErrorCode::ProviderError
+            }
+        };
+
+        let details = (error.status.is_some() || error.body.is_some()).then(|| {
+            Box::new(ErrorDetails {
+                status: error.status,
+                body: error.body,
+                ..ErrorDetails::default()
+            })
+        });
+
+        Self {
+            code,
+            message: error.message,
+            details,
+        }
+    }
+}
@@ -0,0 +1,7 @@
+//! Core shared by the desktop app (`main.rs`) and the `opti-bridge-cli` binary.
+
+pub mod error;
+pub mod models;
+pub mod modules;
+pub mod state;
+pub mod uploaders;
@@ -2,31 +2,84 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod commands;
-mod models;
-mod modules;
-mod state;
-mod uploaders;
 
-use commands::config_cmds::{get_config, save_config};
-use commands::history_cmds::{delete_history_item, get_history};
-use commands::image_cmds::{greet, process_image_from_clipboard, process_image_from_file};
-use commands::upload_cmds::upload_image;
+use opti_bridge::{error, models, modules, state, uploaders};
+
+use commands::config_cmds::{
+    export_config, get_config, get_secret_store_status, get_upload_server_status, import_config,
+    list_env_overrides, save_config, unlock_vault,
+};
+use commands::history_cmds::{delete_history_item, get_history, render_image_set};
+use commands::image_cmds::{
+    discard_image, greet, process_image_from_clipboard, process_image_from_file,
+};
+use commands::link_cmds::{copy_history_link, get_link_templates};
+use commands::pending_cmds::{discard_pending_upload, list_pending_uploads, retry_pending_uploads};
+use commands::provider_cmds::{list_profiles, list_providers, test_provider, verify_provider};
+use commands::upload_cmds::{
+    cancel_batch, cancel_upload, enqueue_uploads, upload_image, upload_image_set,
+};
+use modules::config_manager::ConfigManager;
+use modules::{image_cache, offline_queue, upload_server};
 use state::AppState;
+use tauri::Manager;
 
 fn main() {
     tauri::Builder::default()
         .manage(AppState::new())
+        .setup(|app| {
+            // Neither a busy port nor a config that fails to load should keep
+            // the app from starting; Settings shows the error through
+            // get_upload_server_status, and saving a fixed config starts the
+            // server. The offline queue reads the config on every pass, so it
+            // picks the fix up by itself
+            match ConfigManager::new(&app.handle()).and_then(|manager| manager.load_config()) {
+                Ok(config) => {
+                    let _ = upload_server::sync(&app.handle(), &config);
+                }
+                Err(e) => upload_server::not_started(&app.handle(), e),
+            }
+            offline_queue::spawn_worker(app.handle());
+            image_cache::spawn_sweeper(app.handle());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
             process_image_from_file,
             process_image_from_clipboard,
+            discard_image,
             upload_image,
+            upload_image_set,
+            enqueue_uploads,
+            cancel_upload,
+            cancel_batch,
+            list_pending_uploads,
+            retry_pending_uploads,
+            discard_pending_upload,
+            list_providers,
+            list_profiles,
+            verify_provider,
+            test_provider,
             get_history,
             delete_history_item,
+            render_image_set,
+            get_link_templates,
+            copy_history_link,
             get_config,
             save_config,
+            export_config,
+            import_config,
+            list_env_overrides,
+            get_secret_store_status,
+            get_upload_server_status,
+            unlock_vault,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app_handle, event| {
+            // The process exits without dropping managed state
+            if let tauri::RunEvent::Exit = event {
+                app_handle.state::<AppState>().image_cache.clear();
+            }
+        });
 }
-
@@ -1,7 +1,11 @@
+use crate::error::AppError;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    /// Schema version of `config.json`; files without one are version 0.
+    #[serde(default)]
+    pub version: u32,
     pub cloudinary_cloud_name: String,
     pub cloudinary_api_key: String,
     pub cloudinary_api_secret: String,
@@ -10,13 +14,211 @@ pub struct Config {
     pub r2_bucket_name: String,
     pub r2_endpoint: String,
     pub r2_public_domain: String,
+    #[serde(default)]
+    pub s3_access_key_id: String,
+    #[serde(default)]
+    pub s3_secret_access_key: String,
+    #[serde(default)]
+    pub s3_bucket_name: String,
+    #[serde(default)]
+    pub s3_region: String,
+    #[serde(default)]
+    pub s3_endpoint: String,
+    #[serde(default)]
+    pub s3_force_path_style: bool,
+    #[serde(default)]
+    pub s3_public_domain: String,
+    #[serde(default)]
+    pub s3_acl: String,
+    #[serde(default)]
+    pub s3_cache_control: String,
     pub settings_max_width: u32,
     pub settings_auto_webp: bool,
+    /// Unset in configs written before output formats existed; see `output_format`.
+    #[serde(default)]
+    pub settings_output_format: Option<OutputFormat>,
+    #[serde(default)]
+    pub settings_webp_mode: WebpMode,
+    #[serde(default = "default_webp_quality")]
+    pub settings_webp_quality: u8,
+    #[serde(default = "default_jpeg_quality")]
+    pub settings_jpeg_quality: u8,
+    #[serde(default = "default_avif_quality")]
+    pub settings_avif_quality: u8,
+    /// 1 (slowest, smallest) to 10 (fastest).
+    #[serde(default = "default_avif_speed")]
+    pub settings_avif_speed: u8,
+    #[serde(default)]
+    pub settings_metadata_policy: MetadataPolicy,
+    #[serde(default)]
+    pub settings_animation_mode: AnimationMode,
+    #[serde(default = "default_true")]
+    pub settings_svg_sanitize: bool,
+    #[serde(default)]
+    pub settings_svg_mode: SvgMode,
+    #[serde(default = "default_svg_raster_width")]
+    pub settings_svg_raster_width: u32,
+    /// Widths of a responsive image set, before adding retina sizes.
+    #[serde(default = "default_variant_widths")]
+    pub settings_variant_widths: Vec<u32>,
+    /// Also produce a 2x version of every width.
+    #[serde(default = "default_true")]
+    pub settings_variant_retina: bool,
+    /// Formats of a responsive set in order of preference; the last one is the `<img>` fallback.
+    #[serde(default = "default_variant_formats")]
+    pub settings_variant_formats: Vec<OutputFormat>,
+    /// The `sizes` attribute of generated markup.
+    #[serde(default = "default_variant_sizes")]
+    pub settings_variant_sizes: String,
+    /// Id of the template links are rendered with after an upload.
+    #[serde(default = "default_link_template")]
+    pub settings_link_template: String,
+    #[serde(default)]
+    pub settings_custom_templates: Vec<LinkTemplate>,
+    /// Put the rendered link on the clipboard as soon as an upload finishes.
+    #[serde(default = "default_true")]
+    pub settings_copy_link: bool,
+    /// How many queued items, across all batches, are processed and uploaded at once.
+    #[serde(default = "default_upload_concurrency")]
+    pub settings_upload_concurrency: u32,
+    #[serde(default = "default_provider")]
+    pub settings_default_provider: String,
+    /// Profile used when none is picked; empty means the default provider's own settings.
+    #[serde(default)]
+    pub settings_default_profile: String,
+    /// Named credentials beside each provider's own settings above.
+    #[serde(default)]
+    pub accounts: Vec<ProviderAccount>,
+    #[serde(default)]
+    pub profiles: Vec<UploadProfile>,
+    #[serde(default)]
+    pub settings_server_enabled: bool,
+    #[serde(default = "default_server_port")]
+    pub settings_server_port: u16,
+    /// Largest request body the upload server reads, in megabytes.
+    #[serde(default = "default_server_max_upload_mb")]
+    pub settings_server_max_upload_mb: u32,
+}
+
+/// How WebP output is encoded. `Auto` picks lossless for screenshot-like images.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebpMode {
+    #[default]
+    Auto,
+    Lossy,
+    Lossless,
+}
+
+fn default_webp_quality() -> u8 {
+    85
+}
+
+fn default_jpeg_quality() -> u8 {
+    85
+}
+
+fn default_avif_quality() -> u8 {
+    70
+}
+
+fn default_avif_speed() -> u8 {
+    6
+}
+
+/// Encoding applied to processed images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Webp,
+    /// Optimized PNG.
+    Png,
+    /// Progressive JPEG.
+    Jpeg,
+    Avif,
+    /// Keep the source format; untouched unless it needs resizing.
+    Original,
+}
+
+/// Which EXIF metadata survives processing. Orientation is always applied to
+/// the pixels first, so it is never needed afterwards.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataPolicy {
+    /// Drop everything, including GPS position and camera details.
+    #[default]
+    Strip,
+    /// Keep only the artist and copyright tags.
+    KeepCopyright,
+    KeepAll,
+}
+
+/// How animated GIF and WebP input is handled, regardless of the output format.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnimationMode {
+    /// Re-encode as animated WebP, resizing every frame.
+    #[default]
+    Webp,
+    /// Upload the source file as is.
+    Original,
+}
+
+/// How SVG input is uploaded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SvgMode {
+    /// Upload the vector file as `image/svg+xml`.
+    #[default]
+    Keep,
+    /// Render at `settings_svg_raster_width` and encode in the output format.
+    Rasterize,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_svg_raster_width() -> u32 {
+    1200
+}
+
+fn default_variant_widths() -> Vec<u32> {
+    vec![480, 960, 1600]
+}
+
+fn default_variant_formats() -> Vec<OutputFormat> {
+    vec![OutputFormat::Webp, OutputFormat::Jpeg]
+}
+
+fn default_variant_sizes() -> String {
+    "100vw".to_string()
+}
+
+fn default_link_template() -> String {
+    "markdown".to_string()
+}
+
+fn default_upload_concurrency() -> u32 {
+    3
+}
+
+fn default_provider() -> String {
+    "cloudinary".to_string()
+}
+
+fn default_server_port() -> u16 {
+    crate::modules::upload_server::DEFAULT_PORT
+}
+
+fn default_server_max_upload_mb() -> u32 {
+    50
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            version: crate::modules::config_schema::latest_version(),
             cloudinary_cloud_name: String::new(),
             cloudinary_api_key: String::new(),
             cloudinary_api_secret: String::new(),
@@ -25,22 +227,268 @@ impl Default for Config {
             r2_bucket_name: String::new(),
             r2_endpoint: String::new(),
             r2_public_domain: String::new(),
+            s3_access_key_id: String::new(),
+            s3_secret_access_key: String::new(),
+            s3_bucket_name: String::new(),
+            s3_region: String::new(),
+            s3_endpoint: String::new(),
+            s3_force_path_style: false,
+            s3_public_domain: String::new(),
+            s3_acl: String::new(),
+            s3_cache_control: String::new(),
             settings_max_width: 1600,
             settings_auto_webp: true,
+            settings_output_format: Some(OutputFormat::Webp),
+            settings_webp_mode: WebpMode::Auto,
+            settings_webp_quality: default_webp_quality(),
+            settings_jpeg_quality: default_jpeg_quality(),
+            settings_avif_quality: default_avif_quality(),
+            settings_avif_speed: default_avif_speed(),
+            settings_metadata_policy: MetadataPolicy::default(),
+            settings_animation_mode: AnimationMode::default(),
+            settings_svg_sanitize: true,
+            settings_svg_mode: SvgMode::default(),
+            settings_svg_raster_width: default_svg_raster_width(),
+            settings_variant_widths: default_variant_widths(),
+            settings_variant_retina: true,
+            settings_variant_formats: default_variant_formats(),
+            settings_variant_sizes: default_variant_sizes(),
+            settings_link_template: default_link_template(),
+            settings_custom_templates: Vec::new(),
+            settings_copy_link: true,
+            settings_upload_concurrency: default_upload_concurrency(),
+            settings_default_provider: default_provider(),
+            settings_default_profile: String::new(),
+            accounts: Vec::new(),
+            profiles: Vec::new(),
+            settings_server_enabled: false,
+            settings_server_port: default_server_port(),
+            settings_server_max_upload_mb: default_server_max_upload_mb(),
         }
     }
 }
 
+impl Config {
+    /// The chosen output format. Older configs only carry `settings_auto_webp`,
+    /// where turning it off meant keeping the original format.
+    pub fn output_format(&self) -> OutputFormat {
+        self.settings_output_format
+            .unwrap_or(if self.settings_auto_webp {
+                OutputFormat::Webp
+            } else {
+                OutputFormat::Original
+            })
+    }
+
+    /// Fields kept in the secret store rather than in `config.json`.
+    pub const SECRET_FIELDS: [&'static str; 3] = [
+        "cloudinary_api_secret",
+        "r2_secret_access_key",
+        "s3_secret_access_key",
+    ];
+
+    /// Every secret value keyed by its name in the secret store: the
+    /// `SECRET_FIELDS` themselves, then `<account id>/<field>` for accounts.
+    pub fn secrets_mut(&mut self) -> Vec<(String, &mut String)> {
+        let [cloudinary, r2, s3] = Self::SECRET_FIELDS;
+        let mut secrets = vec![
+            (cloudinary.to_string(), &mut self.cloudinary_api_secret),
+            (r2.to_string(), &mut self.r2_secret_access_key),
+            (s3.to_string(), &mut self.s3_secret_access_key),
+        ];
+
+        for account in &mut self.accounts {
+            for (field, value) in &mut account.fields {
+                if let serde_json::Value::String(value) = value {
+                    if Self::SECRET_FIELDS.contains(&field.as_str()) {
+                        secrets.push((format!("{}/{}", account.id, field), value));
+                    }
+                }
+            }
+        }
+
+        secrets
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessedImageResult {
     pub preview_base64: String,
+    pub mime_type: String,
     pub size_info: String,
     pub temp_id: String,
+    /// Set when the output is not in the configured format, as for animations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format_notice: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UploadResult {
     pub url: String,
+    /// The link rendered with the configured template.
+    pub text: String,
+    /// Whether `text` was put on the clipboard.
+    pub copied: bool,
+    /// Why copying failed; the upload itself succeeded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub copy_error: Option<AppError>,
+    /// Why the upload is missing from history; the upload itself succeeded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history_error: Option<AppError>,
+    /// How many attempts the upload took.
+    pub attempts: u32,
+}
+
+/// A responsive set uploaded in one go, with markup referencing every variant.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageSetResult {
+    pub group_id: String,
+    pub html: String,
+    pub urls: Vec<String>,
+    pub copied: bool,
+    /// Why copying failed; the set itself was uploaded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub copy_error: Option<AppError>,
+}
+
+/// A batch accepted by the upload queue. Progress arrives as `UploadEvent`s.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadBatch {
+    pub batch_id: String,
+    pub items: Vec<BatchItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchItem {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UploadStatus {
+    Queued,
+    Processing,
+    Uploading,
+    /// An attempt failed with a transient error and another one is coming.
+    Retrying,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+/// State change of one batch item, emitted as the `upload-progress` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadEvent {
+    pub batch_id: String,
+    pub item_id: String,
+    pub status: UploadStatus,
+    /// Set while uploading.
+    pub bytes_sent: u64,
+    pub bytes_total: u64,
+    /// Upload attempt this event belongs to, from 1; 0 before uploading starts.
+    pub attempt: u32,
+    /// Set once done.
+    pub url: Option<String>,
+    /// Set once failed, and while retrying.
+    pub error: Option<AppError>,
+}
+
+/// An upload waiting in the offline queue. Its bytes live in `blob_name`
+/// under the `pending` directory of the app data dir.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingUpload {
+    pub id: String,
+    pub provider: String,
+    pub original_name: String,
+    pub blob_name: String,
+    pub size_bytes: i64,
+    pub created_at: i64,
+    /// Attempts made from the queue, not counting the one that put it there.
+    pub attempts: u32,
+    pub last_attempt_at: Option<i64>,
+    pub last_error: Option<AppError>,
+    /// Profile to retry with; `None` for items queued before profiles existed.
+    #[serde(default)]
+    pub profile_id: Option<String>,
+}
+
+/// Outcome of one pass over the offline queue.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PendingFlushResult {
+    pub uploaded: u32,
+    pub remaining: u32,
+}
+
+/// A named set of credentials for one provider. A provider's own settings in
+/// `Config` act as one more account, whose id is the provider id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderAccount {
+    pub id: String,
+    pub name: String,
+    pub provider: String,
+    /// Values keyed like the provider's `ProviderField`s, e.g. `r2_bucket_name`.
+    #[serde(default)]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+/// An account bundled with processing settings; unset ones fall back to the
+/// global settings. Every provider id also works as a profile id, meaning its
+/// own settings with the global processing settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadProfile {
+    pub id: String,
+    pub name: String,
+    pub account_id: String,
+    #[serde(default)]
+    pub max_width: Option<u32>,
+    #[serde(default)]
+    pub output_format: Option<OutputFormat>,
+    /// Quality of whichever lossy format is produced.
+    #[serde(default)]
+    pub quality: Option<u8>,
+}
+
+/// A profile as listed for the upload view, including the implicit ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileInfo {
+    pub id: String,
+    pub name: String,
+    pub provider: String,
+    pub account_id: String,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretBackend {
+    Keyring,
+    Vault,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecretStoreStatus {
+    pub backend: SecretBackend,
+    /// The vault's passphrase is unknown, so secrets cannot be read or saved.
+    pub locked: bool,
+    /// False until the first secret is saved to the vault.
+    pub vault_exists: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadServerStatus {
+    /// Port the server listens on; `None` while it is off or failed to start.
+    pub port: Option<u16>,
+    /// Why it last failed to start or stopped.
+    pub error: Option<AppError>,
+}
+
+/// Text a link is rendered with; placeholders are listed in `modules::link_templates::render`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkTemplate {
+    pub id: String,
+    pub name: String,
+    pub template: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,5 +499,189 @@ pub struct HistoryItem {
     pub url: String,
     pub created_at: i64,
     pub thumbnail_base64: String,
+    pub status: String,
+    pub public_id: Option<String>,
+    pub bucket: Option<String>,
+    pub object_key: Option<String>,
+    pub etag: Option<String>,
+    pub size_bytes: Option<i64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Shared by the variants of a responsive set.
+    pub group_id: Option<String>,
+    /// Named account the upload went through; `None` for the provider's own settings.
+    #[serde(default)]
+    pub account_id: Option<String>,
+}
+
+impl HistoryItem {
+    pub const STATUS_UPLOADED: &'static str = "uploaded";
+    pub const STATUS_DELETE_FAILED: &'static str = "delete_failed";
+
+    pub fn remote_object(&self) -> RemoteObject {
+        RemoteObject {
+            url: self.url.clone(),
+            public_id: self.public_id.clone(),
+            bucket: self.bucket.clone(),
+            object_key: self.object_key.clone(),
+            etag: self.etag.clone(),
+            size_bytes: self.size_bytes,
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+/// What a provider reports about a stored object. Identifiers are provider
+/// specific: Cloudinary fills `public_id`, S3-style stores fill `bucket`/`object_key`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RemoteObject {
+    pub url: String,
+    pub public_id: Option<String>,
+    pub bucket: Option<String>,
+    pub object_key: Option<String>,
+    pub etag: Option<String>,
+    pub size_bytes: Option<i64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct UploaderCapabilities {
+    pub delete: bool,
+    pub verify: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderFieldKind {
+    Text,
+    Secret,
+    Toggle,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderField {
+    pub key: String,
+    pub label: String,
+    pub kind: ProviderFieldKind,
+    pub required: bool,
+}
+
+impl ProviderField {
+    pub fn new(key: &str, label: &str) -> Self {
+        Self {
+            key: key.to_string(),
+            label: label.to_string(),
+            kind: ProviderFieldKind::Text,
+            required: true,
+        }
+    }
+
+    pub fn secret(mut self) -> Self {
+        self.kind = ProviderFieldKind::Secret;
+        self
+    }
+
+    pub fn toggle(mut self) -> Self {
+        self.kind = ProviderFieldKind::Toggle;
+        self.required = false;
+        self
+    }
+
+    pub fn optional(mut self) -> Self {
+        self.required = false;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderInfo {
+    pub id: String,
+    pub name: String,
+    pub fields: Vec<ProviderField>,
+    pub capabilities: UploaderCapabilities,
+}
+
+/// A step of a provider connection test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionCheckKind {
+    Dns,
+    Tls,
+    Auth,
+    Write,
+    Read,
+    Delete,
+    PublicUrl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    /// Not run, because it was not asked for or an earlier step failed.
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionCheck {
+    pub kind: ConnectionCheckKind,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl ConnectionCheck {
+    pub fn passed(kind: ConnectionCheckKind, detail: impl Into<String>) -> Self {
+        Self {
+            kind,
+            status: CheckStatus::Passed,
+            detail: detail.into(),
+        }
+    }
+
+    pub fn failed(kind: ConnectionCheckKind, detail: impl Into<String>) -> Self {
+        Self {
+            kind,
+            status: CheckStatus::Failed,
+            detail: detail.into(),
+        }
+    }
+
+    pub fn skipped(kind: ConnectionCheckKind, detail: impl Into<String>) -> Self {
+        Self {
+            kind,
+            status: CheckStatus::Skipped,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Outcome of `test_provider`, one entry per step in the order they ran.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionReport {
+    pub provider: String,
+    pub ok: bool,
+    pub checks: Vec<ConnectionCheck>,
+}
+
+impl ConnectionReport {
+    pub fn new(provider: &str, checks: Vec<ConnectionCheck>) -> Self {
+        Self {
+            provider: provider.to_string(),
+            ok: checks
+                .iter()
+                .all(|check| check.status != CheckStatus::Failed),
+            checks,
+        }
+    }
+}
+
+/// An `OPTIBRIDGE_*` environment variable overriding a setting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvOverride {
+    pub variable: String,
+    /// `Config` key, or `<account id>/<field>` for an account's field.
+    pub field: String,
+}
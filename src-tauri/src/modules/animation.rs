@@ -0,0 +1,274 @@
+use crate::error::AppError;
+use image::codecs::gif::GifDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{imageops, imageops::FilterType, AnimationDecoder, ImageFormat, RgbaImage};
+use std::io::Cursor;
+
+/// One fully composited frame and how long it stays on screen.
+pub struct AnimationFrame {
+    pub image: RgbaImage,
+    pub delay_ms: u32,
+}
+
+/// Browsers show GIF frames with a delay of 10 ms or less for 100 ms.
+const GIF_MIN_DELAY_MS: u32 = 10;
+const GIF_DEFAULT_DELAY_MS: u32 = 100;
+
+/// Decodes every frame of an animated GIF or WebP. Returns `None` for still
+/// images, including single-frame GIFs, so they take the normal path.
+pub fn decode_frames(
+    bytes: &[u8],
+    source_format: Option<ImageFormat>,
+) -> Result<Option<Vec<AnimationFrame>>, AppError> {
+    let frames = match source_format {
+        Some(ImageFormat::Gif) => GifDecoder::new(Cursor::new(bytes))
+            .map_err(|e| AppError::image(format!("Failed to read GIF: {}", e)))?
+            .into_frames()
+            .collect_frames(),
+        Some(ImageFormat::WebP) if has_animation_flag(bytes) => {
+            WebPDecoder::new(Cursor::new(bytes))
+                .map_err(|e| AppError::image(format!("Failed to read WebP: {}", e)))?
+                .into_frames()
+                .collect_frames()
+        }
+        _ => return Ok(None),
+    }
+    .map_err(|e| AppError::image(format!("Failed to decode animation frames: {}", e)))?;
+
+    if frames.len() < 2 {
+        return Ok(None);
+    }
+
+    // played as fast as possible, 0 and 10 ms GIF delays would turn a
+    // browser-paced animation into a flicker
+    let is_gif = source_format == Some(ImageFormat::Gif);
+
+    Ok(Some(
+        frames
+            .into_iter()
+            .map(|frame| {
+                let (numer, denom) = frame.delay().numer_denom_ms();
+                let delay_ms = match numer / denom.max(1) {
+                    delay if is_gif && delay <= GIF_MIN_DELAY_MS => GIF_DEFAULT_DELAY_MS,
+                    delay => delay,
+                };
+                AnimationFrame {
+                    delay_ms,
+                    image: frame.into_buffer(),
+                }
+            })
+            .collect(),
+    ))
+}
+
+/// Scales every frame down to `max_width`, keeping the delays.
+pub fn resize_frames(frames: Vec<AnimationFrame>, max_width: u32) -> Vec<AnimationFrame> {
+    frames
+        .into_iter()
+        .map(|frame| {
+            let (width, height) = frame.image.dimensions();
+            if width <= max_width {
+                return frame;
+            }
+
+            let new_height = (height as f64 * (max_width as f64 / width as f64)) as u32;
+            AnimationFrame {
+                image: imageops::resize(&frame.image, max_width, new_height, FilterType::Lanczos3),
+                delay_ms: frame.delay_ms,
+            }
+        })
+        .collect()
+}
+
+/// Encodes frames as a looping animated WebP.
+pub fn encode_webp(
+    frames: &[AnimationFrame],
+    lossless: bool,
+    quality: u8,
+) -> Result<Vec<u8>, AppError> {
+    let (width, height) = frames
+        .first()
+        .map(|frame| frame.image.dimensions())
+        .ok_or_else(|| AppError::image("Animation has no frames"))?;
+
+    let mut config =
+        webp::WebPConfig::new().map_err(|_| AppError::image("Failed to configure WebP encoder"))?;
+    config.lossless = lossless as i32;
+    config.quality = quality as f32;
+
+    let mut encoder = webp::AnimEncoder::new(width, height, &config);
+    let mut timestamp = 0u32;
+    for frame in frames {
+        encoder.add_frame(webp::AnimFrame::from_rgba(
+            frame.image.as_raw(),
+            width,
+            height,
+            timestamp as i32,
+        ));
+        timestamp += frame.delay_ms;
+    }
+
+    let encoded = encoder
+        .try_encode()
+        .map_err(|e| AppError::image(format!("Failed to encode animated WebP: {:?}", e)))?;
+
+    let mut bytes = encoded.to_vec();
+    set_last_frame_duration(&mut bytes, timestamp);
+    Ok(bytes)
+}
+
+/// Animation bit of a WebP extended (VP8X) header.
+fn has_animation_flag(bytes: &[u8]) -> bool {
+    bytes.get(12..16) == Some(b"VP8X") && bytes.get(20).is_some_and(|flags| flags & 0x02 != 0)
+}
+
+/// The webp crate closes the animation at timestamp 0, so libwebp guesses the
+/// last frame's duration from the average of the others. Rewrite it so the
+/// animation ends at `total_ms` like the source.
+fn set_last_frame_duration(webp: &mut [u8], total_ms: u32) {
+    let mut offset = 12;
+    let mut frames = Vec::new();
+
+    // RIFF chunks: fourcc, little endian size, payload padded to an even length
+    while let Some(header) = webp.get(offset..offset + 8) {
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        if &header[..4] == b"ANMF" {
+            frames.push(offset + 8);
+        }
+        offset += 8 + size + size % 2;
+    }
+
+    // ANMF payload: x, y, width - 1, height - 1 and duration, 24 bits each
+    let duration = |webp: &[u8], payload: usize| {
+        webp.get(payload + 12..payload + 15)
+            .map(|d| u32::from_le_bytes([d[0], d[1], d[2], 0]))
+    };
+
+    let Some((&last, earlier)) = frames.split_last() else {
+        return;
+    };
+    let elapsed: u32 = earlier
+        .iter()
+        .filter_map(|&payload| duration(webp, payload))
+        .sum();
+
+    if let Some(field) = webp.get_mut(last + 12..last + 15) {
+        let remaining = total_ms.saturating_sub(elapsed).min(0xFF_FFFF);
+        field.copy_from_slice(&remaining.to_le_bytes()[..3]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Delay, Frame, Rgba};
+
+    fn frame(pixel: [u8; 4], delay_ms: u32) -> Frame {
+        Frame::from_parts(
+            RgbaImage::from_pixel(16, 8, Rgba(pixel)),
+            0,
+            0,
+            Delay::from_numer_denom_ms(delay_ms, 1),
+        )
+    }
+
+    fn gif(delays: &[u32]) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        let colours = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255]];
+        image::codecs::gif::GifEncoder::new(&mut buffer)
+            .encode_frames(
+                delays
+                    .iter()
+                    .zip(colours.iter().cycle())
+                    .map(|(&delay, &colour)| frame(colour, delay)),
+            )
+            .unwrap();
+        buffer
+    }
+
+    fn delays(frames: &[AnimationFrame]) -> Vec<u32> {
+        frames.iter().map(|frame| frame.delay_ms).collect()
+    }
+
+    #[test]
+    fn detects_animated_gif_and_webp() {
+        let frames = decode_frames(&gif(&[200, 300]), Some(ImageFormat::Gif))
+            .unwrap()
+            .unwrap();
+        assert_eq!(delays(&frames), [200, 300]);
+        assert_eq!(frames[0].image.dimensions(), (16, 8));
+
+        let webp = encode_webp(&frames, true, 80).unwrap();
+        assert!(has_animation_flag(&webp));
+        let frames = decode_frames(&webp, Some(ImageFormat::WebP))
+            .unwrap()
+            .unwrap();
+        assert_eq!(frames.len(), 2);
+    }
+
+    #[test]
+    fn still_images_are_not_animations() {
+        assert!(decode_frames(&gif(&[200]), Some(ImageFormat::Gif))
+            .unwrap()
+            .is_none());
+
+        let still = webp::Encoder::from_rgba(&[0; 16 * 8 * 4], 16, 8)
+            .encode_lossless()
+            .to_vec();
+        assert!(!has_animation_flag(&still));
+        assert!(decode_frames(&still, Some(ImageFormat::WebP))
+            .unwrap()
+            .is_none());
+        assert!(decode_frames(&gif(&[200, 300]), Some(ImageFormat::Png))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn reencoding_keeps_frames_and_delays() {
+        let frames = decode_frames(&gif(&[120, 250, 80]), Some(ImageFormat::Gif))
+            .unwrap()
+            .unwrap();
+        let webp = encode_webp(&resize_frames(frames, 8), false, 80).unwrap();
+
+        let frames = decode_frames(&webp, Some(ImageFormat::WebP))
+            .unwrap()
+            .unwrap();
+        assert_eq!(delays(&frames), [120, 250, 80]);
+        assert_eq!(frames[0].image.dimensions(), (8, 4));
+    }
+
+    #[test]
+    fn zero_and_ten_ms_gif_delays_play_at_browser_speed() {
+        let frames = decode_frames(&gif(&[0, 10, 20]), Some(ImageFormat::Gif))
+            .unwrap()
+            .unwrap();
+        assert_eq!(delays(&frames), [100, 100, 20]);
+    }
+
+    #[test]
+    fn last_frame_duration_closes_the_animation_at_the_total() {
+        let frames = decode_frames(&gif(&[100, 400]), Some(ImageFormat::Gif))
+            .unwrap()
+            .unwrap();
+        let mut webp = encode_webp(&frames, true, 80).unwrap();
+
+        set_last_frame_duration(&mut webp, 1000);
+        let frames = decode_frames(&webp, Some(ImageFormat::WebP))
+            .unwrap()
+            .unwrap();
+        assert_eq!(delays(&frames), [100, 900]);
+
+        // shorter than the earlier frames: the last one gets no time at all
+        set_last_frame_duration(&mut webp, 50);
+        let frames = decode_frames(&webp, Some(ImageFormat::WebP))
+            .unwrap()
+            .unwrap();
+        assert_eq!(delays(&frames), [100, 0]);
+
+        // not a WebP, or cut off in a header: nothing to rewrite
+        let mut truncated = webp[..30].to_vec();
+        set_last_frame_duration(&mut truncated, 1000);
+        assert_eq!(truncated, webp[..30]);
+    }
+}
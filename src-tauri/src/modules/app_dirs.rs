@@ -0,0 +1,23 @@
+use crate::error::{AppError, ErrorCode};
+use std::fs;
+use std::path::PathBuf;
+
+/// Must match `tauri.bundle.identifier` so the CLI and the app share data.
+pub const APP_IDENTIFIER: &str = "com.optibridge.app";
+
+/// The data directory Tauri resolves for this app, computed without an `AppHandle`.
+pub fn default_data_dir() -> Option<PathBuf> {
+    dirs_next::data_dir().map(|dir| dir.join(APP_IDENTIFIER))
+}
+
+/// Resolves and creates the app data directory of a running Tauri app.
+pub fn app_data_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    let app_dir = app_handle
+        .path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| AppError::new(ErrorCode::Io, "Failed to get app data directory"))?;
+
+    fs::create_dir_all(&app_dir).map_err(|e| AppError::io("Failed to create app directory", e))?;
+
+    Ok(app_dir)
+}
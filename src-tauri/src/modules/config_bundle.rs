@@ -0,0 +1,177 @@
+//! Portable copy of the settings for sharing with teammates: `config.json`
+//! plus, when the exporter picks a passphrase, the provider secrets sealed
+//! with it the same way as in the secrets vault. Without one they are left out.
+
+use crate::error::{AppError, ErrorCode};
+use crate::models::Config;
+use crate::modules::config_schema;
+use crate::modules::secret_store::{SealedSecrets, REDACTED};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+pub const BUNDLE_FORMAT: &str = "opti-bridge-config";
+
+/// Key the UI's passphrase input and passphrase errors share.
+const PASSPHRASE_FIELD: &str = "bundle_passphrase";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigBundle {
+    pub format: String,
+    pub exported_at: String,
+    /// The settings as kept in `config.json`, without secrets.
+    pub config: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secrets: Option<SealedSecrets>,
+}
+
+impl ConfigBundle {
+    /// Bundles `config`, whose secrets are filled in. They are sealed with
+    /// `passphrase` when given and dropped otherwise.
+    pub fn new(config: &Config, passphrase: Option<&str>) -> Result<Self, AppError> {
+        let mut config = config.clone();
+        let mut secrets = BTreeMap::new();
+        for (key, value) in config.secrets_mut() {
+            if !value.is_empty() {
+                secrets.insert(key, std::mem::take(value));
+            }
+        }
+
+        let secrets = match passphrase {
+            Some("") => return Err(passphrase_error("The bundle passphrase cannot be empty")),
+            Some(passphrase) => Some(SealedSecrets::seal(passphrase, &secrets)?),
+            None => None,
+        };
+
+        Ok(Self {
+            format: BUNDLE_FORMAT.to_string(),
+            exported_at: chrono::Utc::now().to_rfc3339(),
+            config: serde_json::to_value(&config)
+                .map_err(|e| AppError::internal(format!("Failed to serialize config: {}", e)))?,
+            secrets,
+        })
+    }
+
+    pub fn parse(content: &str) -> Result<Self, AppError> {
+        let bundle: Self = serde_json::from_str(content)
+            .map_err(|e| invalid(format!("Invalid config bundle: {}", e)))?;
+        if bundle.format != BUNDLE_FORMAT {
+            return Err(invalid(format!(
+                "Not a config bundle (format \"{}\")",
+                bundle.format
+            )));
+        }
+
+        Ok(bundle)
+    }
+
+    pub fn to_json(&self) -> Result<String, AppError> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::internal(format!("Failed to serialize bundle: {}", e)))
+    }
+
+    /// The bundled settings, migrated to the current config version, and the
+    /// number of secrets taken from the bundle. Secrets it does not hold read
+    /// `REDACTED`, so saving the result keeps any already stored under the same key.
+    pub fn into_config(self, passphrase: Option<&str>) -> Result<(Config, usize), AppError> {
+        let mut secrets = match (&self.secrets, passphrase) {
+            (None, _) => BTreeMap::new(),
+            (Some(_), None) => return Err(passphrase_error(
+                "This bundle's secrets are encrypted; enter the passphrase it was exported with",
+            )),
+            (Some(sealed), Some(passphrase)) => sealed
+                .open(passphrase)
+                .map_err(|e| invalid(format!("Invalid config bundle: {}", e)))?
+                .ok_or_else(|| passphrase_error("Wrong bundle passphrase"))?,
+        };
+
+        let (mut config, _) = config_schema::upgrade(self.config)?;
+        let mut imported = 0;
+        for (key, value) in config.secrets_mut() {
+            match secrets.remove(&key) {
+                Some(secret) => {
+                    *value = secret;
+                    imported += 1;
+                }
+                None => *value = REDACTED.to_string(),
+            }
+        }
+
+        Ok((config, imported))
+    }
+}
+
+fn invalid(message: impl Into<String>) -> AppError {
+    AppError::new(ErrorCode::InvalidConfig, message)
+}
+
+fn passphrase_error(message: &str) -> AppError {
+    invalid(message).with_fields(vec![PASSPHRASE_FIELD.to_string()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        Config {
+            cloudinary_cloud_name: "demo".to_string(),
+            cloudinary_api_secret: "cloudinary secret".to_string(),
+            s3_secret_access_key: "s3 secret".to_string(),
+            ..Config::default()
+        }
+    }
+
+    fn round_trip(bundle: ConfigBundle) -> ConfigBundle {
+        ConfigBundle::parse(&bundle.to_json().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn round_trip_with_sealed_secrets() {
+        let bundle = round_trip(ConfigBundle::new(&config(), Some("team passphrase")).unwrap());
+        assert_eq!(bundle.config["cloudinary_api_secret"], "");
+        assert!(!bundle.to_json().unwrap().contains("s3 secret"));
+
+        let (imported, count) = bundle.into_config(Some("team passphrase")).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(imported.cloudinary_cloud_name, "demo");
+        assert_eq!(imported.cloudinary_api_secret, "cloudinary secret");
+        assert_eq!(imported.s3_secret_access_key, "s3 secret");
+        // Not in the bundle, so saving keeps whatever is stored
+        assert_eq!(imported.r2_secret_access_key, REDACTED);
+    }
+
+    #[test]
+    fn round_trip_without_secrets() {
+        let bundle = round_trip(ConfigBundle::new(&config(), None).unwrap());
+        assert!(bundle.secrets.is_none());
+
+        let (imported, count) = bundle.into_config(None).unwrap();
+        assert_eq!(count, 0);
+        assert_eq!(imported.cloudinary_cloud_name, "demo");
+        assert_eq!(imported.cloudinary_api_secret, REDACTED);
+    }
+
+    #[test]
+    fn sealed_secrets_need_the_right_passphrase() {
+        let bundle = ConfigBundle::new(&config(), Some("team passphrase")).unwrap();
+
+        for passphrase in [None, Some("wrong")] {
+            let error = bundle.clone().into_config(passphrase).unwrap_err();
+            assert_eq!(error.code, ErrorCode::InvalidConfig);
+            assert_eq!(error.details.unwrap().fields, vec![PASSPHRASE_FIELD]);
+        }
+
+        let error = ConfigBundle::new(&config(), Some("")).unwrap_err();
+        assert_eq!(error.details.unwrap().fields, vec![PASSPHRASE_FIELD]);
+    }
+
+    #[test]
+    fn parse_rejects_other_files() {
+        let error =
+            ConfigBundle::parse(r#"{"format": "something-else", "exported_at": "", "config": {}}"#)
+                .unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidConfig);
+        assert!(ConfigBundle::parse("{}").is_err());
+    }
+}
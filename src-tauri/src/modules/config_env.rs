@@ -0,0 +1,395 @@
+//! `OPTIBRIDGE_*` environment variables layered over `config.json`, so CI and
+//! the CLI can pass credentials without writing them to disk. A variable is
+//! named after the `Config` key in upper case, such as
+//! `OPTIBRIDGE_R2_SECRET_ACCESS_KEY` or `OPTIBRIDGE_SETTINGS_MAX_WIDTH`. An
+//! account's field is `OPTIBRIDGE_ACCOUNTS__<ACCOUNT ID>__<FIELD>`, with the
+//! id in upper case and anything but letters and digits written as `_`; the
+//! field must be one of the provider's, and toggles take `true` or `false`.
+//!
+//! Overrides apply when the config is loaded and are never saved back.
+
+use crate::error::{AppError, ErrorCode};
+use crate::models::{Config, EnvOverride, ProviderFieldKind};
+use crate::modules::config_schema;
+use crate::uploaders::registry::UploaderRegistry;
+use serde_json::{Map, Value};
+
+pub const ENV_PREFIX: &str = "OPTIBRIDGE_";
+
+const ACCOUNTS_PREFIX: &str = "ACCOUNTS__";
+
+/// Keys holding structure rather than a setting.
+const FIXED_KEYS: [&str; 3] = ["version", "accounts", "profiles"];
+
+/// A variable matched to the setting it replaces.
+struct Match {
+    variable: String,
+    raw: String,
+    target: Target,
+}
+
+enum Target {
+    Key(String),
+    Account {
+        id: String,
+        field: String,
+        kind: ProviderFieldKind,
+    },
+}
+
+impl Match {
+    fn field(&self) -> String {
+        match &self.target {
+            Target::Key(key) => key.clone(),
+            Target::Account { id, field, .. } => format!("{}/{}", id, field),
+        }
+    }
+
+    /// The variable's text as the JSON the setting holds, or `None` when it
+    /// cannot be one. Account fields follow their provider field's kind. Other
+    /// strings are taken as they are; anything else is parsed, so `true`,
+    /// `2048` and `["webp"]` work.
+    fn value(&self, current: &Value) -> Option<Value> {
+        match (&self.target, current) {
+            (
+                Target::Account {
+                    kind: ProviderFieldKind::Toggle,
+                    ..
+                },
+                _,
+            ) => self.raw.trim().parse().ok().map(Value::Bool),
+            (Target::Account { .. }, _) | (Target::Key(_), Value::String(_)) => {
+                Some(Value::from(self.raw.as_str()))
+            }
+            (Target::Key(_), _) => Some(
+                serde_json::from_str(&self.raw).unwrap_or_else(|_| Value::from(self.raw.as_str())),
+            ),
+        }
+    }
+
+    fn error(&self, reason: impl std::fmt::Display) -> (String, String) {
+        let message = format!(
+            "{} does not fit {}: {}",
+            self.variable,
+            self.field(),
+            reason
+        );
+        (self.field(), message)
+    }
+
+    fn info(&self) -> EnvOverride {
+        EnvOverride {
+            variable: self.variable.clone(),
+            field: self.field(),
+        }
+    }
+}
+
+/// The variables currently overriding settings of `config`.
+pub fn overrides(config: &Config) -> Vec<EnvOverride> {
+    find(config).iter().map(Match::info).collect()
+}
+
+/// `config` with every override applied. A value that does not fit its
+/// setting, or that `config_schema::validate` refuses, is an error naming
+/// the variable.
+pub fn apply(config: Config) -> Result<Config, AppError> {
+    let matches = find(&config);
+    if matches.is_empty() {
+        return Ok(config);
+    }
+
+    let mut fields = to_fields(&config)?;
+    for found in &matches {
+        if let Some(slot) = slot_mut(&mut fields, &found.target, true) {
+            *slot = found
+                .value(slot)
+                .ok_or_else(|| invalid(vec![found.error("expected true or false")]))?;
+        }
+
+        if let Err(e) = serde_json::from_value::<Config>(Value::Object(fields.clone())) {
+            return Err(invalid(vec![found.error(e)]));
+        }
+    }
+
+    let config = from_fields(fields)?;
+
+    // Only the overridden values are checked; the stored ones were on saving
+    if let Err(e) = config_schema::validate(&config) {
+        let field_errors = e
+            .details
+            .map(|details| details.field_errors)
+            .unwrap_or_default();
+        let errors: Vec<(String, String)> = matches
+            .iter()
+            .filter_map(|found| Some(found.error(field_errors.get(&found.field())?)))
+            .collect();
+        if !errors.is_empty() {
+            return Err(invalid(errors));
+        }
+    }
+
+    Ok(config)
+}
+
+/// Puts the `stored` value back wherever `config` still holds the one an
+/// override supplied, so saving never writes an override to disk. Settings
+/// changed since loading are kept.
+pub fn restore(config: Config, stored: &Config) -> Result<Config, AppError> {
+    let matches = find(&config);
+    if matches.is_empty() {
+        return Ok(config);
+    }
+
+    let mut fields = to_fields(&config)?;
+    let mut stored_fields = to_fields(stored)?;
+    for found in &matches {
+        let Some(slot) = slot_mut(&mut fields, &found.target, false) else {
+            continue;
+        };
+        if found.value(slot).as_ref() != Some(slot) {
+            continue;
+        }
+
+        // An account field the stored config does not have is left empty
+        *slot = slot_mut(&mut stored_fields, &found.target, false)
+            .map_or_else(|| Value::from(""), |previous| previous.clone());
+    }
+
+    from_fields(fields)
+}
+
+fn find(config: &Config) -> Vec<Match> {
+    let Ok(fields) = to_fields(config) else {
+        return Vec::new();
+    };
+    let registry = UploaderRegistry::with_defaults();
+
+    std::env::vars()
+        .filter_map(|(variable, raw)| {
+            let name = variable.strip_prefix(ENV_PREFIX)?;
+            let target = match name.strip_prefix(ACCOUNTS_PREFIX) {
+                Some(rest) => {
+                    let (id, field) = rest.split_once("__")?;
+                    let account = config
+                        .accounts
+                        .iter()
+                        .find(|account| env_name(&account.id) == id)?;
+                    let field = registry
+                        .info(&account.provider)?
+                        .fields
+                        .iter()
+                        .find(|known| known.key.to_uppercase() == field)?;
+                    Target::Account {
+                        id: account.id.clone(),
+                        field: field.key.clone(),
+                        kind: field.kind,
+                    }
+                }
+                None => {
+                    let key = fields.keys().find(|key| {
+                        !FIXED_KEYS.contains(&key.as_str()) && key.to_uppercase() == name
+                    })?;
+                    Target::Key(key.clone())
+                }
+            };
+
+            Some(Match {
+                variable,
+                raw,
+                target,
+            })
+        })
+        .collect()
+}
+
+/// How an account id appears in a variable name.
+fn env_name(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// The value a target points at. With `create`, an account field not set
+/// yet is added as `null`.
+fn slot_mut<'a>(
+    fields: &'a mut Map<String, Value>,
+    target: &Target,
+    create: bool,
+) -> Option<&'a mut Value> {
+    match target {
+        Target::Key(key) => fields.get_mut(key),
+        Target::Account { id, field, .. } => {
+            let account_fields = fields
+                .get_mut("accounts")?
+                .as_array_mut()?
+                .iter_mut()
+                .find(|account| account["id"] == id.as_str())?
+                .get_mut("fields")?
+                .as_object_mut()?;
+
+            if create {
+                Some(account_fields.entry(field.clone()).or_insert(Value::Null))
+            } else {
+                account_fields.get_mut(field)
+            }
+        }
+    }
+}
+
+/// Names every variable in `errors`, which are keyed by the field each overrides.
+fn invalid(errors: Vec<(String, String)>) -> AppError {
+    let message = errors
+        .iter()
+        .map(|(_, message)| message.as_str())
+        .collect::<Vec<_>>()
+        .join("; ");
+    AppError::new(ErrorCode::InvalidConfig, message).with_field_errors(errors)
+}
+
+fn to_fields(config: &Config) -> Result<Map<String, Value>, AppError> {
+    match serde_json::to_value(config) {
+        Ok(Value::Object(fields)) => Ok(fields),
+        _ => Err(AppError::internal("Failed to serialize config")),
+    }
+}
+
+fn from_fields(fields: Map<String, Value>) -> Result<Config, AppError> {
+    serde_json::from_value(Value::Object(fields))
+        .map_err(|e| AppError::internal(format!("Failed to apply environment overrides: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ProviderAccount;
+    use serde_json::json;
+
+    // Variables are shared by every test thread, so each test uses its own
+    // account id and only overrides that account's fields
+
+    fn config_with(id: &str, provider: &str, fields: Value) -> Config {
+        let mut config = Config::default();
+        config.accounts.push(ProviderAccount {
+            id: id.to_string(),
+            name: id.to_string(),
+            provider: provider.to_string(),
+            fields: fields.as_object().cloned().unwrap_or_default(),
+        });
+        config
+    }
+
+    fn set(id: &str, field: &str, value: &str) {
+        std::env::set_var(
+            format!(
+                "{}{}{}__{}",
+                ENV_PREFIX,
+                ACCOUNTS_PREFIX,
+                env_name(id),
+                field
+            ),
+            value,
+        );
+    }
+
+    #[test]
+    fn account_overrides_follow_the_provider_field_kind() {
+        let id = "env-kinds";
+        set(id, "CLOUDINARY_API_KEY", "123456789");
+        set(id, "CLOUDINARY_CLOUD_NAME", "true");
+        set(id, "NOT_A_FIELD", "ignored");
+        let config = config_with(id, "cloudinary", json!({}));
+
+        let applied = apply(config.clone()).unwrap();
+        let fields = &applied.accounts[0].fields;
+        assert_eq!(fields["cloudinary_api_key"], json!("123456789"));
+        assert_eq!(fields["cloudinary_cloud_name"], json!("true"));
+        assert!(!fields.contains_key("not_a_field"));
+
+        let mut overridden: Vec<String> = overrides(&config)
+            .into_iter()
+            .map(|found| found.field)
+            .filter(|field| field.starts_with(id))
+            .collect();
+        overridden.sort();
+        assert_eq!(
+            overridden,
+            vec![
+                "env-kinds/cloudinary_api_key",
+                "env-kinds/cloudinary_cloud_name"
+            ]
+        );
+    }
+
+    #[test]
+    fn toggles_take_true_or_false() {
+        let id = "env-toggle";
+        set(id, "S3_FORCE_PATH_STYLE", " true ");
+        let applied = apply(config_with(id, "s3", json!({}))).unwrap();
+        assert_eq!(
+            applied.accounts[0].fields["s3_force_path_style"],
+            json!(true)
+        );
+
+        let id = "env-bad-toggle";
+        set(id, "S3_FORCE_PATH_STYLE", "yes");
+        let error = apply(config_with(id, "s3", json!({}))).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidConfig);
+        assert_eq!(
+            error.details.unwrap().fields,
+            vec!["env-bad-toggle/s3_force_path_style"]
+        );
+    }
+
+    #[test]
+    fn apply_validates_overridden_values() {
+        let id = "env-invalid";
+        set(id, "S3_PUBLIC_DOMAIN", "http://img.example.com");
+        // Stored values are not the variable's fault and are not reported
+        let config = config_with(id, "s3", json!({ "s3_endpoint": "not a url" }));
+
+        let error = apply(config).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidConfig);
+        assert!(
+            error.message.starts_with(
+                "OPTIBRIDGE_ACCOUNTS__ENV_INVALID__S3_PUBLIC_DOMAIN does not fit env-invalid/s3_public_domain: must be an https:// URL"
+            ),
+            "{}",
+            error.message
+        );
+        assert_eq!(
+            error.details.unwrap().fields,
+            vec!["env-invalid/s3_public_domain"]
+        );
+    }
+
+    #[test]
+    fn restore_puts_stored_values_back() {
+        let id = "env-restore";
+        set(id, "R2_BUCKET_NAME", "from-env");
+        set(id, "R2_ACCESS_KEY_ID", "from-env");
+        set(id, "R2_ENDPOINT", "https://env.example.com");
+        let stored = config_with(
+            id,
+            "r2",
+            json!({ "r2_bucket_name": "stored", "r2_access_key_id": "stored" }),
+        );
+
+        let mut edited = apply(stored.clone()).unwrap();
+        // Changed in Settings after loading, so it is saved
+        edited.accounts[0].fields["r2_access_key_id"] = json!("edited");
+
+        let restored = restore(edited, &stored).unwrap();
+        let fields = &restored.accounts[0].fields;
+        assert_eq!(fields["r2_bucket_name"], json!("stored"));
+        assert_eq!(fields["r2_access_key_id"], json!("edited"));
+        // Not stored before, so nothing of the override is written
+        assert_eq!(fields["r2_endpoint"], json!(""));
+    }
+}
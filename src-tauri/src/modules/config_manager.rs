@@ -1,46 +1,473 @@
+use super::app_dirs;
+use super::config_bundle::ConfigBundle;
+use super::config_env;
+use super::config_schema;
+use super::secret_store::{self, SecretStore, REDACTED, VAULT_FILE};
+use crate::error::{AppError, ErrorCode};
 use crate::models::Config;
+use crate::state::AppState;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::PoisonError;
+use tauri::Manager;
 
+/// Reads and writes `config.json`. Secrets are kept out of the file: they go to
+/// the `SecretStore` and are filled back in on load. Older versions of the
+/// file are migrated on load; see `config_schema`. `OPTIBRIDGE_*` variables
+/// override what is stored; see `config_env`.
 pub struct ConfigManager {
     config_path: PathBuf,
+    secrets: SecretStore,
 }
 
 impl ConfigManager {
-    pub fn new(app_handle: &tauri::AppHandle) -> Result<Self, String> {
-        let app_dir = app_handle
-            .path_resolver()
-            .app_data_dir()
-            .ok_or("Failed to get app data directory")?;
+    pub fn new(app_handle: &tauri::AppHandle) -> Result<Self, AppError> {
+        let app_dir = app_dirs::app_data_dir(app_handle)?;
+        let passphrase = app_handle
+            .state::<AppState>()
+            .vault_passphrase
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
 
-        fs::create_dir_all(&app_dir)
-            .map_err(|e| format!("Failed to create app directory: {}", e))?;
+        Ok(Self::with_passphrase(
+            &app_dir.join("config.json"),
+            passphrase.or_else(secret_store::env_passphrase),
+        ))
+    }
+
+    /// Uses `config_path` directly; the file does not need to exist yet. A
+    /// vault is unlocked with `OPTIBRIDGE_VAULT_PASSPHRASE`.
+    pub fn open(config_path: &Path) -> Self {
+        Self::with_passphrase(config_path, secret_store::env_passphrase())
+    }
+
+    pub fn with_passphrase(config_path: &Path, passphrase: Option<String>) -> Self {
+        Self {
+            config_path: config_path.to_path_buf(),
+            secrets: SecretStore::detect(config_path.with_file_name(VAULT_FILE), passphrase),
+        }
+    }
+
+    pub fn secrets(&self) -> &SecretStore {
+        &self.secrets
+    }
+
+    /// Unlocks the vault, then moves any secrets still in `config.json` into it.
+    pub fn unlock_vault(&mut self, passphrase: String) -> Result<(), AppError> {
+        self.secrets.unlock(passphrase)?;
+        self.load_stored().map(|_| ())
+    }
+
+    /// The config in effect: what is stored, with environment overrides applied.
+    pub fn load_config(&self) -> Result<Config, AppError> {
+        config_env::apply(self.load_stored()?)
+    }
+
+    /// The stored config with secrets filled in. While the vault is locked
+    /// they are left empty, apart from any not yet moved out of the file.
+    /// Fails when the secret store cannot be read, or written while secrets
+    /// are still waiting to be moved out of the file.
+    pub fn load_stored(&self) -> Result<Config, AppError> {
+        let mut config = self.read_file()?;
+
+        if config
+            .secrets_mut()
+            .iter()
+            .any(|(_, value)| !value.is_empty())
+        {
+            self.migrate_secrets(&config).map_err(|e| AppError {
+                message: format!("Failed to move secrets out of config.json: {}", e.message),
+                ..e
+            })?;
+        }
 
-        let config_path = app_dir.join("config.json");
+        if !self.secrets.is_locked() {
+            let mut stored = self.secrets.read(&secret_keys(&mut config))?;
+            for (key, value) in config.secrets_mut() {
+                if let Some(secret) = stored.remove(&key) {
+                    *value = secret;
+                }
+            }
+        }
 
-        Ok(Self { config_path })
+        Ok(config)
     }
 
-    pub fn load_config(&self) -> Result<Config, String> {
+    /// The config as handed to the webview: every secret that is set reads
+    /// `REDACTED`, so none leaves the backend.
+    pub fn load_redacted(&self) -> Result<Config, AppError> {
+        let mut config = self.load_config()?;
+        // Loading fills in every stored secret, unless the vault is locked
+        let stored = if self.secrets.is_locked() {
+            self.secrets.stored_keys(&secret_keys(&mut config))?
+        } else {
+            Vec::new()
+        };
+
+        for (key, value) in config.secrets_mut() {
+            if !value.is_empty() || stored.contains(&key) {
+                *value = REDACTED.to_string();
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Writes `config`, sending changed secrets to the secret store. A secret
+    /// reading `REDACTED` is left as it is; those of removed accounts are deleted.
+    /// `REDACTED` under a key with nothing stored, as after renaming an
+    /// account, is refused naming the field, since its value would be lost.
+    /// Nothing is written unless every field passes `config_schema::validate`,
+    /// and values that came from environment overrides are not written at all.
+    pub fn save_config(&self, config: &Config) -> Result<(), AppError> {
+        config_schema::validate(config)?;
+
+        let mut loaded = self.load_stored()?;
+        let mut config = config_env::restore(config.clone(), &loaded)?;
+        let mut current: BTreeMap<String, String> = loaded
+            .secrets_mut()
+            .into_iter()
+            .map(|(key, value)| (key, value.clone()))
+            .collect();
+        let mut changes = Vec::new();
+        let mut missing = Vec::new();
+        // A locked vault loads every secret empty; ask it which ones it has
+        let locked_keys = if self.secrets.is_locked() {
+            self.secrets.stored_keys(&secret_keys(&mut config))?
+        } else {
+            Vec::new()
+        };
+
+        for (key, value) in config.secrets_mut() {
+            let previous = current.remove(&key).unwrap_or_default();
+            if value == REDACTED {
+                if previous.is_empty() && !locked_keys.contains(&key) {
+                    missing.push((key, "Enter this secret again".to_string()));
+                    continue;
+                }
+                value.clone_from(&previous);
+            }
+            if *value != previous {
+                changes.push((key, value.clone()));
+            }
+        }
+        changes.extend(
+            current
+                .into_iter()
+                .filter(|(_, value)| !value.is_empty())
+                .map(|(key, _)| (key, String::new())),
+        );
+
+        if !missing.is_empty() {
+            return Err(AppError::new(
+                ErrorCode::InvalidConfig,
+                "Some secrets have nothing stored under their account anymore",
+            )
+            .with_field_errors(missing));
+        }
+
+        if self.secrets.is_locked() {
+            // Secrets not yet moved out of the file stay there until the vault is unlocked
+            if let Some((key, _)) = changes.first() {
+                return Err(AppError::new(
+                    ErrorCode::VaultLocked,
+                    "Unlock the secrets vault before changing secrets",
+                )
+                .with_fields(vec![key.clone()]));
+            }
+        } else {
+            self.secrets.write(&changes)?;
+            clear_secrets(&mut config);
+        }
+
+        self.write_file(&config)
+    }
+
+    /// The stored settings as a bundle, without environment overrides. Secrets
+    /// are included, sealed with `passphrase`, only when one is given.
+    pub fn export_bundle(&self, passphrase: Option<&str>) -> Result<ConfigBundle, AppError> {
+        if passphrase.is_some() && self.secrets.is_locked() {
+            return Err(AppError::new(
+                ErrorCode::VaultLocked,
+                "Unlock the secrets vault before exporting secrets",
+            )
+            .with_fields(vec!["vault_passphrase".to_string()]));
+        }
+
+        ConfigBundle::new(&self.load_stored()?, passphrase)
+    }
+
+    /// Replaces the settings with those of `bundle`, returning how many
+    /// secrets it supplied. Secrets it leaves out keep their stored values.
+    pub fn import_bundle(
+        &self,
+        bundle: ConfigBundle,
+        passphrase: Option<&str>,
+    ) -> Result<usize, AppError> {
+        let (config, imported) = bundle.into_config(passphrase)?;
+        self.save_config(&config)?;
+        Ok(imported)
+    }
+
+    /// Moves secrets still stored in `config.json`, as written by older
+    /// versions, into the secret store and rewrites the file without them.
+    /// Postponed while the vault is locked.
+    fn migrate_secrets(&self, config: &Config) -> Result<(), AppError> {
+        if self.secrets.is_locked() {
+            return Ok(());
+        }
+
+        let mut config = config.clone();
+        let changes: Vec<(String, String)> = config
+            .secrets_mut()
+            .into_iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(key, value)| (key, value.clone()))
+            .collect();
+        self.secrets.write(&changes)?;
+
+        clear_secrets(&mut config);
+        self.write_file(&config)
+    }
+
+    fn read_file(&self) -> Result<Config, AppError> {
         if !self.config_path.exists() {
             return Ok(Config::default());
         }
 
         let content = fs::read_to_string(&self.config_path)
-            .map_err(|e| format!("Failed to read config: {}", e))?;
+            .map_err(|e| AppError::io("Failed to read config", e))?;
+
+        let (config, migrated_from) = config_schema::parse(&content)?;
+        if let Some(version) = migrated_from {
+            // Kept so the file can be restored if a migration goes wrong. Secrets
+            // are left out: they are about to move to the secret store
+            let backup = self
+                .config_path
+                .with_extension(format!("json.v{}.bak", version));
+            fs::write(&backup, without_secrets(&content)?)
+                .map_err(|e| AppError::io("Failed to back up config before migrating", e))?;
+            self.write_file(&config)?;
+        }
 
-        serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse config: {}", e))
+        Ok(config)
     }
 
-    pub fn save_config(&self, config: &Config) -> Result<(), String> {
-        let content = serde_json::to_string_pretty(config)
-            .map_err(|e| format!("Failed to serialize config: {}", e))?;
+    /// Writes a temporary file next to `config.json` and renames it over the
+    /// original, so a crash or full disk never leaves a half-written config.
+    fn write_file(&self, config: &Config) -> Result<(), AppError> {
+        let mut config = config.clone();
+        config.version = config_schema::latest_version();
+        let content = serde_json::to_string_pretty(&config)
+            .map_err(|e| AppError::internal(format!("Failed to serialize config: {}", e)))?;
+
+        if let Some(parent) = self.config_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| AppError::io("Failed to create config directory", e))?;
+        }
+
+        let temp_path = self.config_path.with_extension("json.tmp");
+        let written = fs::File::create(&temp_path).and_then(|mut file| {
+            file.write_all(content.as_bytes())?;
+            file.sync_all()
+        });
+        if let Err(e) = written {
+            let _ = fs::remove_file(&temp_path);
+            return Err(AppError::io("Failed to write config", e));
+        }
+
+        fs::rename(&temp_path, &self.config_path).map_err(|e| {
+            let _ = fs::remove_file(&temp_path);
+            AppError::io("Failed to replace config", e)
+        })
+    }
+}
+
+fn secret_keys(config: &mut Config) -> Vec<String> {
+    config
+        .secrets_mut()
+        .into_iter()
+        .map(|(key, _)| key)
+        .collect()
+}
+
+fn clear_secrets(config: &mut Config) {
+    for (_, value) in config.secrets_mut() {
+        value.clear();
+    }
+}
 
-        fs::write(&self.config_path, content)
-            .map_err(|e| format!("Failed to write config: {}", e))?;
+/// `config.json` content of any version with every secret emptied, the
+/// way `clear_secrets` leaves a loaded config.
+fn without_secrets(content: &str) -> Result<String, AppError> {
+    fn clear(fields: &mut Map<String, Value>) {
+        for field in Config::SECRET_FIELDS {
+            if let Some(value @ Value::String(_)) = fields.get_mut(field) {
+                *value = Value::from("");
+            }
+        }
+    }
 
-        Ok(())
+    let mut value: Value = serde_json::from_str(content)
+        .map_err(|e| AppError::internal(format!("Failed to parse config: {}", e)))?;
+    if let Some(fields) = value.as_object_mut() {
+        clear(fields);
+        let accounts = fields.get_mut("accounts").and_then(Value::as_array_mut);
+        for account in accounts.into_iter().flatten() {
+            if let Some(account_fields) = account.get_mut("fields").and_then(Value::as_object_mut) {
+                clear(account_fields);
+            }
+        }
     }
+
+    serde_json::to_string_pretty(&value)
+        .map_err(|e| AppError::internal(format!("Failed to serialize config: {}", e)))
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backups_leave_secrets_out() {
+        let content = r#"{
+            "version": 0,
+            "cloudinary_api_key": "123456",
+            "cloudinary_api_secret": "shh",
+            "accounts": [{
+                "id": "work",
+                "name": "Work",
+                "provider": "r2",
+                "fields": { "r2_access_key_id": "AKIA", "r2_secret_access_key": "shh" }
+            }]
+        }"#;
+
+        let backup: Value = serde_json::from_str(&without_secrets(content).unwrap()).unwrap();
+        assert_eq!(backup["version"], 0);
+        assert_eq!(backup["cloudinary_api_key"], "123456");
+        assert_eq!(backup["cloudinary_api_secret"], "");
+        assert_eq!(backup["accounts"][0]["fields"]["r2_access_key_id"], "AKIA");
+        assert_eq!(backup["accounts"][0]["fields"]["r2_secret_access_key"], "");
+    }
+
+    fn temp_manager() -> ConfigManager {
+        let dir = std::env::temp_dir().join(format!("opti-bridge-config-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        ConfigManager {
+            config_path: dir.join("config.json"),
+            secrets: SecretStore::Vault {
+                path: dir.join(VAULT_FILE),
+                passphrase: Some("correct horse".to_string()),
+            },
+        }
+    }
+
+    fn r2_account(id: &str, secret: &str) -> crate::models::ProviderAccount {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": "Work",
+            "provider": "r2",
+            "fields": { "r2_access_key_id": "AKIA", "r2_secret_access_key": secret }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn renamed_account_must_enter_its_secret_again() {
+        let manager = temp_manager();
+        let mut config = Config {
+            accounts: vec![r2_account("work", "shh")],
+            ..Config::default()
+        };
+        manager.save_config(&config).unwrap();
+
+        // what the webview sends back after renaming the account
+        config.accounts = vec![r2_account("office", REDACTED)];
+        let error = manager.save_config(&config).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidConfig);
+        assert_eq!(
+            error.details.unwrap().fields,
+            ["office/r2_secret_access_key"]
+        );
+        let stored = manager.load_stored().unwrap();
+        assert_eq!(stored.accounts[0].id, "work");
+        assert_eq!(stored.accounts[0].fields["r2_secret_access_key"], "shh");
+
+        config.accounts = vec![r2_account("office", "shh")];
+        manager.save_config(&config).unwrap();
+        let stored = manager.load_stored().unwrap();
+        assert_eq!(stored.accounts[0].fields["r2_secret_access_key"], "shh");
+
+        // unchanged keys still keep their secret
+        config.accounts = vec![r2_account("office", REDACTED)];
+        manager.save_config(&config).unwrap();
+        let stored = manager.load_stored().unwrap();
+        assert_eq!(stored.accounts[0].fields["r2_secret_access_key"], "shh");
+
+        let _ = fs::remove_dir_all(manager.config_path.parent().unwrap());
+    }
+
+    #[test]
+    fn loading_an_old_version_backs_it_up_and_rewrites_it() {
+        let manager = temp_manager();
+        fs::write(
+            &manager.config_path,
+            r#"{ "version": 1, "r2_public_domain": "http://cdn.example.com" }"#,
+        )
+        .unwrap();
+
+        let config = manager.load_config().unwrap();
+        assert_eq!(config.r2_public_domain, "https://cdn.example.com");
+
+        let backup = manager.config_path.with_extension("json.v1.bak");
+        let backup: Value = serde_json::from_str(&fs::read_to_string(backup).unwrap()).unwrap();
+        assert_eq!(backup["r2_public_domain"], "http://cdn.example.com");
+
+        let written: Value =
+            serde_json::from_str(&fs::read_to_string(&manager.config_path).unwrap()).unwrap();
+        assert_eq!(written["version"], config_schema::latest_version());
+        assert_eq!(written["r2_public_domain"], "https://cdn.example.com");
+        assert!(!manager.config_path.with_extension("json.tmp").exists());
+
+        // already migrated: loading again leaves the file alone
+        let dir = manager.config_path.parent().unwrap().to_path_buf();
+        let files = || fs::read_dir(&dir).unwrap().count();
+        let before = files();
+        manager.load_config().unwrap();
+        assert_eq!(files(), before);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn invalid_settings_are_not_written() {
+        let manager = temp_manager();
+        let config = Config {
+            settings_jpeg_quality: 90,
+            ..Config::default()
+        };
+        manager.save_config(&config).unwrap();
+        assert!(!manager.config_path.with_extension("json.tmp").exists());
+        let saved = fs::read_to_string(&manager.config_path).unwrap();
+
+        let invalid = Config {
+            settings_jpeg_quality: 0,
+            r2_public_domain: "http://cdn.example.com".to_string(),
+            ..config
+        };
+        let error = manager.save_config(&invalid).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidConfig);
+        assert_eq!(
+            error.details.unwrap().fields,
+            ["r2_public_domain", "settings_jpeg_quality"]
+        );
+        assert_eq!(fs::read_to_string(&manager.config_path).unwrap(), saved);
+        assert_eq!(manager.load_config().unwrap().settings_jpeg_quality, 90);
+
+        let _ = fs::remove_dir_all(manager.config_path.parent().unwrap());
+    }
+}
@@ -0,0 +1,463 @@
+//! Versions of `config.json` and the rules its values follow. Files are
+//! migrated step by step to `latest_version()` on load, and any key still
+//! missing afterwards takes its default, so adding a field never makes an
+//! existing file unreadable. Values are validated before they are saved.
+
+use crate::error::{AppError, ErrorCode};
+use crate::models::Config;
+use serde_json::{Map, Value};
+use std::ops::RangeInclusive;
+
+/// One step between config versions. `version` is the version after it runs.
+pub struct ConfigMigration {
+    pub version: u32,
+    pub description: &'static str,
+    pub migrate: fn(&mut Map<String, Value>),
+}
+
+/// Ordered history of `config.json`. Never edit a released step; append a new one.
+pub const MIGRATIONS: &[ConfigMigration] = &[
+    ConfigMigration {
+        version: 1,
+        description: "record the output format chosen with settings_auto_webp",
+        migrate: output_format_from_auto_webp,
+    },
+    ConfigMigration {
+        version: 2,
+        description: "give public domains an https:// scheme",
+        migrate: public_domains_with_scheme,
+    },
+];
+
+const MAX_WIDTH_RANGE: RangeInclusive<u32> = 100..=8192;
+const QUALITY_RANGE: RangeInclusive<u8> = 1..=100;
+/// 0 is a valid lossy WebP quality, unlike for JPEG and AVIF.
+const WEBP_QUALITY_RANGE: RangeInclusive<u8> = 0..=100;
+const AVIF_SPEED_RANGE: RangeInclusive<u8> = 1..=10;
+const CONCURRENCY_RANGE: RangeInclusive<u32> = 1..=16;
+const SERVER_PORT_RANGE: RangeInclusive<u16> = 1024..=65535;
+const SERVER_MAX_UPLOAD_MB_RANGE: RangeInclusive<u32> = 1..=1024;
+
+const ENDPOINT_FIELDS: [&str; 2] = ["r2_endpoint", "s3_endpoint"];
+const PUBLIC_DOMAIN_FIELDS: [&str; 2] = ["r2_public_domain", "s3_public_domain"];
+
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+/// Parses `config.json` content, migrating it to the latest version. Also
+/// returns the version it was migrated from, so the caller can write it back.
+pub fn parse(content: &str) -> Result<(Config, Option<u32>), AppError> {
+    let value: Value = serde_json::from_str(content)
+        .map_err(|e| invalid(format!("Failed to parse config: {}", e)))?;
+    upgrade(value)
+}
+
+/// `parse` for a config already read as JSON, such as one in an exported bundle.
+pub fn upgrade(value: Value) -> Result<(Config, Option<u32>), AppError> {
+    let Value::Object(mut fields) = value else {
+        return Err(invalid("Failed to parse config: not a JSON object"));
+    };
+
+    let starting_version = fields.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+    if starting_version > latest_version() {
+        return Err(invalid(format!(
+            "config.json was written by a newer version of the app (config version {}, this app supports {})",
+            starting_version,
+            latest_version()
+        )));
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > starting_version) {
+        (migration.migrate)(&mut fields);
+    }
+    fields.insert("version".to_string(), Value::from(latest_version()));
+
+    let migrated_from = (starting_version < latest_version()).then_some(starting_version);
+    Ok((from_fields(fields)?, migrated_from))
+}
+
+/// Checks every value a user can get wrong; the error names each bad field
+/// and why, in settings order. Account fields are keyed `<account id>/<field>`.
+pub fn validate(config: &Config) -> Result<(), AppError> {
+    let mut errors = Vec::new();
+
+    for (field, value) in [
+        ("r2_endpoint", &config.r2_endpoint),
+        ("s3_endpoint", &config.s3_endpoint),
+    ] {
+        check_endpoint(&mut errors, field, value);
+    }
+    for (field, value) in [
+        ("r2_public_domain", &config.r2_public_domain),
+        ("s3_public_domain", &config.s3_public_domain),
+    ] {
+        check_public_domain(&mut errors, field, value);
+    }
+
+    check_range(
+        &mut errors,
+        "settings_max_width",
+        config.settings_max_width,
+        &MAX_WIDTH_RANGE,
+    );
+    check_range(
+        &mut errors,
+        "settings_upload_concurrency",
+        config.settings_upload_concurrency,
+        &CONCURRENCY_RANGE,
+    );
+    check_range(
+        &mut errors,
+        "settings_jpeg_quality",
+        config.settings_jpeg_quality,
+        &QUALITY_RANGE,
+    );
+    check_range(
+        &mut errors,
+        "settings_webp_quality",
+        config.settings_webp_quality,
+        &WEBP_QUALITY_RANGE,
+    );
+    check_range(
+        &mut errors,
+        "settings_avif_quality",
+        config.settings_avif_quality,
+        &QUALITY_RANGE,
+    );
+    check_range(
+        &mut errors,
+        "settings_avif_speed",
+        config.settings_avif_speed,
+        &AVIF_SPEED_RANGE,
+    );
+    check_range(
+        &mut errors,
+        "settings_svg_raster_width",
+        config.settings_svg_raster_width,
+        &MAX_WIDTH_RANGE,
+    );
+    check_range(
+        &mut errors,
+        "settings_server_port",
+        config.settings_server_port,
+        &SERVER_PORT_RANGE,
+    );
+    check_range(
+        &mut errors,
+        "settings_server_max_upload_mb",
+        config.settings_server_max_upload_mb,
+        &SERVER_MAX_UPLOAD_MB_RANGE,
+    );
+
+    for account in &config.accounts {
+        for (field, value) in &account.fields {
+            let key = format!("{}/{}", account.id, field);
+            match value.as_str() {
+                Some(value) if ENDPOINT_FIELDS.contains(&field.as_str()) => {
+                    check_endpoint(&mut errors, &key, value)
+                }
+                Some(value) if PUBLIC_DOMAIN_FIELDS.contains(&field.as_str()) => {
+                    check_public_domain(&mut errors, &key, value)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    for profile in &config.profiles {
+        if let Some(max_width) = profile.max_width {
+            check_range(
+                &mut errors,
+                &format!("{}/max_width", profile.id),
+                max_width,
+                &MAX_WIDTH_RANGE,
+            );
+        }
+        if let Some(quality) = profile.quality {
+            check_range(
+                &mut errors,
+                &format!("{}/quality", profile.id),
+                quality,
+                &QUALITY_RANGE,
+            );
+        }
+    }
+
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    let message = errors
+        .iter()
+        .map(|(field, message)| format!("{}: {}", field, message))
+        .collect::<Vec<_>>()
+        .join("; ");
+    Err(invalid(format!("Invalid settings: {}", message)).with_field_errors(errors))
+}
+
+/// Deserializes over the defaults. When that fails, each key is tried on
+/// its own so the error can name the ones holding a value of the wrong type.
+fn from_fields(fields: Map<String, Value>) -> Result<Config, AppError> {
+    let Ok(Value::Object(defaults)) = serde_json::to_value(Config::default()) else {
+        return Err(AppError::internal("Failed to serialize default config"));
+    };
+
+    let mut merged = defaults.clone();
+    merged.extend(fields.clone());
+    let error = match serde_json::from_value(Value::Object(merged)) {
+        Ok(config) => return Ok(config),
+        Err(e) => e,
+    };
+
+    let bad_fields: Vec<(String, String)> = fields
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let mut single = defaults.clone();
+            single.insert(key.clone(), value);
+            serde_json::from_value::<Config>(Value::Object(single))
+                .err()
+                .map(|e| (key, e.to_string()))
+        })
+        .collect();
+
+    Err(invalid(format!("Failed to parse config: {}", error)).with_field_errors(bad_fields))
+}
+
+fn invalid(message: impl Into<String>) -> AppError {
+    AppError::new(ErrorCode::InvalidConfig, message)
+}
+
+fn check_range<T: PartialOrd + std::fmt::Display>(
+    errors: &mut Vec<(String, String)>,
+    field: &str,
+    value: T,
+    range: &RangeInclusive<T>,
+) {
+    if !range.contains(&value) {
+        errors.push((
+            field.to_string(),
+            format!(
+                "must be between {} and {}, not {}",
+                range.start(),
+                range.end(),
+                value
+            ),
+        ));
+    }
+}
+
+fn check_endpoint(errors: &mut Vec<(String, String)>, field: &str, value: &str) {
+    let value = value.trim();
+    if value.is_empty() {
+        return;
+    }
+
+    match reqwest::Url::parse(value) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") && url.host_str().is_some() => {}
+        _ => errors.push((
+            field.to_string(),
+            format!("must be an http:// or https:// URL, not {}", value),
+        )),
+    }
+}
+
+fn check_public_domain(errors: &mut Vec<(String, String)>, field: &str, value: &str) {
+    let value = value.trim();
+    if value.is_empty() {
+        return;
+    }
+
+    match reqwest::Url::parse(value) {
+        Ok(url) if url.scheme() == "https" && url.host_str().is_some() => {}
+        _ => errors.push((
+            field.to_string(),
+            format!(
+                "must be an https:// URL such as https://cdn.example.com, not {}",
+                value
+            ),
+        )),
+    }
+}
+
+/// Version 1: `settings_output_format` replaced `settings_auto_webp`, where
+/// turning it off meant keeping the original format.
+fn output_format_from_auto_webp(fields: &mut Map<String, Value>) {
+    if fields
+        .get("settings_output_format")
+        .is_some_and(|format| !format.is_null())
+    {
+        return;
+    }
+
+    let auto_webp = fields
+        .get("settings_auto_webp")
+        .and_then(Value::as_bool)
+        .unwrap_or(true);
+    let format = if auto_webp { "webp" } else { "original" };
+    fields.insert("settings_output_format".to_string(), Value::from(format));
+}
+
+/// Version 2: public domains are now required to be https URLs. A bare host
+/// produced links without a scheme, so it only ever worked as https; http
+/// ones are upgraded rather than left for `validate` to refuse on next save.
+fn public_domains_with_scheme(fields: &mut Map<String, Value>) {
+    let add_scheme = |value: &mut Value| {
+        if let Some(domain) = value.as_str().map(str::trim) {
+            if let Some(host) = domain.strip_prefix("http://") {
+                *value = Value::from(format!("https://{}", host));
+            } else if !domain.is_empty() && !domain.contains("://") {
+                *value = Value::from(format!("https://{}", domain));
+            }
+        }
+    };
+
+    for field in PUBLIC_DOMAIN_FIELDS {
+        if let Some(value) = fields.get_mut(field) {
+            add_scheme(value);
+        }
+    }
+
+    let accounts = fields.get_mut("accounts").and_then(Value::as_array_mut);
+    for account in accounts.into_iter().flatten() {
+        let Some(account_fields) = account.get_mut("fields").and_then(Value::as_object_mut) else {
+            continue;
+        };
+        for field in PUBLIC_DOMAIN_FIELDS {
+            if let Some(value) = account_fields.get_mut(field) {
+                add_scheme(value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::OutputFormat;
+    use serde_json::json;
+
+    fn field_errors(error: &AppError) -> Vec<&str> {
+        error
+            .details
+            .as_ref()
+            .map(|details| details.fields.iter().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn v1_migration_keeps_the_format_auto_webp_chose() {
+        let (config, migrated_from) = parse(r#"{"settings_auto_webp": false}"#).unwrap();
+        assert_eq!(config.settings_output_format, Some(OutputFormat::Original));
+        assert_eq!(migrated_from, Some(0));
+
+        let (config, _) = parse(r#"{"settings_auto_webp": true}"#).unwrap();
+        assert_eq!(config.settings_output_format, Some(OutputFormat::Webp));
+
+        let (config, _) = parse(r#"{}"#).unwrap();
+        assert_eq!(config.settings_output_format, Some(OutputFormat::Webp));
+
+        // A format chosen before versions were recorded wins over the old toggle
+        let (config, _) =
+            parse(r#"{"settings_auto_webp": true, "settings_output_format": "avif"}"#).unwrap();
+        assert_eq!(config.settings_output_format, Some(OutputFormat::Avif));
+    }
+
+    #[test]
+    fn v2_migration_makes_public_domains_https() {
+        let (config, migrated_from) = upgrade(json!({
+            "version": 1,
+            "r2_public_domain": " cdn.example.com ",
+            "s3_public_domain": "http://assets.example.com",
+            "accounts": [{
+                "id": "work",
+                "name": "Work",
+                "provider": "s3",
+                "fields": { "s3_public_domain": "img.example.com", "s3_bucket_name": "photos" }
+            }, {
+                "id": "home",
+                "name": "Home",
+                "provider": "r2",
+                "fields": { "r2_public_domain": "http://img.example.org:8080/photos" }
+            }]
+        }))
+        .unwrap();
+
+        assert_eq!(migrated_from, Some(1));
+        assert_eq!(config.version, latest_version());
+        assert_eq!(config.r2_public_domain, "https://cdn.example.com");
+        assert_eq!(config.s3_public_domain, "https://assets.example.com");
+        let fields = &config.accounts[0].fields;
+        assert_eq!(fields["s3_public_domain"], "https://img.example.com");
+        assert_eq!(fields["s3_bucket_name"], "photos");
+        assert_eq!(
+            config.accounts[1].fields["r2_public_domain"],
+            "https://img.example.org:8080/photos"
+        );
+        // nothing the migration leaves behind is refused on the next save
+        validate(&config).unwrap();
+    }
+
+    #[test]
+    fn current_and_newer_versions() {
+        let current = json!({ "version": latest_version(), "r2_public_domain": "cdn.example.com" });
+        let (config, migrated_from) = upgrade(current).unwrap();
+        assert_eq!(migrated_from, None);
+        assert_eq!(config.r2_public_domain, "cdn.example.com");
+
+        let error = upgrade(json!({ "version": latest_version() + 1 })).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidConfig);
+    }
+
+    #[test]
+    fn parse_names_fields_of_the_wrong_type() {
+        let error =
+            parse(r#"{"settings_max_width": "wide", "settings_jpeg_quality": 80}"#).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidConfig);
+        assert_eq!(field_errors(&error), vec!["settings_max_width"]);
+    }
+
+    #[test]
+    fn validate_accepts_the_defaults() {
+        validate(&Config::default()).unwrap();
+    }
+
+    #[test]
+    fn validate_reports_every_bad_field() {
+        let mut config = Config {
+            s3_endpoint: "ftp://s3.example.com".to_string(),
+            r2_public_domain: "cdn.example.com".to_string(),
+            settings_max_width: 50,
+            settings_webp_quality: 0,
+            settings_server_port: 80,
+            ..Config::default()
+        };
+        let account_fields = json!({ "s3_public_domain": "http://img.example.com" });
+        config.accounts.push(crate::models::ProviderAccount {
+            id: "work".to_string(),
+            name: "Work".to_string(),
+            provider: "s3".to_string(),
+            fields: account_fields.as_object().unwrap().clone(),
+        });
+
+        let error = validate(&config).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidConfig);
+        assert_eq!(
+            field_errors(&error),
+            vec![
+                "s3_endpoint",
+                "r2_public_domain",
+                "settings_max_width",
+                "settings_server_port",
+                "work/s3_public_domain",
+            ]
+        );
+        assert!(
+            error
+                .message
+                .contains("settings_max_width: must be between 100 and 8192, not 50"),
+            "{}",
+            error.message
+        );
+    }
+}
@@ -1,91 +1,282 @@
-use crate::models::HistoryItem;
-use rusqlite::{params, Connection};
+use super::app_dirs;
+use super::migrations;
+use crate::error::AppError;
+use crate::models::{HistoryItem, PendingUpload};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::path::{Path, PathBuf};
+
+const HISTORY_COLUMNS: &str =
+    "id, provider, original_name, url, created_at, thumbnail_base64, status, \
+     public_id, bucket, object_key, etag, size_bytes, width, height, group_id, account_id";
+
+const PENDING_COLUMNS: &str =
+    "id, provider, original_name, blob_name, size_bytes, created_at, attempts, \
+     last_attempt_at, last_error, profile_id";
 
 pub struct Database {
     conn: Connection,
 }
 
 impl Database {
-    pub fn new(app_handle: &tauri::AppHandle) -> Result<Self, String> {
-        let app_dir = app_handle
-            .path_resolver()
-            .app_data_dir()
-            .ok_or("Failed to get app data directory")?;
-
-        std::fs::create_dir_all(&app_dir)
-            .map_err(|e| format!("Failed to create app directory: {}", e))?;
-
-        let db_path = app_dir.join("history.db");
-
-        let conn = Connection::open(&db_path)
-            .map_err(|e| format!("Failed to open database: {}", e))?;
-
-        // Create table if not exists
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS uploads (
-                id TEXT PRIMARY KEY,
-                provider TEXT NOT NULL,
-                original_name TEXT NOT NULL,
-                url TEXT NOT NULL,
-                created_at INTEGER NOT NULL,
-                thumbnail_base64 TEXT NOT NULL
-            )",
-            [],
-        )
-        .map_err(|e| format!("Failed to create table: {}", e))?;
+    pub fn new(app_handle: &tauri::AppHandle) -> Result<Self, AppError> {
+        let app_dir = app_dirs::app_data_dir(app_handle)?;
+
+        Self::open(&app_dir.join("history.db"))
+    }
+
+    /// Opens the database at `db_path`, migrating it to the latest schema.
+    /// Existing databases are copied to `history.db.v<N>.bak` before migrating.
+    pub fn open(db_path: &Path) -> Result<Self, AppError> {
+        if let Some(parent) = db_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| AppError::io("Failed to create database directory", e))?;
+        }
+
+        let mut conn = Connection::open(db_path)
+            .map_err(|e| AppError::database("Failed to open database", e))?;
+
+        let version = migrations::current_version(&conn)?;
+        if version > 0 && version < migrations::latest_version() {
+            let backup_path = backup_path(db_path, version);
+            std::fs::copy(db_path, &backup_path)
+                .map_err(|e| AppError::io("Failed to back up database before migrating", e))?;
+        }
+
+        migrations::run(&mut conn)?;
 
         Ok(Self { conn })
     }
 
-    pub fn insert_history(&self, item: &HistoryItem) -> Result<(), String> {
+    pub fn insert_history(&self, item: &HistoryItem) -> Result<(), AppError> {
         self.conn
             .execute(
-                "INSERT INTO uploads (id, provider, original_name, url, created_at, thumbnail_base64) 
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                &format!(
+                    "INSERT INTO uploads ({}) 
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+                    HISTORY_COLUMNS
+                ),
                 params![
                     &item.id,
                     &item.provider,
                     &item.original_name,
                     &item.url,
                     &item.created_at,
-                    &item.thumbnail_base64
+                    &item.thumbnail_base64,
+                    &item.status,
+                    &item.public_id,
+                    &item.bucket,
+                    &item.object_key,
+                    &item.etag,
+                    &item.size_bytes,
+                    &item.width,
+                    &item.height,
+                    &item.group_id,
+                    &item.account_id
                 ],
             )
-            .map_err(|e| format!("Failed to insert history: {}", e))?;
+            .map_err(|e| AppError::database("Failed to insert history", e))?;
 
         Ok(())
     }
 
-    pub fn get_history(&self) -> Result<Vec<HistoryItem>, String> {
+    pub fn get_history(&self) -> Result<Vec<HistoryItem>, AppError> {
         let mut stmt = self
             .conn
-            .prepare("SELECT id, provider, original_name, url, created_at, thumbnail_base64 FROM uploads ORDER BY created_at DESC")
-            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+            .prepare(&format!(
+                "SELECT {} FROM uploads ORDER BY created_at DESC",
+                HISTORY_COLUMNS
+            ))
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
 
         let items = stmt
-            .query_map([], |row| {
-                Ok(HistoryItem {
-                    id: row.get(0)?,
-                    provider: row.get(1)?,
-                    original_name: row.get(2)?,
-                    url: row.get(3)?,
-                    created_at: row.get(4)?,
-                    thumbnail_base64: row.get(5)?,
-                })
-            })
-            .map_err(|e| format!("Failed to query history: {}", e))?
+            .query_map([], row_to_history_item)
+            .map_err(|e| AppError::database("Failed to query history", e))?
             .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| format!("Failed to collect results: {}", e))?;
+            .map_err(|e| AppError::database("Failed to collect results", e))?;
 
         Ok(items)
     }
 
-    pub fn delete_history(&self, id: &str) -> Result<(), String> {
+    pub fn get_history_item(&self, id: &str) -> Result<Option<HistoryItem>, AppError> {
+        self.conn
+            .query_row(
+                &format!("SELECT {} FROM uploads WHERE id = ?1", HISTORY_COLUMNS),
+                params![id],
+                row_to_history_item,
+            )
+            .optional()
+            .map_err(|e| AppError::database("Failed to query history item", e))
+    }
+
+    /// Variants of a responsive set, narrowest first.
+    pub fn get_group(&self, group_id: &str) -> Result<Vec<HistoryItem>, AppError> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT {} FROM uploads WHERE group_id = ?1 ORDER BY width",
+                HISTORY_COLUMNS
+            ))
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let items = stmt
+            .query_map(params![group_id], row_to_history_item)
+            .map_err(|e| AppError::database("Failed to query history group", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::database("Failed to collect results", e))?;
+
+        Ok(items)
+    }
+
+    pub fn set_status(&self, id: &str, status: &str) -> Result<(), AppError> {
+        self.conn
+            .execute(
+                "UPDATE uploads SET status = ?1 WHERE id = ?2",
+                params![status, id],
+            )
+            .map_err(|e| AppError::database("Failed to update history status", e))?;
+
+        Ok(())
+    }
+
+    pub fn delete_history(&self, id: &str) -> Result<(), AppError> {
         self.conn
             .execute("DELETE FROM uploads WHERE id = ?1", params![id])
-            .map_err(|e| format!("Failed to delete history: {}", e))?;
+            .map_err(|e| AppError::database("Failed to delete history", e))?;
+
+        Ok(())
+    }
+
+    pub fn insert_pending(&self, item: &PendingUpload) -> Result<(), AppError> {
+        self.conn
+            .execute(
+                &format!(
+                    "INSERT INTO pending_uploads ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                    PENDING_COLUMNS
+                ),
+                params![
+                    &item.id,
+                    &item.provider,
+                    &item.original_name,
+                    &item.blob_name,
+                    &item.size_bytes,
+                    &item.created_at,
+                    &item.attempts,
+                    &item.last_attempt_at,
+                    error_json(item.last_error.as_ref()),
+                    &item.profile_id
+                ],
+            )
+            .map_err(|e| AppError::database("Failed to queue upload", e))?;
 
         Ok(())
     }
+
+    /// The offline queue, oldest first.
+    pub fn get_pending(&self) -> Result<Vec<PendingUpload>, AppError> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT {} FROM pending_uploads ORDER BY created_at",
+                PENDING_COLUMNS
+            ))
+            .map_err(|e| AppError::database("Failed to prepare statement", e))?;
+
+        let items = stmt
+            .query_map([], row_to_pending_upload)
+            .map_err(|e| AppError::database("Failed to query pending uploads", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::database("Failed to collect results", e))?;
+
+        Ok(items)
+    }
+
+    pub fn get_pending_item(&self, id: &str) -> Result<Option<PendingUpload>, AppError> {
+        self.conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM pending_uploads WHERE id = ?1",
+                    PENDING_COLUMNS
+                ),
+                params![id],
+                row_to_pending_upload,
+            )
+            .optional()
+            .map_err(|e| AppError::database("Failed to query pending upload", e))
+    }
+
+    /// Counts a failed retry from the queue.
+    pub fn record_pending_attempt(
+        &self,
+        id: &str,
+        attempted_at: i64,
+        error: &AppError,
+    ) -> Result<(), AppError> {
+        self.conn
+            .execute(
+                "UPDATE pending_uploads
+                 SET attempts = attempts + 1, last_attempt_at = ?1, last_error = ?2
+                 WHERE id = ?3",
+                params![attempted_at, error_json(Some(error)), id],
+            )
+            .map_err(|e| AppError::database("Failed to update pending upload", e))?;
+
+        Ok(())
+    }
+
+    pub fn delete_pending(&self, id: &str) -> Result<(), AppError> {
+        self.conn
+            .execute("DELETE FROM pending_uploads WHERE id = ?1", params![id])
+            .map_err(|e| AppError::database("Failed to delete pending upload", e))?;
+
+        Ok(())
+    }
+}
+
+fn backup_path(db_path: &Path, version: u32) -> PathBuf {
+    let mut name = db_path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{}.bak", version));
+    db_path.with_file_name(name)
+}
+
+fn row_to_history_item(row: &Row) -> rusqlite::Result<HistoryItem> {
+    Ok(HistoryItem {
+        id: row.get(0)?,
+        provider: row.get(1)?,
+        original_name: row.get(2)?,
+        url: row.get(3)?,
+        created_at: row.get(4)?,
+        thumbnail_base64: row.get(5)?,
+        status: row.get(6)?,
+        public_id: row.get(7)?,
+        bucket: row.get(8)?,
+        object_key: row.get(9)?,
+        etag: row.get(10)?,
+        size_bytes: row.get(11)?,
+        width: row.get(12)?,
+        height: row.get(13)?,
+        group_id: row.get(14)?,
+        account_id: row.get(15)?,
+    })
 }
 
+fn row_to_pending_upload(row: &Row) -> rusqlite::Result<PendingUpload> {
+    let last_error: Option<String> = row.get(8)?;
+
+    Ok(PendingUpload {
+        id: row.get(0)?,
+        provider: row.get(1)?,
+        original_name: row.get(2)?,
+        blob_name: row.get(3)?,
+        size_bytes: row.get(4)?,
+        created_at: row.get(5)?,
+        attempts: row.get(6)?,
+        last_attempt_at: row.get(7)?,
+        last_error: last_error.and_then(|json| serde_json::from_str(&json).ok()),
+        profile_id: row.get(9)?,
+    })
+}
+
+/// Errors are stored as JSON so the UI gets the code and details back.
+fn error_json(error: Option<&AppError>) -> Option<String> {
+    error.and_then(|error| serde_json::to_string(error).ok())
+}
@@ -0,0 +1,334 @@
+//! Processed images waiting for upload, keyed by `temp_id`. Memory use is
+//! capped: the least recently used images are written to a private temp
+//! directory of this process and read back when needed. Images nobody
+//! touched for `DEFAULT_TTL` are dropped, since the preview that created them
+//! was abandoned.
+
+use crate::error::AppError;
+use crate::state::AppState;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+use tauri::async_runtime;
+use tauri::{AppHandle, Manager};
+use uuid::Uuid;
+
+const DEFAULT_MEMORY_LIMIT: usize = 256 * 1024 * 1024;
+const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// How often the background task drops expired images.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// A processed image waiting for upload. The source is kept so responsive
+/// variants can be cut from it instead of from the already resized output.
+#[derive(Clone)]
+pub struct CachedImage {
+    pub source: Vec<u8>,
+    pub processed: Vec<u8>,
+}
+
+impl CachedImage {
+    fn size(&self) -> usize {
+        self.source.len() + self.processed.len()
+    }
+}
+
+pub struct ImageCache {
+    inner: Mutex<Inner>,
+    spill_dir: PathBuf,
+    memory_limit: usize,
+    ttl: Duration,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<String, Entry>,
+    /// Bytes of the entries held in memory.
+    memory_used: usize,
+}
+
+struct Entry {
+    /// `None` once spilled to disk.
+    image: Option<CachedImage>,
+    last_used: Instant,
+}
+
+impl ImageCache {
+    /// `spill_dir` is created on first use, readable only by the current
+    /// user, and removed with the cache. It must not be shared with another
+    /// cache, so `Default` picks a fresh directory per process.
+    pub fn new(spill_dir: PathBuf, memory_limit: usize, ttl: Duration) -> Self {
+        Self {
+            inner: Mutex::new(Inner::default()),
+            spill_dir,
+            memory_limit,
+            ttl,
+        }
+    }
+
+    /// Fails when the cache is over its limit and older images cannot be
+    /// written to disk; the image is not kept then.
+    pub fn insert(&self, temp_id: String, image: CachedImage) -> Result<(), AppError> {
+        let mut inner = self.lock();
+        self.expire(&mut inner);
+
+        if let Some(previous) = inner.entries.remove(&temp_id) {
+            self.forget(&mut inner, &temp_id, previous);
+        }
+        inner.memory_used += image.size();
+        inner.entries.insert(
+            temp_id.clone(),
+            Entry {
+                image: Some(image),
+                last_used: Instant::now(),
+            },
+        );
+
+        if let Err(e) = self.spill_over_limit(&mut inner) {
+            if let Some(entry) = inner.entries.remove(&temp_id) {
+                self.forget(&mut inner, &temp_id, entry);
+            }
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    /// Returns a copy of the image, reading it back from disk if it was spilled.
+    /// Reading back can push the cache over its limit; when older images then
+    /// fail to spill they stay in memory, and that error comes with the image.
+    pub fn get(&self, temp_id: &str) -> Result<(CachedImage, Option<AppError>), AppError> {
+        let mut inner = self.lock();
+        self.expire(&mut inner);
+
+        let entry = inner
+            .entries
+            .get_mut(temp_id)
+            .ok_or_else(|| AppError::not_found("Image not found in cache"))?;
+        entry.last_used = Instant::now();
+
+        if let Some(image) = &entry.image {
+            return Ok((image.clone(), None));
+        }
+
+        let image = CachedImage {
+            source: fs::read(self.spill_path(temp_id, "source"))
+                .map_err(|e| AppError::io("Failed to read cached image", e))?,
+            processed: fs::read(self.spill_path(temp_id, "processed"))
+                .map_err(|e| AppError::io("Failed to read cached image", e))?,
+        };
+        self.remove_spilled(temp_id);
+
+        entry.image = Some(image.clone());
+        inner.memory_used += image.size();
+        // The spilled copy is gone, so the image must not be lost to this
+        let spill_error = self.spill_over_limit(&mut inner).err();
+
+        Ok((image, spill_error))
+    }
+
+    /// Drops an image; returns whether it was cached.
+    pub fn remove(&self, temp_id: &str) -> bool {
+        let mut inner = self.lock();
+        match inner.entries.remove(temp_id) {
+            Some(entry) => {
+                self.forget(&mut inner, temp_id, entry);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drops every image unused for longer than the TTL.
+    pub fn purge_expired(&self) {
+        let mut inner = self.lock();
+        self.expire(&mut inner);
+    }
+
+    /// Drops every image and deletes the spill directory.
+    pub fn clear(&self) {
+        let mut inner = self.lock();
+        inner.entries.clear();
+        inner.memory_used = 0;
+        let _ = fs::remove_dir_all(&self.spill_dir);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn expire(&self, inner: &mut Inner) {
+        let expired: Vec<String> = inner
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.last_used.elapsed() > self.ttl)
+            .map(|(temp_id, _)| temp_id.clone())
+            .collect();
+
+        for temp_id in expired {
+            if let Some(entry) = inner.entries.remove(&temp_id) {
+                self.forget(inner, &temp_id, entry);
+            }
+        }
+    }
+
+    /// Writes least recently used images to disk until memory use fits the
+    /// limit. An image that cannot be written stays in memory.
+    fn spill_over_limit(&self, inner: &mut Inner) -> Result<(), AppError> {
+        while inner.memory_used > self.memory_limit {
+            let Some((temp_id, entry)) = inner
+                .entries
+                .iter_mut()
+                .filter(|(_, entry)| entry.image.is_some())
+                .min_by_key(|(_, entry)| entry.last_used)
+            else {
+                return Ok(());
+            };
+            let Some(image) = entry.image.take() else {
+                return Ok(());
+            };
+
+            if let Err(e) = self.spill(temp_id, &image) {
+                entry.image = Some(image);
+                return Err(e);
+            }
+            inner.memory_used -= image.size();
+        }
+
+        Ok(())
+    }
+
+    fn spill(&self, temp_id: &str, image: &CachedImage) -> Result<(), AppError> {
+        self.create_spill_dir()
+            .map_err(|e| AppError::io("Failed to create image cache directory", e))?;
+
+        let written = fs::write(self.spill_path(temp_id, "source"), &image.source)
+            .and_then(|()| fs::write(self.spill_path(temp_id, "processed"), &image.processed));
+        if let Err(e) = written {
+            self.remove_spilled(temp_id);
+            return Err(AppError::io("Failed to spill cached image to disk", e));
+        }
+
+        Ok(())
+    }
+
+    fn forget(&self, inner: &mut Inner, temp_id: &str, entry: Entry) {
+        match entry.image {
+            Some(image) => inner.memory_used -= image.size(),
+            None => self.remove_spilled(temp_id),
+        }
+    }
+
+    fn remove_spilled(&self, temp_id: &str) {
+        let _ = fs::remove_file(self.spill_path(temp_id, "source"));
+        let _ = fs::remove_file(self.spill_path(temp_id, "processed"));
+    }
+
+    fn spill_path(&self, temp_id: &str, part: &str) -> PathBuf {
+        self.spill_dir.join(format!("{}.{}", temp_id, part))
+    }
+
+    /// Creates the directory with owner-only permissions, as the images may
+    /// be private. It is made once, so an existing one is ours already.
+    fn create_spill_dir(&self) -> std::io::Result<()> {
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::DirBuilderExt;
+            builder.mode(0o700);
+        }
+
+        match builder.create(&self.spill_dir) {
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(()),
+            created => created,
+        }
+    }
+}
+
+impl Default for ImageCache {
+    fn default() -> Self {
+        // Random, so two processes (the app and the CLI) never share or
+        // delete each other's files, and the name cannot be claimed in advance
+        let spill_dir = std::env::temp_dir().join(format!("opti-bridge-cache-{}", Uuid::new_v4()));
+        Self::new(spill_dir, DEFAULT_MEMORY_LIMIT, DEFAULT_TTL)
+    }
+}
+
+impl Drop for ImageCache {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// Drops expired images every `SWEEP_INTERVAL`, so an idle app lets go of them too.
+pub fn spawn_sweeper(app_handle: AppHandle) {
+    async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(SWEEP_INTERVAL).await;
+            app_handle.state::<AppState>().image_cache.purge_expired();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(byte: u8) -> CachedImage {
+        CachedImage {
+            source: vec![byte; 8],
+            processed: vec![byte; 8],
+        }
+    }
+
+    #[test]
+    fn spills_to_a_private_directory_of_its_own() {
+        assert_ne!(
+            ImageCache::default().spill_dir,
+            ImageCache::default().spill_dir
+        );
+
+        let spill_dir = std::env::temp_dir().join(format!("opti-bridge-test-{}", Uuid::new_v4()));
+        let cache = ImageCache::new(spill_dir.clone(), 16, DEFAULT_TTL);
+        cache.insert("a".into(), image(1)).unwrap();
+        cache.insert("b".into(), image(2)).unwrap();
+        assert!(cache.spill_path("a", "source").exists());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&cache.spill_dir).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+
+        let (image, spill_error) = cache.get("a").unwrap();
+        assert_eq!(image.source, vec![1; 8]);
+        assert!(spill_error.is_none());
+        assert!(!cache.spill_path("a", "source").exists());
+
+        drop(cache);
+        assert!(!spill_dir.exists());
+    }
+
+    #[test]
+    fn read_back_image_survives_a_failed_spill() {
+        let spill_dir = std::env::temp_dir().join(format!("opti-bridge-test-{}", Uuid::new_v4()));
+        let cache = ImageCache::new(spill_dir, 16, DEFAULT_TTL);
+        cache.insert("a".into(), image(1)).unwrap();
+        cache.insert("b".into(), image(2)).unwrap();
+
+        // reading "a" back has to spill "b", which a directory is in the way of
+        fs::create_dir(cache.spill_path("b", "source")).unwrap();
+        let (image, spill_error) = cache.get("a").unwrap();
+        assert_eq!(image.processed, vec![1; 8]);
+        assert!(spill_error.is_some());
+
+        // both stay in memory, over the limit, until a spill succeeds
+        assert_eq!(cache.lock().memory_used, 32);
+        fs::remove_dir(cache.spill_path("b", "source")).unwrap();
+        assert_eq!(cache.get("b").unwrap().0.source, vec![2; 8]);
+        assert_eq!(cache.get("a").unwrap().0.source, vec![1; 8]);
+    }
+}
@@ -1,32 +1,248 @@
-use image::{imageops::FilterType, DynamicImage, ImageFormat, GenericImageView};
+use super::{animation, metadata, svg};
+use crate::error::AppError;
+use crate::models::{AnimationMode, Config, MetadataPolicy, OutputFormat, SvgMode, WebpMode};
+use image::codecs::png::PngEncoder;
+use image::{
+    imageops::FilterType, DynamicImage, GenericImageView, ImageEncoder, ImageFormat, RgbImage,
+    RgbaImage,
+};
+use rgb::FromSlice;
+use std::collections::HashSet;
 use std::io::Cursor;
 
+/// Upper bound on pixels inspected when classifying an image.
+const CLASSIFY_SAMPLES: u32 = 250_000;
+
+/// One encoded size/format of a responsive set.
+pub struct ImageVariant {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone)]
 pub struct ImageProcessor {
     max_width: u32,
+    output_format: OutputFormat,
+    webp_mode: WebpMode,
+    webp_quality: u8,
+    jpeg_quality: u8,
+    avif_quality: u8,
+    avif_speed: u8,
+    metadata_policy: MetadataPolicy,
+    animation_mode: AnimationMode,
+    svg_sanitize: bool,
+    svg_mode: SvgMode,
+    svg_raster_width: u32,
 }
 
 impl ImageProcessor {
     pub fn new(max_width: u32) -> Self {
-        Self { max_width }
+        Self {
+            max_width,
+            output_format: OutputFormat::Webp,
+            webp_mode: WebpMode::Auto,
+            webp_quality: 85,
+            jpeg_quality: 85,
+            avif_quality: 70,
+            avif_speed: 6,
+            metadata_policy: MetadataPolicy::Strip,
+            animation_mode: AnimationMode::Webp,
+            svg_sanitize: true,
+            svg_mode: SvgMode::Keep,
+            svg_raster_width: 1200,
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(config.settings_max_width)
+            .with_output_format(config.output_format())
+            .with_webp(config.settings_webp_mode, config.settings_webp_quality)
+            .with_jpeg_quality(config.settings_jpeg_quality)
+            .with_avif(config.settings_avif_quality, config.settings_avif_speed)
+            .with_metadata_policy(config.settings_metadata_policy)
+            .with_animation_mode(config.settings_animation_mode)
+            .with_svg(
+                config.settings_svg_sanitize,
+                config.settings_svg_mode,
+                config.settings_svg_raster_width,
+            )
+    }
+
+    pub fn with_output_format(mut self, format: OutputFormat) -> Self {
+        self.output_format = format;
+        self
+    }
+
+    pub fn with_webp(mut self, mode: WebpMode, quality: u8) -> Self {
+        self.webp_mode = mode;
+        self.webp_quality = quality.min(100);
+        self
+    }
+
+    pub fn with_jpeg_quality(mut self, quality: u8) -> Self {
+        self.jpeg_quality = quality.clamp(1, 100);
+        self
+    }
+
+    /// `speed` runs from 1 (slowest, smallest files) to 10 (fastest).
+    pub fn with_avif(mut self, quality: u8, speed: u8) -> Self {
+        self.avif_quality = quality.clamp(1, 100);
+        self.avif_speed = speed.clamp(1, 10);
+        self
+    }
+
+    pub fn with_metadata_policy(mut self, policy: MetadataPolicy) -> Self {
+        self.metadata_policy = policy;
+        self
+    }
+
+    pub fn with_animation_mode(mut self, mode: AnimationMode) -> Self {
+        self.animation_mode = mode;
+        self
+    }
+
+    pub fn with_svg(mut self, sanitize: bool, mode: SvgMode, raster_width: u32) -> Self {
+        self.svg_sanitize = sanitize;
+        self.svg_mode = mode;
+        self.svg_raster_width = raster_width.max(1);
+        self
+    }
+
+    pub fn process_from_path(&self, path: &str) -> Result<Vec<u8>, AppError> {
+        let bytes = std::fs::read(path).map_err(|e| AppError::io("Failed to open image", e))?;
+
+        self.process_from_bytes(&bytes)
     }
 
-    pub fn process_from_path(&self, path: &str) -> Result<Vec<u8>, String> {
-        let img = image::open(path)
-            .map_err(|e| format!("Failed to open image: {}", e))?;
-        
-        self.process_image(img)
+    pub fn process_from_bytes(&self, bytes: &[u8]) -> Result<Vec<u8>, AppError> {
+        self.process(bytes)
     }
 
-    pub fn process_from_bytes(&self, bytes: &[u8]) -> Result<Vec<u8>, String> {
+    fn process(&self, bytes: &[u8]) -> Result<Vec<u8>, AppError> {
+        // image can't decode SVG at all
+        if svg::is_svg(bytes) {
+            return self.process_svg(bytes);
+        }
+
+        let source_format = image::guess_format(bytes).ok();
+        if let Some(frames) = animation::decode_frames(bytes, source_format)? {
+            return self.process_animation(bytes, frames);
+        }
+
         let img = image::load_from_memory(bytes)
-            .map_err(|e| format!("Failed to load image from memory: {}", e))?;
-        
-        self.process_image(img)
+            .map_err(|e| AppError::image(format!("Failed to load image from memory: {}", e)))?;
+
+        // image ignores EXIF orientation; rotate before the width check and resize
+        let exif = metadata::read_exif(bytes);
+        let orientation = metadata::orientation(exif.as_ref());
+        let img = metadata::apply_orientation(img, orientation);
+        let output_exif = metadata::exif_for_output(exif.as_ref(), self.metadata_policy);
+
+        // Nothing to resize, rotate or convert: keep the original pixels untouched
+        if self.output_format == OutputFormat::Original
+            && orientation == 1
+            && img.width() <= self.max_width
+        {
+            return metadata::embed_exif(bytes.to_vec(), output_exif);
+        }
+
+        let encoded = self.process_image(img, source_format)?;
+        metadata::embed_exif(encoded, output_exif)
     }
 
-    fn process_image(&self, img: DynamicImage) -> Result<Vec<u8>, String> {
+    /// Encodes `bytes` once per width and format, formats in the given order
+    /// and widths ascending. Widths beyond the source size are dropped rather
+    /// than upscaled.
+    pub fn process_variants(
+        &self,
+        bytes: &[u8],
+        widths: &[u32],
+        formats: &[OutputFormat],
+    ) -> Result<Vec<ImageVariant>, AppError> {
+        let mut widths = widths.to_vec();
+        widths.sort_unstable();
+        widths.dedup();
+
+        let mut variants = Vec::new();
+        for &format in formats {
+            let mut previous_width = None;
+            for &width in &widths {
+                let processor = Self {
+                    max_width: width,
+                    ..self.clone()
+                }
+                .with_output_format(format);
+
+                let data = processor.process_from_bytes(bytes)?;
+                let (actual_width, height) = self.get_dimensions(&data)?;
+                // The source is exactly as wide as the previous width
+                if previous_width == Some(actual_width) {
+                    break;
+                }
+                previous_width = Some(actual_width);
+                variants.push(ImageVariant {
+                    width: actual_width,
+                    height,
+                    data,
+                });
+
+                // The source is narrower than this width; larger ones would be identical
+                if actual_width < width {
+                    break;
+                }
+            }
+        }
+
+        Ok(variants)
+    }
+
+    fn process_svg(&self, bytes: &[u8]) -> Result<Vec<u8>, AppError> {
+        let cleaned = if self.svg_sanitize {
+            svg::sanitize(bytes)?
+        } else {
+            bytes.to_vec()
+        };
+
+        match self.svg_mode {
+            SvgMode::Keep => Ok(cleaned),
+            SvgMode::Rasterize => {
+                let raster = svg::rasterize(&cleaned, self.svg_raster_width)?;
+                self.process_image(DynamicImage::ImageRgba8(raster), None)
+            }
+        }
+    }
+
+    fn process_animation(
+        &self,
+        bytes: &[u8],
+        frames: Vec<animation::AnimationFrame>,
+    ) -> Result<Vec<u8>, AppError> {
+        let output_exif =
+            metadata::exif_for_output(metadata::read_exif(bytes).as_ref(), self.metadata_policy);
+
+        if self.animation_mode == AnimationMode::Original {
+            return metadata::embed_exif(bytes.to_vec(), output_exif);
+        }
+
+        let frames = animation::resize_frames(frames, self.max_width);
+        let lossless = match self.webp_mode {
+            WebpMode::Lossless => true,
+            WebpMode::Lossy => false,
+            WebpMode::Auto => looks_like_screenshot(&frames[0].image),
+        };
+
+        let encoded = animation::encode_webp(&frames, lossless, self.webp_quality)?;
+        metadata::embed_exif(encoded, output_exif)
+    }
+
+    fn process_image(
+        &self,
+        img: DynamicImage,
+        source_format: Option<ImageFormat>,
+    ) -> Result<Vec<u8>, AppError> {
         let (width, height) = img.dimensions();
-        
+
         // Resize if needed
         let processed = if width > self.max_width {
             let new_height = (height as f64 * (self.max_width as f64 / width as f64)) as u32;
@@ -35,40 +251,542 @@ impl ImageProcessor {
             img
         };
 
-        // Convert to WebP
-        let mut buffer = Vec::new();
-        let mut cursor = Cursor::new(&mut buffer);
-        
-        processed
-            .write_to(&mut cursor, ImageFormat::WebP)
-            .map_err(|e| format!("Failed to encode as WebP: {}", e))?;
+        match self.target_format(source_format) {
+            OutputFormat::Png => encode_png(&processed.to_rgba8()),
+            OutputFormat::Jpeg => encode_jpeg(&flatten_alpha(&processed), self.jpeg_quality),
+            OutputFormat::Avif => {
+                encode_avif(&processed.to_rgba8(), self.avif_quality, self.avif_speed)
+            }
+            _ => {
+                let rgba = processed.to_rgba8();
+                let lossless = match self.webp_mode {
+                    WebpMode::Lossless => true,
+                    WebpMode::Lossy => false,
+                    WebpMode::Auto => looks_like_screenshot(&rgba),
+                };
 
-        Ok(buffer)
+                encode_webp(&rgba, lossless, self.webp_quality)
+            }
+        }
     }
 
-    pub fn create_thumbnail(&self, bytes: &[u8], max_size: u32) -> Result<Vec<u8>, String> {
-        let img = image::load_from_memory(bytes)
-            .map_err(|e| format!("Failed to load image: {}", e))?;
-        
+    /// The concrete encoding for this image. "Original" re-encodes in the
+    /// source format when we have an encoder for it and falls back to PNG.
+    fn target_format(&self, source_format: Option<ImageFormat>) -> OutputFormat {
+        match (self.output_format, source_format) {
+            (OutputFormat::Original, Some(ImageFormat::Jpeg)) => OutputFormat::Jpeg,
+            (OutputFormat::Original, Some(ImageFormat::WebP)) => OutputFormat::Webp,
+            (OutputFormat::Original, _) => OutputFormat::Png,
+            (format, _) => format,
+        }
+    }
+
+    pub fn create_thumbnail(&self, bytes: &[u8], max_size: u32) -> Result<Vec<u8>, AppError> {
+        let img = if svg::is_svg(bytes) {
+            DynamicImage::ImageRgba8(svg::rasterize(bytes, max_size)?)
+        } else {
+            image::load_from_memory(bytes)
+                .map_err(|e| AppError::image(format!("Failed to load image: {}", e)))?
+        };
+
         let thumbnail = img.thumbnail(max_size, max_size);
-        
+
         let mut buffer = Vec::new();
         let mut cursor = Cursor::new(&mut buffer);
-        
+
         thumbnail
             .write_to(&mut cursor, ImageFormat::WebP)
-            .map_err(|e| format!("Failed to encode thumbnail: {}", e))?;
+            .map_err(|e| AppError::image(format!("Failed to encode thumbnail: {}", e)))?;
 
         Ok(buffer)
     }
 
-    pub fn get_size_info(&self, bytes: &[u8]) -> String {
-        let kb = bytes.len() as f64 / 1024.0;
-        if kb < 1024.0 {
-            format!("{:.2} KB", kb)
-        } else {
-            format!("{:.2} MB", kb / 1024.0)
+    pub fn get_dimensions(&self, bytes: &[u8]) -> Result<(u32, u32), AppError> {
+        if let Some(dimensions) = avif_dimensions(bytes) {
+            return Ok(dimensions);
         }
+        if svg::is_svg(bytes) {
+            return svg::dimensions(bytes);
+        }
+
+        image::io::Reader::new(Cursor::new(bytes))
+            .with_guessed_format()
+            .map_err(|e| AppError::image(format!("Failed to read image: {}", e)))?
+            .into_dimensions()
+            .map_err(|e| AppError::image(format!("Failed to read image dimensions: {}", e)))
+    }
+
+    /// File extension matching the encoded bytes, e.g. for naming uploads.
+    pub fn detect_extension(bytes: &[u8]) -> &'static str {
+        if is_avif(bytes) {
+            return "avif";
+        }
+        if svg::is_svg(bytes) {
+            return "svg";
+        }
+
+        match image::guess_format(bytes) {
+            Ok(ImageFormat::WebP) => "webp",
+            Ok(ImageFormat::Png) => "png",
+            Ok(ImageFormat::Jpeg) => "jpg",
+            Ok(ImageFormat::Gif) => "gif",
+            Ok(ImageFormat::Bmp) => "bmp",
+            Ok(ImageFormat::Tiff) => "tiff",
+            Ok(ImageFormat::Ico) => "ico",
+            _ => "bin",
+        }
+    }
+
+    /// Why `processed` is not in the configured output format: animations
+    /// follow the animation mode instead, since PNG, JPEG and AVIF output
+    /// would keep only one frame.
+    pub fn format_notice(&self, processed: &[u8]) -> Option<String> {
+        let configured = match self.output_format {
+            OutputFormat::Png => "PNG",
+            OutputFormat::Jpeg => "JPEG",
+            OutputFormat::Avif => "AVIF",
+            OutputFormat::Webp | OutputFormat::Original => return None,
+        };
+
+        match (Self::detect_extension(processed), self.animation_mode) {
+            ("webp" | "gif", AnimationMode::Original) => Some(format!(
+                "Animated image uploaded unchanged instead of as {}",
+                configured
+            )),
+            ("webp", AnimationMode::Webp) => Some(format!(
+                "Animated image kept as animated WebP instead of {}",
+                configured
+            )),
+            _ => None,
+        }
+    }
+
+    /// Size of `processed` and how it compares to `original`. For AVIF output the
+    /// source is also run through the WebP settings so the saving is visible.
+    pub fn get_size_info(&self, original: &[u8], processed: &[u8]) -> String {
+        let mut comparisons = vec![relative_size(processed.len(), original.len(), "original")];
+
+        if Self::detect_extension(processed) == "avif" {
+            let webp = self.clone().with_output_format(OutputFormat::Webp);
+            if let Ok(webp_bytes) = webp.process_from_bytes(original) {
+                comparisons.push(relative_size(processed.len(), webp_bytes.len(), "WebP"));
+            }
+        }
+
+        format!(
+            "{} ({})",
+            format_size(processed.len()),
+            comparisons.join(", ")
+        )
     }
 }
 
+fn format_size(len: usize) -> String {
+    let kb = len as f64 / 1024.0;
+    if kb < 1024.0 {
+        format!("{:.2} KB", kb)
+    } else {
+        format!("{:.2} MB", kb / 1024.0)
+    }
+}
+
+fn relative_size(len: usize, reference: usize, label: &str) -> String {
+    if reference == 0 || len == reference {
+        return format!("same size as {}", label);
+    }
+
+    let percent = (len as f64 / reference as f64 - 1.0) * 100.0;
+    if percent < 0.0 {
+        format!("{:.0}% smaller than {}", -percent, label)
+    } else {
+        format!("{:.0}% larger than {}", percent, label)
+    }
+}
+
+fn encode_avif(rgba: &RgbaImage, quality: u8, speed: u8) -> Result<Vec<u8>, AppError> {
+    let pixels = ravif::Img::new(
+        rgba.as_raw().as_rgba(),
+        rgba.width() as usize,
+        rgba.height() as usize,
+    );
+
+    let encoded = ravif::Encoder::new()
+        .with_quality(quality as f32)
+        .with_alpha_quality(quality as f32)
+        .with_speed(speed)
+        .encode_rgba(pixels)
+        .map_err(|e| AppError::image(format!("Failed to encode as AVIF: {}", e)))?;
+
+    Ok(encoded.avif_file)
+}
+
+/// image only knows a couple of fixed `ftyp` box sizes, so check the brand itself.
+fn is_avif(bytes: &[u8]) -> bool {
+    bytes.get(4..8) == Some(b"ftyp") && matches!(bytes.get(8..12), Some(b"avif" | b"avis"))
+}
+
+/// image can't decode AVIF without dav1d, but the `ispe` property in the
+/// container header records the dimensions.
+fn avif_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    if !is_avif(bytes) {
+        return None;
+    }
+
+    // meta is a full box: skip its version and flags
+    let meta = find_box(bytes, b"meta")?;
+    let iprp = find_box(meta.get(4..)?, b"iprp")?;
+    let ipco = find_box(iprp, b"ipco")?;
+    let ispe = find_box(ipco, b"ispe")?;
+
+    let field = |at: usize| Some(u32::from_be_bytes(ispe.get(at..at + 4)?.try_into().ok()?));
+    Some((field(4)?, field(8)?))
+}
+
+/// Payload of the first ISO BMFF box of type `kind` in a sequence of boxes.
+fn find_box<'a>(mut data: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    while data.len() >= 8 {
+        let (header, size) = match u32::from_be_bytes(data[..4].try_into().ok()?) {
+            0 => (8, data.len()),
+            1 => (
+                16,
+                u64::from_be_bytes(data.get(8..16)?.try_into().ok()?) as usize,
+            ),
+            size => (8, size as usize),
+        };
+
+        let payload = data.get(header..size)?;
+        if &data[4..8] == kind {
+            return Some(payload);
+        }
+        data = &data[size..];
+    }
+
+    None
+}
+
+fn encode_webp(rgba: &RgbaImage, lossless: bool, quality: u8) -> Result<Vec<u8>, AppError> {
+    let encoder = webp::Encoder::from_rgba(rgba.as_raw(), rgba.width(), rgba.height());
+
+    let encoded = encoder
+        .encode_simple(lossless, quality as f32)
+        .map_err(|e| AppError::image(format!("Failed to encode as WebP: {:?}", e)))?;
+
+    Ok(encoded.to_vec())
+}
+
+fn encode_png(rgba: &RgbaImage) -> Result<Vec<u8>, AppError> {
+    let mut buffer = Vec::new();
+    PngEncoder::new(&mut buffer)
+        .write_image(
+            rgba.as_raw(),
+            rgba.width(),
+            rgba.height(),
+            image::ColorType::Rgba8,
+        )
+        .map_err(|e| AppError::image(format!("Failed to encode as PNG: {}", e)))?;
+
+    // oxipng picks the smallest color type, bit depth and filter for the pixels
+    oxipng::optimize_from_memory(&buffer, &oxipng::Options::from_preset(2))
+        .map_err(|e| AppError::image(format!("Failed to optimize PNG: {}", e)))
+}
+
+fn encode_jpeg(rgb: &RgbImage, quality: u8) -> Result<Vec<u8>, AppError> {
+    let (width, height) = rgb.dimensions();
+    let width = u16::try_from(width).map_err(|_| AppError::image("Image is too large for JPEG"))?;
+    let height =
+        u16::try_from(height).map_err(|_| AppError::image("Image is too large for JPEG"))?;
+
+    let mut buffer = Vec::new();
+    let mut encoder = jpeg_encoder::Encoder::new(&mut buffer, quality);
+    encoder.set_progressive(true);
+    encoder.set_optimized_huffman_tables(true);
+    encoder
+        .encode(rgb.as_raw(), width, height, jpeg_encoder::ColorType::Rgb)
+        .map_err(|e| AppError::image(format!("Failed to encode as JPEG: {}", e)))?;
+
+    Ok(buffer)
+}
+
+/// JPEG has no alpha channel; composite transparent areas onto white instead of black.
+fn flatten_alpha(img: &DynamicImage) -> RgbImage {
+    if !img.color().has_alpha() {
+        return img.to_rgb8();
+    }
+
+    let rgba = img.to_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend = |c: u8| ((c as u16 * a as u16 + 255 * (255 - a as u16)) / 255) as u8;
+        image::Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+/// Screenshots, diagrams and UI captures have few distinct colors and long runs
+/// of identical pixels; photographs have neither. Lossless WebP keeps text and
+/// edges crisp and is usually smaller for the former, lossy wins for the latter.
+fn looks_like_screenshot(rgba: &RgbaImage) -> bool {
+    let (width, height) = rgba.dimensions();
+    if width < 2 || height == 0 {
+        return true;
+    }
+
+    // Sample whole rows so neighbouring pixels can be compared
+    let row_step = ((width as u64 * height as u64) / CLASSIFY_SAMPLES as u64).max(1) as u32;
+
+    let mut colors = HashSet::new();
+    let mut pairs = 0u64;
+    let mut flat_pairs = 0u64;
+
+    for y in (0..height).step_by(row_step as usize) {
+        let mut previous = rgba.get_pixel(0, y);
+        for x in 1..width {
+            let pixel = rgba.get_pixel(x, y);
+            if colors.len() <= 4096 {
+                colors.insert(pixel.0);
+            }
+            if pixel == previous {
+                flat_pairs += 1;
+            }
+            pairs += 1;
+            previous = pixel;
+        }
+    }
+
+    colors.len() <= 256 || flat_pairs * 2 >= pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A few flat blocks of colour with a line of "text", like a UI capture.
+    fn screenshot(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| match (x / 40 + y / 30) % 3 {
+            _ if y == height / 2 && x % 3 == 0 => image::Rgba([20, 20, 20, 255]),
+            0 => image::Rgba([240, 240, 240, 255]),
+            1 => image::Rgba([30, 110, 220, 255]),
+            _ => image::Rgba([255, 255, 255, 255]),
+        })
+    }
+
+    /// Smooth gradients with sensor-like noise, like a photograph.
+    fn photo(width: u32, height: u32) -> RgbaImage {
+        let mut state = 0x2545_f491_u32;
+        RgbaImage::from_fn(width, height, |x, y| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let noise = (state >> 24) as u8 % 24;
+            image::Rgba([
+                (x * 255 / width) as u8 / 2 + noise,
+                (y * 255 / height) as u8 / 2 + noise,
+                ((x + y) * 255 / (width + height)) as u8 / 2 + noise,
+                255,
+            ])
+        })
+    }
+
+    fn png(rgba: &RgbaImage) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        DynamicImage::ImageRgba8(rgba.clone())
+            .write_to(&mut Cursor::new(&mut buffer), ImageFormat::Png)
+            .unwrap();
+        buffer
+    }
+
+    /// Lossless WebP stores a `VP8L` bitstream, lossy a `VP8 ` one.
+    fn is_lossless_webp(bytes: &[u8]) -> bool {
+        assert_eq!(ImageProcessor::detect_extension(bytes), "webp");
+        bytes.windows(4).any(|chunk| chunk == b"VP8L")
+    }
+
+    #[test]
+    fn classifies_screenshots_and_photos() {
+        assert!(looks_like_screenshot(&screenshot(320, 200)));
+        assert!(!looks_like_screenshot(&photo(320, 200)));
+        // Too small to judge: lossless costs nothing there
+        assert!(looks_like_screenshot(&photo(1, 50)));
+    }
+
+    #[test]
+    fn auto_webp_picks_lossless_for_screenshots_only() {
+        let auto = ImageProcessor::new(2048).with_webp(WebpMode::Auto, 80);
+        assert!(is_lossless_webp(
+            &auto
+                .process_from_bytes(&png(&screenshot(320, 200)))
+                .unwrap()
+        ));
+        assert!(!is_lossless_webp(
+            &auto.process_from_bytes(&png(&photo(320, 200))).unwrap()
+        ));
+
+        let lossy = ImageProcessor::new(2048).with_webp(WebpMode::Lossy, 80);
+        assert!(!is_lossless_webp(
+            &lossy
+                .process_from_bytes(&png(&screenshot(320, 200)))
+                .unwrap()
+        ));
+
+        let lossless = ImageProcessor::new(2048).with_webp(WebpMode::Lossless, 80);
+        assert!(is_lossless_webp(
+            &lossless.process_from_bytes(&png(&photo(320, 200))).unwrap()
+        ));
+    }
+
+    /// A two-frame animated GIF, red then blue.
+    fn animated_gif() -> Vec<u8> {
+        let mut buffer = Vec::new();
+        let frames = [[255, 0, 0, 255], [0, 0, 255, 255]].map(|pixel| {
+            image::Frame::from_parts(
+                RgbaImage::from_pixel(16, 8, image::Rgba(pixel)),
+                0,
+                0,
+                image::Delay::from_numer_denom_ms(200, 1),
+            )
+        });
+        image::codecs::gif::GifEncoder::new(&mut buffer)
+            .encode_frames(frames)
+            .unwrap();
+        buffer
+    }
+
+    #[test]
+    fn original_keeps_the_source_format_where_it_can() {
+        let original = ImageProcessor::new(2048).with_output_format(OutputFormat::Original);
+        for (source, expected) in [
+            (Some(ImageFormat::Jpeg), OutputFormat::Jpeg),
+            (Some(ImageFormat::WebP), OutputFormat::Webp),
+            (Some(ImageFormat::Png), OutputFormat::Png),
+            (Some(ImageFormat::Bmp), OutputFormat::Png),
+            (None, OutputFormat::Png),
+        ] {
+            assert_eq!(original.target_format(source), expected, "{:?}", source);
+        }
+
+        let jpeg = ImageProcessor::new(2048).with_output_format(OutputFormat::Jpeg);
+        assert_eq!(
+            jpeg.target_format(Some(ImageFormat::Png)),
+            OutputFormat::Jpeg
+        );
+    }
+
+    #[test]
+    fn older_configs_take_the_format_from_auto_webp() {
+        for (auto_webp, expected) in [(true, OutputFormat::Webp), (false, OutputFormat::Original)] {
+            let config = Config {
+                settings_output_format: None,
+                settings_auto_webp: auto_webp,
+                ..Config::default()
+            };
+            assert_eq!(config.output_format(), expected);
+            assert_eq!(ImageProcessor::from_config(&config).output_format, expected);
+        }
+
+        let config = Config {
+            settings_output_format: Some(OutputFormat::Avif),
+            settings_auto_webp: true,
+            ..Config::default()
+        };
+        assert_eq!(config.output_format(), OutputFormat::Avif);
+    }
+
+    #[test]
+    fn output_extension_and_mime_type_match_the_format() {
+        let source = png(&screenshot(64, 40));
+        for (format, extension, mime) in [
+            (OutputFormat::Webp, "webp", "image/webp"),
+            (OutputFormat::Png, "png", "image/png"),
+            (OutputFormat::Jpeg, "jpg", "image/jpeg"),
+            (OutputFormat::Avif, "avif", "image/avif"),
+            (OutputFormat::Original, "png", "image/png"),
+        ] {
+            let processor = ImageProcessor::new(2048)
+                .with_output_format(format)
+                .with_avif(70, 10);
+            let output = processor.process_from_bytes(&source).unwrap();
+            let detected = ImageProcessor::detect_extension(&output);
+            assert_eq!(detected, extension, "{:?}", format);
+            assert_eq!(crate::uploaders::mime_type(detected), mime);
+            assert_eq!(processor.format_notice(&output), None);
+        }
+
+        assert_eq!(ImageProcessor::detect_extension(&animated_gif()), "gif");
+        assert_eq!(
+            ImageProcessor::detect_extension(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>"),
+            "svg"
+        );
+        assert_eq!(ImageProcessor::detect_extension(b"not an image"), "bin");
+        assert_eq!(
+            crate::uploaders::mime_type("bin"),
+            "application/octet-stream"
+        );
+    }
+
+    #[test]
+    fn animations_say_when_they_are_not_in_the_output_format() {
+        let jpeg = ImageProcessor::new(2048).with_output_format(OutputFormat::Jpeg);
+        let output = jpeg.process_from_bytes(&animated_gif()).unwrap();
+        assert_eq!(ImageProcessor::detect_extension(&output), "webp");
+        assert_eq!(
+            jpeg.format_notice(&output).as_deref(),
+            Some("Animated image kept as animated WebP instead of JPEG")
+        );
+
+        let unchanged = jpeg.with_animation_mode(AnimationMode::Original);
+        let output = unchanged.process_from_bytes(&animated_gif()).unwrap();
+        assert_eq!(
+            unchanged.format_notice(&output).as_deref(),
+            Some("Animated image uploaded unchanged instead of as JPEG")
+        );
+
+        let webp = ImageProcessor::new(2048);
+        let output = webp.process_from_bytes(&animated_gif()).unwrap();
+        assert_eq!(webp.format_notice(&output), None);
+    }
+
+    #[test]
+    fn avif_dimensions_come_from_the_encoded_header() {
+        let avif = encode_avif(&screenshot(40, 30), 70, 10).unwrap();
+        assert!(is_avif(&avif));
+        assert_eq!(avif_dimensions(&avif), Some((40, 30)));
+        assert!(find_box(&avif, b"meta").is_some());
+        assert!(find_box(&avif, b"nope").is_none());
+    }
+
+    #[test]
+    fn truncated_avif_has_no_dimensions() {
+        let avif = encode_avif(&screenshot(40, 30), 70, 10).unwrap();
+        // None until the whole meta box is in, the dimensions from then on
+        let complete = (0..avif.len())
+            .find(|&len| avif_dimensions(&avif[..len]).is_some())
+            .unwrap();
+        assert!(complete > 8);
+        for len in 0..avif.len() {
+            let expected = (len >= complete).then_some((40, 30));
+            assert_eq!(avif_dimensions(&avif[..len]), expected, "{} bytes", len);
+        }
+
+        // a box claiming to be smaller than its own header
+        let mut broken = avif[..12].to_vec();
+        broken[..4].copy_from_slice(&4u32.to_be_bytes());
+        assert_eq!(find_box(&broken, b"ftyp"), None);
+        assert_eq!(avif_dimensions(b"not an avif file"), None);
+    }
+
+    #[test]
+    fn variants_stop_at_the_source_width() {
+        let source = png(&screenshot(960, 40));
+        let formats = [OutputFormat::Webp, OutputFormat::Png];
+        let processor = ImageProcessor::new(2048);
+
+        let variants = processor
+            .process_variants(&source, &[1600, 960, 480], &formats)
+            .unwrap();
+        let widths: Vec<u32> = variants.iter().map(|variant| variant.width).collect();
+        assert_eq!(widths, [480, 960, 480, 960]);
+
+        let variants = processor
+            .process_variants(&source, &[1200, 1600], &formats)
+            .unwrap();
+        let widths: Vec<u32> = variants.iter().map(|variant| variant.width).collect();
+        assert_eq!(widths, [960, 960]);
+    }
+}
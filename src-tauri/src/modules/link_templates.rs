@@ -0,0 +1,123 @@
+use crate::error::{AppError, ErrorCode};
+use crate::models::{Config, HistoryItem, LinkTemplate};
+use arboard::Clipboard;
+use std::path::Path;
+
+/// Built-in templates as (id, name, template).
+const PRESETS: &[(&str, &str, &str)] = &[
+    ("url", "URL", "{url}"),
+    ("markdown", "Markdown", "![{alt}]({url})"),
+    (
+        "html",
+        "HTML",
+        "<img src=\"{url}\" alt=\"{alt}\" width=\"{width}\" height=\"{height}\">",
+    ),
+    ("bbcode", "BBCode", "[img]{url}[/img]"),
+    (
+        "rst",
+        "reStructuredText",
+        ".. image:: {url}\n   :alt: {alt}",
+    ),
+    ("asciidoc", "AsciiDoc", "image::{url}[{alt}]"),
+];
+
+/// The presets followed by the user's own templates.
+pub fn all(config: &Config) -> Vec<LinkTemplate> {
+    PRESETS
+        .iter()
+        .map(|(id, name, template)| LinkTemplate {
+            id: id.to_string(),
+            name: name.to_string(),
+            template: template.to_string(),
+        })
+        .chain(config.settings_custom_templates.iter().cloned())
+        .collect()
+}
+
+/// Looks a template up by id; user templates shadow presets with the same id.
+pub fn find(config: &Config, id: &str) -> Result<LinkTemplate, AppError> {
+    all(config)
+        .into_iter()
+        .rev()
+        .find(|template| template.id == id)
+        .ok_or_else(|| {
+            AppError::not_found(format!("Unknown link template: {}", id))
+                .with_fields(vec!["settings_link_template".to_string()])
+        })
+}
+
+/// Fills `{url}`, `{filename}`, `{width}`, `{height}` and `{alt}` from an
+/// uploaded item. Unknown placeholders are left as written, unknown
+/// dimensions become empty, and `alt` defaults to the file name without
+/// its extension.
+pub fn render(template: &str, item: &HistoryItem, alt: Option<&str>) -> String {
+    let stem = Path::new(&item.original_name)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let alt = alt.map(str::to_string).unwrap_or(stem);
+
+    let value = |name: &str| match name {
+        "url" => Some(item.url.clone()),
+        "filename" => Some(item.original_name.clone()),
+        "width" => Some(
+            item.width
+                .map(|width| width.to_string())
+                .unwrap_or_default(),
+        ),
+        "height" => Some(
+            item.height
+                .map(|height| height.to_string())
+                .unwrap_or_default(),
+        ),
+        "alt" => Some(alt.clone()),
+        _ => None,
+    };
+
+    // Single pass, so values containing braces are never expanded again
+    let mut rendered = String::with_capacity(template.len() + item.url.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let placeholder = &rest[start..];
+        match placeholder
+            .find('}')
+            .and_then(|end| Some((end, value(&placeholder[1..end])?)))
+        {
+            Some((end, value)) => {
+                rendered.push_str(&value);
+                rest = &placeholder[end + 1..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &placeholder[1..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+
+    rendered
+}
+
+/// Renders `item` with the configured template.
+pub fn render_default(
+    config: &Config,
+    item: &HistoryItem,
+    alt: Option<&str>,
+) -> Result<String, AppError> {
+    let template = find(config, &config.settings_link_template)?;
+    Ok(render(&template.template, item, alt))
+}
+
+/// Puts `text` on the system clipboard. On X11 and Wayland the text stays
+/// available after the process exits only if a clipboard manager takes it over.
+pub fn copy_to_clipboard(text: &str) -> Result<(), AppError> {
+    Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(text))
+        .map_err(|e| {
+            AppError::new(
+                ErrorCode::ClipboardUnavailable,
+                format!("Failed to copy to clipboard: {}", e),
+            )
+        })
+}
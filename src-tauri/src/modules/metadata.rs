@@ -0,0 +1,336 @@
+use crate::error::AppError;
+use crate::models::MetadataPolicy;
+use exif::experimental::Writer;
+use exif::{Exif, In, Tag};
+use image::DynamicImage;
+use img_parts::riff::{RiffChunk, RiffContent};
+use img_parts::webp::CHUNK_EXIF;
+use img_parts::{Bytes, DynImage, ImageEXIF};
+use std::io::Cursor;
+
+/// IFD0 tag holding the orientation.
+const ORIENTATION_TAG: u16 = 0x0112;
+
+/// EXIF of a source image, if it is in a container kamadak-exif understands
+/// (JPEG, PNG, WebP, TIFF, HEIF).
+pub fn read_exif(bytes: &[u8]) -> Option<Exif> {
+    exif::Reader::new()
+        .read_from_container(&mut Cursor::new(bytes))
+        .ok()
+}
+
+/// EXIF orientation, 1 (upright) when missing or out of range.
+pub fn orientation(exif: Option<&Exif>) -> u32 {
+    exif.and_then(|exif| exif.get_field(Tag::Orientation, In::PRIMARY))
+        .and_then(|field| field.value.get_uint(0))
+        .filter(|orientation| (1..=8).contains(orientation))
+        .unwrap_or(1)
+}
+
+/// Rotates and mirrors decoded pixels so they display upright without EXIF.
+pub fn apply_orientation(img: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => img.fliph(),
+        3 => img.rotate180(),
+        4 => img.flipv(),
+        5 => img.rotate90().fliph(),
+        6 => img.rotate90(),
+        7 => img.rotate270().fliph(),
+        8 => img.rotate270(),
+        _ => img,
+    }
+}
+
+/// The EXIF block (raw TIFF) to write into processed output, or `None` to strip it.
+/// Orientation is always reset because the pixels have already been rotated.
+pub fn exif_for_output(exif: Option<&Exif>, policy: MetadataPolicy) -> Option<Vec<u8>> {
+    let exif = exif?;
+
+    match policy {
+        MetadataPolicy::Strip => None,
+        MetadataPolicy::KeepCopyright => {
+            let fields: Vec<_> = [Tag::Artist, Tag::Copyright]
+                .iter()
+                .filter_map(|tag| exif.get_field(*tag, In::PRIMARY))
+                .collect();
+            if fields.is_empty() {
+                return None;
+            }
+
+            let mut writer = Writer::new();
+            for field in fields {
+                writer.push_field(field);
+            }
+
+            let mut buffer = Cursor::new(Vec::new());
+            writer.write(&mut buffer, exif.little_endian()).ok()?;
+            Some(buffer.into_inner())
+        }
+        MetadataPolicy::KeepAll => {
+            let mut tiff = exif.buf().to_vec();
+            reset_orientation(&mut tiff, exif.little_endian());
+            Some(tiff)
+        }
+    }
+}
+
+/// Replaces whatever EXIF `bytes` carry with `exif`, leaving the pixel data as is.
+/// Containers without EXIF support (GIF, BMP, AVIF) are returned unchanged.
+pub fn embed_exif(bytes: Vec<u8>, exif: Option<Vec<u8>>) -> Result<Vec<u8>, AppError> {
+    let bytes = Bytes::from(bytes);
+    let mut image = match DynImage::from_bytes(bytes.clone())
+        .map_err(|e| AppError::image(format!("Failed to read image metadata: {}", e)))?
+    {
+        Some(image) => image,
+        None => return Ok(bytes.to_vec()),
+    };
+
+    if exif.is_none() && image.exif().is_none() {
+        return Ok(bytes.to_vec());
+    }
+
+    let exif = exif.map(Bytes::from);
+    image.set_exif(exif.clone());
+
+    // img-parts prefixes WebP EXIF chunks with "Exif\0\0", but the container
+    // spec and most readers expect the bare TIFF block
+    if let (DynImage::WebP(webp), Some(exif)) = (&mut image, exif) {
+        for chunk in webp
+            .chunks_mut()
+            .iter_mut()
+            .filter(|chunk| chunk.id() == CHUNK_EXIF)
+        {
+            *chunk = RiffChunk::new(CHUNK_EXIF, RiffContent::Data(exif.clone()));
+        }
+    }
+
+    Ok(image.encoder().bytes().to_vec())
+}
+
+/// Overwrites the IFD0 orientation entry with 1 (upright) in a raw TIFF block.
+fn reset_orientation(tiff: &mut [u8], little_endian: bool) {
+    let read_u16 = |tiff: &[u8], at: usize| {
+        let bytes: [u8; 2] = tiff.get(at..at + 2)?.try_into().ok()?;
+        Some(if little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    };
+    let read_u32 = |tiff: &[u8], at: usize| {
+        let bytes: [u8; 4] = tiff.get(at..at + 4)?.try_into().ok()?;
+        Some(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    };
+
+    let Some(ifd) = read_u32(tiff, 4).map(|offset| offset as usize) else {
+        return;
+    };
+    let entries = read_u16(tiff, ifd).unwrap_or(0) as usize;
+
+    // Each entry is tag (2), type (2), count (4) and an inline SHORT value
+    let entry = (0..entries)
+        .map(|index| ifd + 2 + index * 12)
+        .find(|&entry| read_u16(tiff, entry) == Some(ORIENTATION_TAG));
+
+    if let Some(value) = entry.and_then(|entry| tiff.get_mut(entry + 8..entry + 10)) {
+        let upright = if little_endian {
+            1u16.to_le_bytes()
+        } else {
+            1u16.to_be_bytes()
+        };
+        value.copy_from_slice(&upright);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    const FIXTURES: [&[u8]; 8] = [
+        include_bytes!("../../tests/fixtures/orientation/1.jpg"),
+        include_bytes!("../../tests/fixtures/orientation/2.jpg"),
+        include_bytes!("../../tests/fixtures/orientation/3.jpg"),
+        include_bytes!("../../tests/fixtures/orientation/4.jpg"),
+        include_bytes!("../../tests/fixtures/orientation/5.jpg"),
+        include_bytes!("../../tests/fixtures/orientation/6.jpg"),
+        include_bytes!("../../tests/fixtures/orientation/7.jpg"),
+        include_bytes!("../../tests/fixtures/orientation/8.jpg"),
+    ];
+
+    /// Quadrant centres of the upright 24×16 image and their colours.
+    const QUADRANTS: [((u32, u32), [u8; 3]); 4] = [
+        ((6, 4), [255, 0, 0]),
+        ((18, 4), [0, 255, 0]),
+        ((6, 12), [0, 0, 255]),
+        ((18, 12), [255, 255, 255]),
+    ];
+
+    fn fixtures() -> impl Iterator<Item = (u32, &'static [u8])> {
+        (1..).zip(FIXTURES)
+    }
+
+    fn ascii(exif: &Exif, tag: Tag) -> Option<String> {
+        exif.get_field(tag, In::PRIMARY)
+            .map(|field| field.display_value().to_string())
+    }
+
+    fn output_exif(bytes: &[u8], policy: MetadataPolicy) -> Option<Exif> {
+        let exif = exif_for_output(read_exif(bytes).as_ref(), policy);
+        read_exif(&embed_exif(bytes.to_vec(), exif).unwrap())
+    }
+
+    #[test]
+    fn reads_orientation_of_each_fixture() {
+        for (expected, bytes) in fixtures() {
+            assert_eq!(orientation(read_exif(bytes).as_ref()), expected);
+        }
+        assert_eq!(orientation(None), 1);
+    }
+
+    #[test]
+    fn applying_orientation_makes_each_fixture_upright() {
+        for (orientation, bytes) in fixtures() {
+            let img = image::load_from_memory(bytes).unwrap();
+            let stored = if orientation >= 5 { (16, 24) } else { (24, 16) };
+            assert_eq!(
+                (img.width(), img.height()),
+                stored,
+                "orientation {}",
+                orientation
+            );
+
+            let upright = apply_orientation(img, orientation).to_rgb8();
+            assert_eq!(
+                upright.dimensions(),
+                (24, 16),
+                "orientation {}",
+                orientation
+            );
+
+            for ((x, y), expected) in QUADRANTS {
+                let Rgb(actual) = *upright.get_pixel(x, y);
+                let close = actual.iter().zip(expected).all(|(a, e)| a.abs_diff(e) < 48);
+                assert!(
+                    close,
+                    "orientation {}: pixel ({}, {}) is {:?}, expected {:?}",
+                    orientation, x, y, actual, expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn strip_removes_all_exif() {
+        for (orientation, bytes) in fixtures() {
+            assert!(
+                exif_for_output(read_exif(bytes).as_ref(), MetadataPolicy::Strip).is_none(),
+                "orientation {}",
+                orientation
+            );
+            assert!(output_exif(bytes, MetadataPolicy::Strip).is_none());
+        }
+    }
+
+    #[test]
+    fn keep_copyright_keeps_only_artist_and_copyright() {
+        for (orientation, bytes) in fixtures() {
+            let exif = output_exif(bytes, MetadataPolicy::KeepCopyright).unwrap();
+
+            assert_eq!(ascii(&exif, Tag::Artist).as_deref(), Some("\"Jane Doe\""));
+            assert_eq!(
+                ascii(&exif, Tag::Copyright).as_deref(),
+                Some("\"CC BY 4.0\"")
+            );
+            assert!(
+                ascii(&exif, Tag::Make).is_none(),
+                "orientation {}",
+                orientation
+            );
+            assert!(exif.get_field(Tag::Orientation, In::PRIMARY).is_none());
+        }
+    }
+
+    #[test]
+    fn keep_copyright_without_those_tags_strips() {
+        let mut writer = Writer::new();
+        let make = exif::Field {
+            tag: Tag::Make,
+            ifd_num: In::PRIMARY,
+            value: exif::Value::Ascii(vec![b"Camera".to_vec()]),
+        };
+        writer.push_field(&make);
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        let bytes = embed_exif(FIXTURES[0].to_vec(), Some(tiff.into_inner())).unwrap();
+
+        assert!(output_exif(&bytes, MetadataPolicy::KeepCopyright).is_none());
+    }
+
+    #[test]
+    fn keep_all_keeps_tags_and_resets_orientation() {
+        for (orientation, bytes) in fixtures() {
+            let exif = output_exif(bytes, MetadataPolicy::KeepAll).unwrap();
+
+            assert_eq!(
+                ascii(&exif, Tag::Make).as_deref(),
+                Some("\"OptiBridge Test\"")
+            );
+            assert_eq!(ascii(&exif, Tag::Artist).as_deref(), Some("\"Jane Doe\""));
+            assert_eq!(
+                ascii(&exif, Tag::Copyright).as_deref(),
+                Some("\"CC BY 4.0\"")
+            );
+            assert_eq!(
+                super::orientation(Some(&exif)),
+                1,
+                "orientation {}",
+                orientation
+            );
+        }
+    }
+
+    #[test]
+    fn no_source_exif_means_none_for_every_policy() {
+        for policy in [
+            MetadataPolicy::Strip,
+            MetadataPolicy::KeepCopyright,
+            MetadataPolicy::KeepAll,
+        ] {
+            assert!(exif_for_output(None, policy).is_none());
+        }
+    }
+
+    #[test]
+    fn keep_all_reaches_every_output_format_but_avif() {
+        use crate::models::OutputFormat;
+        use crate::modules::image_processor::ImageProcessor;
+
+        for format in [OutputFormat::Jpeg, OutputFormat::Webp, OutputFormat::Png] {
+            let output = ImageProcessor::new(2048)
+                .with_output_format(format)
+                .with_metadata_policy(MetadataPolicy::KeepAll)
+                .process_from_bytes(FIXTURES[5])
+                .unwrap();
+            let exif = read_exif(&output).unwrap_or_else(|| panic!("{:?}", format));
+            assert_eq!(ascii(&exif, Tag::Artist).as_deref(), Some("\"Jane Doe\""));
+            assert_eq!(super::orientation(Some(&exif)), 1);
+        }
+
+        // the Settings hint says AVIF output never carries metadata
+        let avif = ImageProcessor::new(2048)
+            .with_output_format(OutputFormat::Avif)
+            .with_avif(70, 10)
+            .with_metadata_policy(MetadataPolicy::KeepAll)
+            .process_from_bytes(FIXTURES[5])
+            .unwrap();
+        assert_eq!(ImageProcessor::detect_extension(&avif), "avif");
+        assert!(read_exif(&avif).is_none());
+        let exif = exif_for_output(read_exif(FIXTURES[5]).as_ref(), MetadataPolicy::KeepAll);
+        assert_eq!(embed_exif(avif.clone(), exif).unwrap(), avif);
+    }
+}
@@ -0,0 +1,331 @@
+use crate::error::{AppError, ErrorCode};
+use rusqlite::Connection;
+
+/// One schema step. `version` is the `PRAGMA user_version` after it runs.
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+/// Ordered schema history for `history.db`. Never edit a released step; append a new one.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create uploads table",
+        sql: "CREATE TABLE uploads (
+                id TEXT PRIMARY KEY,
+                provider TEXT NOT NULL,
+                original_name TEXT NOT NULL,
+                url TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                thumbnail_base64 TEXT NOT NULL
+            );",
+    },
+    Migration {
+        version: 2,
+        description: "add upload status",
+        sql: "ALTER TABLE uploads ADD COLUMN status TEXT NOT NULL DEFAULT 'uploaded';",
+    },
+    Migration {
+        version: 3,
+        description: "add provider object identifiers",
+        sql: "ALTER TABLE uploads ADD COLUMN public_id TEXT;
+              ALTER TABLE uploads ADD COLUMN bucket TEXT;
+              ALTER TABLE uploads ADD COLUMN object_key TEXT;
+              ALTER TABLE uploads ADD COLUMN etag TEXT;
+              ALTER TABLE uploads ADD COLUMN size_bytes INTEGER;
+              ALTER TABLE uploads ADD COLUMN width INTEGER;
+              ALTER TABLE uploads ADD COLUMN height INTEGER;",
+    },
+    Migration {
+        version: 4,
+        description: "group responsive variants",
+        sql: "ALTER TABLE uploads ADD COLUMN group_id TEXT;
+              CREATE INDEX idx_uploads_group_id ON uploads (group_id);",
+    },
+    Migration {
+        version: 5,
+        description: "create offline upload queue",
+        sql: "CREATE TABLE pending_uploads (
+                id TEXT PRIMARY KEY,
+                provider TEXT NOT NULL,
+                original_name TEXT NOT NULL,
+                blob_name TEXT NOT NULL,
+                size_bytes INTEGER NOT NULL,
+                created_at INTEGER NOT NULL,
+                attempts INTEGER NOT NULL DEFAULT 0,
+                last_attempt_at INTEGER,
+                last_error TEXT
+            );",
+    },
+    Migration {
+        version: 6,
+        description: "record accounts and profiles",
+        sql: "ALTER TABLE uploads ADD COLUMN account_id TEXT;
+              ALTER TABLE pending_uploads ADD COLUMN profile_id TEXT;",
+    },
+];
+
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+/// Reads the schema version. Databases written before versioning have
+/// `user_version` 0, so their version is inferred from the columns present.
+pub fn current_version(conn: &Connection) -> Result<u32, AppError> {
+    let version: u32 = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| AppError::database("Failed to read schema version", e))?;
+
+    if version > 0 || !has_table(conn, "uploads")? {
+        return Ok(version);
+    }
+
+    let inferred = if has_column(conn, "uploads", "public_id")? {
+        3
+    } else if has_column(conn, "uploads", "status")? {
+        2
+    } else {
+        1
+    };
+
+    Ok(inferred)
+}
+
+/// Applies every step newer than the current version, each in its own transaction.
+pub fn run(conn: &mut Connection) -> Result<(), AppError> {
+    let starting_version = current_version(conn)?;
+
+    if starting_version > latest_version() {
+        return Err(AppError::new(
+            ErrorCode::Database,
+            format!(
+                "Database schema version {} is newer than this app supports ({})",
+                starting_version,
+                latest_version()
+            ),
+        ));
+    }
+
+    let mut version = starting_version;
+    for migration in MIGRATIONS.iter().filter(|m| m.version > starting_version) {
+        let tx = conn
+            .transaction()
+            .map_err(|e| AppError::database("Failed to start migration", e))?;
+
+        tx.execute_batch(migration.sql).map_err(|e| {
+            let context = format!(
+                "Migration to version {} ({}) failed",
+                migration.version, migration.description
+            );
+            AppError::database(&context, e)
+        })?;
+        tx.pragma_update(None, "user_version", migration.version)
+            .map_err(|e| AppError::database("Failed to update schema version", e))?;
+
+        tx.commit()
+            .map_err(|e| AppError::database("Failed to commit migration", e))?;
+
+        version = migration.version;
+    }
+
+    // Pin the inferred version of legacy databases that were already current
+    conn.pragma_update(None, "user_version", version)
+        .map_err(|e| AppError::database("Failed to update schema version", e))?;
+
+    Ok(())
+}
+
+fn has_table(conn: &Connection, table: &str) -> Result<bool, AppError> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        [table],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|count| count > 0)
+    .map_err(|e| AppError::database("Failed to inspect schema", e))
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool, AppError> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA table_info({})", table))
+        .map_err(|e| AppError::database("Failed to inspect schema", e))?;
+
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))
+        .map_err(|e| AppError::database("Failed to inspect schema", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::database("Failed to inspect schema", e))?;
+
+    Ok(columns.iter().any(|name| name == column))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::database::Database;
+    use std::path::{Path, PathBuf};
+
+    /// A database as written by the app at schema `version`, holding one
+    /// upload (and one pending upload once that table exists). Legacy
+    /// databases predate `user_version` and leave it at 0.
+    fn build_fixture(conn: &Connection, version: u32, versioned: bool) {
+        for migration in MIGRATIONS.iter().filter(|m| m.version <= version) {
+            conn.execute_batch(migration.sql).unwrap();
+        }
+        if versioned {
+            conn.pragma_update(None, "user_version", version).unwrap();
+        }
+
+        conn.execute(
+            "INSERT INTO uploads (id, provider, original_name, url, created_at, thumbnail_base64)
+             VALUES ('upload-1', 'cloudinary', 'shot.png', 'https://example.com/shot.webp', 1700000000, 'thumb')",
+            [],
+        )
+        .unwrap();
+        if version >= 5 {
+            conn.execute(
+                "INSERT INTO pending_uploads (id, provider, original_name, blob_name, size_bytes, created_at)
+                 VALUES ('pending-1', 'r2', 'diagram.png', 'pending-1.bin', 42, 1700000001)",
+                [],
+            )
+            .unwrap();
+        }
+    }
+
+    fn fixture(version: u32, versioned: bool) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        build_fixture(&conn, version, versioned);
+        conn
+    }
+
+    fn user_version(conn: &Connection) -> u32 {
+        conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    fn assert_upgraded(conn: &mut Connection, from: u32) {
+        run(conn).unwrap();
+
+        assert_eq!(
+            user_version(conn),
+            latest_version(),
+            "from version {}",
+            from
+        );
+        let (url, status, account_id): (String, String, Option<String>) = conn
+            .query_row(
+                "SELECT url, status, account_id FROM uploads WHERE id = 'upload-1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(url, "https://example.com/shot.webp");
+        assert_eq!(status, "uploaded");
+        assert_eq!(account_id, None);
+
+        let pending: i64 = conn
+            .query_row("SELECT COUNT(*) FROM pending_uploads", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(pending, i64::from(from >= 5), "from version {}", from);
+    }
+
+    fn temp_db(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("opti-bridge-{}-{}", name, uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("history.db")
+    }
+
+    fn backup_of(db_path: &Path, version: u32) -> PathBuf {
+        db_path.with_file_name(format!("history.db.v{}.bak", version))
+    }
+
+    #[test]
+    fn infers_legacy_versions() {
+        for version in 1..=3 {
+            assert_eq!(current_version(&fixture(version, false)).unwrap(), version);
+        }
+        let empty = Connection::open_in_memory().unwrap();
+        assert_eq!(current_version(&empty).unwrap(), 0);
+    }
+
+    #[test]
+    fn upgrades_legacy_databases() {
+        for version in 1..=3 {
+            assert_upgraded(&mut fixture(version, false), version);
+        }
+    }
+
+    #[test]
+    fn upgrades_every_prior_version() {
+        for version in 1..latest_version() {
+            assert_upgraded(&mut fixture(version, true), version);
+        }
+    }
+
+    #[test]
+    fn creates_new_databases() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run(&mut conn).unwrap();
+        assert_eq!(user_version(&conn), latest_version());
+    }
+
+    #[test]
+    fn current_databases_are_left_alone() {
+        let mut conn = fixture(latest_version(), true);
+        run(&mut conn).unwrap();
+        assert_upgraded(&mut conn, latest_version());
+    }
+
+    #[test]
+    fn rejects_newer_databases() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", latest_version() + 1)
+            .unwrap();
+        assert!(run(&mut conn).is_err());
+    }
+
+    #[test]
+    fn open_backs_up_before_migrating() {
+        let cases = (1..=3)
+            .map(|version| (version, false))
+            .chain((1..latest_version()).map(|version| (version, true)));
+
+        for (version, versioned) in cases {
+            let db_path = temp_db("migrate");
+            build_fixture(&Connection::open(&db_path).unwrap(), version, versioned);
+
+            let db = Database::open(&db_path).unwrap();
+            let history = db.get_history().unwrap();
+            assert_eq!(history.len(), 1);
+            assert_eq!(history[0].original_name, "shot.png");
+
+            let backup = Connection::open(backup_of(&db_path, version)).unwrap();
+            assert_eq!(current_version(&backup).unwrap(), version);
+            let rows: i64 = backup
+                .query_row("SELECT COUNT(*) FROM uploads", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(rows, 1);
+
+            std::fs::remove_dir_all(db_path.parent().unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn open_skips_backup_for_new_and_current_databases() {
+        let db_path = temp_db("fresh");
+        Database::open(&db_path).unwrap();
+        Database::open(&db_path).unwrap();
+
+        let files = std::fs::read_dir(db_path.parent().unwrap())
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with(".bak"))
+            .count();
+        assert_eq!(files, 0);
+
+        std::fs::remove_dir_all(db_path.parent().unwrap()).unwrap();
+    }
+}
@@ -1,4 +1,20 @@
-pub mod image_processor;
-pub mod database;
+pub mod animation;
+pub mod app_dirs;
+pub mod config_bundle;
+pub mod config_env;
 pub mod config_manager;
-
+pub mod config_schema;
+pub mod database;
+pub mod image_cache;
+pub mod image_processor;
+pub mod link_templates;
+pub mod metadata;
+pub mod migrations;
+pub mod offline_queue;
+pub mod profiles;
+pub mod responsive;
+pub mod secret_store;
+pub mod svg;
+pub mod upload_pipeline;
+pub mod upload_queue;
+pub mod upload_server;
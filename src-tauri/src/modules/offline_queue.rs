@@ -0,0 +1,249 @@
+//! Uploads that could not be delivered, kept until they can be: a row in
+//! `pending_uploads` plus the processed bytes in `pending/<id>.<ext>` under the
+//! app data directory, so they survive restarts. Only uploads that failed for
+//! lack of a connection are queued, and a background task retries them. Items
+//! whose retry fails for another reason wait for the user to fix the cause and
+//! call `retry_pending_uploads`.
+
+use crate::error::{self, AppError, ErrorCode};
+use crate::models::{PendingFlushResult, PendingUpload};
+use crate::modules::app_dirs;
+use crate::modules::config_manager::ConfigManager;
+use crate::modules::database::Database;
+use crate::modules::image_processor::ImageProcessor;
+use crate::modules::profiles::{self, UploadTarget};
+use crate::modules::upload_pipeline;
+use crate::state::AppState;
+use chrono::Utc;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::async_runtime;
+use tauri::{AppHandle, Manager};
+use uuid::Uuid;
+
+/// Emitted whenever items are added, uploaded, retried or discarded.
+pub const PENDING_EVENT: &str = "pending-uploads-changed";
+
+/// How often the background task checks whether the connection is back.
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Serializes passes over the queue so an item is never uploaded twice.
+#[derive(Default)]
+pub struct OfflineQueue {
+    flushing: tokio::sync::Mutex<()>,
+}
+
+impl OfflineQueue {
+    /// Retries queued items, oldest first, and records each outcome. Unless
+    /// `all` is set only items that last failed with a network error are
+    /// tried, and the pass stops at the first one that fails the same way.
+    pub async fn flush(
+        &self,
+        app_handle: &AppHandle,
+        all: bool,
+    ) -> Result<PendingFlushResult, AppError> {
+        let _flushing = self.flushing.lock().await;
+
+        let items = Database::new(app_handle)?.get_pending()?;
+        let mut result = PendingFlushResult::default();
+        if items.is_empty() {
+            return Ok(result);
+        }
+
+        let config = ConfigManager::new(app_handle)?.load_config()?;
+        let state = app_handle.state::<AppState>();
+        let dir = pending_dir(app_handle)?;
+        let mut offline = false;
+        let mut attempted = false;
+
+        for item in items {
+            let waits_for_network = item
+                .last_error
+                .as_ref()
+                .is_none_or(|error| error.code == ErrorCode::Network);
+            if offline || !(all || waits_for_network) {
+                result.remaining += 1;
+                continue;
+            }
+
+            attempted = true;
+            // The profile may have been removed since; that is recorded like any other failure
+            let profile_id = item.profile_id.as_deref().unwrap_or(&item.provider);
+            let uploaded = match profiles::resolve(&state.uploaders, &config, Some(profile_id)) {
+                Ok(target) => match fs::read(dir.join(&item.blob_name)) {
+                    Ok(image_data) => {
+                        upload_pipeline::upload_processed(
+                            &state.uploaders,
+                            &target,
+                            &image_data,
+                            &item.original_name,
+                        )
+                        .await
+                    }
+                    Err(e) => Err(AppError::io("Failed to read pending upload", e)),
+                },
+                Err(e) => Err(e),
+            };
+
+            let db = Database::new(app_handle)?;
+            match uploaded {
+                Ok(history_item) => {
+                    // Dequeue first: a failed history write must not upload it again
+                    db.delete_pending(&item.id)?;
+                    let _ = fs::remove_file(dir.join(&item.blob_name));
+                    result.uploaded += 1;
+
+                    if let Err(e) = db.insert_history(&history_item) {
+                        let message = format!(
+                            "{} was uploaded to {} but could not be added to history: {}",
+                            item.original_name, history_item.url, e.message
+                        );
+                        error::report(app_handle, &AppError { message, ..e });
+                    }
+                }
+                Err(e) => {
+                    // Still offline: the remaining items would fail the same way
+                    offline = e.code == ErrorCode::Network;
+                    db.record_pending_attempt(&item.id, Utc::now().timestamp(), &e)?;
+                    result.remaining += 1;
+                }
+            }
+        }
+
+        if attempted {
+            emit_changed(app_handle);
+        }
+
+        Ok(result)
+    }
+}
+
+/// Saves `image_data` for a later attempt after an upload failed for lack of
+/// a connection, and returns the error pointing at the queued item. Other
+/// failures are returned unchanged: a retry would not fix them, and a
+/// provider that answered oddly may have stored the image already. When the
+/// item cannot be saved, the error says so.
+pub fn park(
+    app_handle: &AppHandle,
+    target: &UploadTarget,
+    original_name: &str,
+    image_data: &[u8],
+    error: AppError,
+) -> AppError {
+    if error.code != ErrorCode::Network {
+        return error;
+    }
+
+    match save(app_handle, target, original_name, image_data, &error) {
+        Ok(item) => {
+            emit_changed(app_handle);
+
+            let message = format!(
+                "{}. Saved to pending uploads; it will be uploaded when the connection is back",
+                error.message
+            );
+            AppError { message, ..error }.with_pending_id(&item.id)
+        }
+        Err(e) => {
+            let message = format!(
+                "{}. It could not be saved to pending uploads: {}",
+                error.message, e.message
+            );
+            AppError { message, ..error }
+        }
+    }
+}
+
+pub fn list(app_handle: &AppHandle) -> Result<Vec<PendingUpload>, AppError> {
+    Database::new(app_handle)?.get_pending()
+}
+
+/// Drops a queued item and its bytes without uploading it.
+pub fn discard(app_handle: &AppHandle, id: &str) -> Result<(), AppError> {
+    let db = Database::new(app_handle)?;
+    let item = db
+        .get_pending_item(id)?
+        .ok_or_else(|| AppError::not_found("Pending upload not found"))?;
+
+    db.delete_pending(id)?;
+    let _ = fs::remove_file(pending_dir(app_handle)?.join(&item.blob_name));
+    emit_changed(app_handle);
+
+    Ok(())
+}
+
+/// Retries network failures every `RETRY_INTERVAL`, starting right away so
+/// items left from the last session go out on launch. A failing pass is
+/// reported once, not again every interval while it keeps failing the same way.
+pub fn spawn_worker(app_handle: AppHandle) {
+    async_runtime::spawn(async move {
+        let mut last_error: Option<String> = None;
+        loop {
+            let queue = &app_handle.state::<AppState>().offline_queue;
+            match queue.flush(&app_handle, false).await {
+                Ok(_) => last_error = None,
+                Err(e) => {
+                    if last_error.as_deref() != Some(e.message.as_str()) {
+                        let message = format!("Offline queue: {}", e.message);
+                        error::report(
+                            &app_handle,
+                            &AppError {
+                                message,
+                                ..e.clone()
+                            },
+                        );
+                        last_error = Some(e.message);
+                    }
+                }
+            }
+            tokio::time::sleep(RETRY_INTERVAL).await;
+        }
+    });
+}
+
+fn save(
+    app_handle: &AppHandle,
+    target: &UploadTarget,
+    original_name: &str,
+    image_data: &[u8],
+    error: &AppError,
+) -> Result<PendingUpload, AppError> {
+    let id = Uuid::new_v4().to_string();
+    let blob_name = format!("{}.{}", id, ImageProcessor::detect_extension(image_data));
+    let blob_path = pending_dir(app_handle)?.join(&blob_name);
+
+    fs::write(&blob_path, image_data)
+        .map_err(|e| AppError::io("Failed to save pending upload", e))?;
+
+    let item = PendingUpload {
+        id,
+        provider: target.provider.clone(),
+        original_name: original_name.to_string(),
+        blob_name,
+        size_bytes: image_data.len() as i64,
+        created_at: Utc::now().timestamp(),
+        attempts: 0,
+        last_attempt_at: None,
+        last_error: Some(error.clone()),
+        profile_id: Some(target.profile_id.clone()),
+    };
+
+    if let Err(e) = Database::new(app_handle).and_then(|db| db.insert_pending(&item)) {
+        let _ = fs::remove_file(&blob_path);
+        return Err(e);
+    }
+
+    Ok(item)
+}
+
+fn pending_dir(app_handle: &AppHandle) -> Result<PathBuf, AppError> {
+    let dir = app_dirs::app_data_dir(app_handle)?.join("pending");
+    fs::create_dir_all(&dir)
+        .map_err(|e| AppError::io("Failed to create pending upload directory", e))?;
+    Ok(dir)
+}
+
+fn emit_changed(app_handle: &AppHandle) {
+    let _ = app_handle.emit_all(PENDING_EVENT, ());
+}
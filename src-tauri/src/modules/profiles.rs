@@ -0,0 +1,187 @@
+//! Upload profiles: which account an upload goes through and how the image is
+//! processed first. Uploaders and the image processor keep reading a plain
+//! `Config`, so a profile resolves to an `UploadTarget` whose config has the
+//! account's fields and the profile's settings written into it.
+//!
+//! Ids are looked up as a profile, then as a named account, then as a
+//! provider id, which stands for that provider's own settings. Uploads
+//! recorded before profiles existed keep working that way.
+
+use crate::error::{AppError, ErrorCode};
+use crate::models::{Config, ProfileInfo, ProviderAccount, ProviderFieldKind, UploadProfile};
+use crate::uploaders::registry::UploaderRegistry;
+use serde_json::Value;
+
+/// Where and how to upload, with `config` ready for `UploaderRegistry::build`
+/// and `ImageProcessor::from_config`.
+#[derive(Clone)]
+pub struct UploadTarget {
+    pub profile_id: String,
+    pub provider: String,
+    /// `None` for the provider's own settings.
+    pub account_id: Option<String>,
+    pub config: Config,
+    /// The profile changes how images are processed, so bytes processed with
+    /// the global settings have to be processed again.
+    pub overrides_processing: bool,
+}
+
+/// The profile used when none is picked.
+pub fn default_profile_id(config: &Config) -> &str {
+    if config.settings_default_profile.is_empty() {
+        &config.settings_default_provider
+    } else {
+        &config.settings_default_profile
+    }
+}
+
+/// Resolves `profile_id`, or the default profile when it is `None`.
+pub fn resolve(
+    registry: &UploaderRegistry,
+    config: &Config,
+    profile_id: Option<&str>,
+) -> Result<UploadTarget, AppError> {
+    let profile_id = profile_id.unwrap_or_else(|| default_profile_id(config));
+
+    let Some(profile) = config
+        .profiles
+        .iter()
+        .find(|profile| profile.id == profile_id)
+    else {
+        return resolve_account(registry, config, profile_id);
+    };
+
+    let mut target = resolve_account(registry, config, &profile.account_id)?;
+    target.profile_id = profile.id.clone();
+    target.overrides_processing = apply_profile(&mut target.config, profile);
+    Ok(target)
+}
+
+/// Resolves a named account or a provider id, with the global processing settings.
+pub fn resolve_account(
+    registry: &UploaderRegistry,
+    config: &Config,
+    account_id: &str,
+) -> Result<UploadTarget, AppError> {
+    if let Some(account) = config
+        .accounts
+        .iter()
+        .find(|account| account.id == account_id)
+    {
+        return Ok(UploadTarget {
+            profile_id: account.id.clone(),
+            provider: account.provider.clone(),
+            account_id: Some(account.id.clone()),
+            config: apply_account(registry, config, account)?,
+            overrides_processing: false,
+        });
+    }
+
+    if registry.info(account_id).is_none() {
+        return Err(
+            AppError::not_found(format!("Unknown profile or account: {}", account_id))
+                .with_fields(vec!["settings_default_profile".to_string()]),
+        );
+    }
+
+    Ok(UploadTarget {
+        profile_id: account_id.to_string(),
+        provider: account_id.to_string(),
+        account_id: None,
+        config: config.clone(),
+        overrides_processing: false,
+    })
+}
+
+/// Everything that can be uploaded to: each provider's own settings, each
+/// named account, then each profile.
+pub fn list(registry: &UploaderRegistry, config: &Config) -> Vec<ProfileInfo> {
+    let default_id = default_profile_id(config);
+    let info = |id: &str, name: &str, provider: &str, account_id: &str| ProfileInfo {
+        id: id.to_string(),
+        name: name.to_string(),
+        provider: provider.to_string(),
+        account_id: account_id.to_string(),
+        is_default: id == default_id,
+    };
+
+    let providers = registry
+        .providers()
+        .into_iter()
+        .map(|provider| info(&provider.id, &provider.name, &provider.id, &provider.id));
+    let accounts = config
+        .accounts
+        .iter()
+        .map(|account| info(&account.id, &account.name, &account.provider, &account.id));
+    let profiles = config.profiles.iter().filter_map(|profile| {
+        let provider = config
+            .accounts
+            .iter()
+            .find(|account| account.id == profile.account_id)
+            .map_or(profile.account_id.as_str(), |account| {
+                account.provider.as_str()
+            });
+        registry
+            .info(provider)
+            .map(|_| info(&profile.id, &profile.name, provider, &profile.account_id))
+    });
+
+    providers.chain(accounts).chain(profiles).collect()
+}
+
+/// `config` with the provider's fields replaced by the account's. Fields the
+/// account leaves out are empty rather than taken from the provider's own settings.
+fn apply_account(
+    registry: &UploaderRegistry,
+    config: &Config,
+    account: &ProviderAccount,
+) -> Result<Config, AppError> {
+    let info = registry.info(&account.provider).ok_or_else(|| {
+        AppError::new(
+            ErrorCode::UnknownProvider,
+            format!(
+                "Account {} uses an unknown provider: {}",
+                account.name, account.provider
+            ),
+        )
+    })?;
+
+    let mut values = serde_json::to_value(config)
+        .map_err(|e| AppError::internal(format!("Failed to serialize config: {}", e)))?;
+    for field in &info.fields {
+        let value = account
+            .fields
+            .get(&field.key)
+            .cloned()
+            .unwrap_or(match field.kind {
+                ProviderFieldKind::Toggle => Value::Bool(false),
+                ProviderFieldKind::Text | ProviderFieldKind::Secret => Value::String(String::new()),
+            });
+        values[field.key.as_str()] = value;
+    }
+
+    serde_json::from_value(values).map_err(|e| {
+        AppError::new(
+            ErrorCode::InvalidConfig,
+            format!("Account {} has an invalid value: {}", account.name, e),
+        )
+        .with_provider(&account.provider)
+    })
+}
+
+/// Writes the settings `profile` sets into `config`; returns whether it set any.
+fn apply_profile(config: &mut Config, profile: &UploadProfile) -> bool {
+    if let Some(max_width) = profile.max_width {
+        config.settings_max_width = max_width;
+    }
+    if let Some(format) = profile.output_format {
+        config.settings_output_format = Some(format);
+    }
+    if let Some(quality) = profile.quality {
+        config.settings_webp_quality = quality;
+        config.settings_jpeg_quality = quality;
+        config.settings_avif_quality = quality;
+    }
+
+    profile.max_width.is_some() || profile.output_format.is_some() || profile.quality.is_some()
+}
@@ -0,0 +1,204 @@
+use crate::error::AppError;
+use crate::models::HistoryItem;
+use crate::uploaders::content_type;
+
+/// Formats browsers may not support, in the order `<source>` elements should
+/// list them. Anything else is treated as a universally supported fallback.
+const MODERN_TYPES: &[&str] = &["image/avif", "image/webp"];
+
+/// The configured widths plus their 2x versions when `retina` is set.
+pub fn variant_widths(widths: &[u32], retina: bool) -> Vec<u32> {
+    let mut all: Vec<u32> = widths.iter().copied().filter(|&width| width > 0).collect();
+    if retina {
+        let doubled: Vec<u32> = all.iter().map(|width| width.saturating_mul(2)).collect();
+        all.extend(doubled);
+    }
+
+    all.sort_unstable();
+    all.dedup();
+    all
+}
+
+/// `<img srcset sizes>` for a single format, or a `<picture>` with a
+/// `<source>` per modern format and an `<img>` fallback.
+pub fn render_html(items: &[HistoryItem], sizes: &str, alt: &str) -> Result<String, AppError> {
+    let mut by_type: Vec<(&'static str, Vec<&HistoryItem>)> = Vec::new();
+    for item in items {
+        let mime = content_type(url_path(&item.url));
+        match by_type.iter_mut().find(|(existing, _)| *existing == mime) {
+            Some((_, group)) => group.push(item),
+            None => by_type.push((mime, vec![item])),
+        }
+    }
+
+    for (_, group) in &mut by_type {
+        group.sort_by_key(|item| item.width.unwrap_or(0));
+    }
+
+    // Modern formats first in preference order, the fallback last
+    by_type.sort_by_key(|(mime, _)| {
+        MODERN_TYPES
+            .iter()
+            .position(|modern| modern == mime)
+            .unwrap_or(MODERN_TYPES.len())
+    });
+
+    let (_, fallback) = by_type
+        .last()
+        .ok_or_else(|| AppError::not_found("Image set has no variants"))?;
+    let largest = fallback
+        .last()
+        .copied()
+        .ok_or_else(|| AppError::not_found("Image set has no variants"))?;
+
+    let mut img = format!(
+        "<img src=\"{}\" srcset=\"{}\" sizes=\"{}\"",
+        escape(&largest.url),
+        escape(&srcset(fallback)),
+        escape(sizes)
+    );
+    if let (Some(width), Some(height)) = (largest.width, largest.height) {
+        img.push_str(&format!(" width=\"{}\" height=\"{}\"", width, height));
+    }
+    img.push_str(&format!(
+        " alt=\"{}\" loading=\"lazy\" decoding=\"async\">",
+        escape(alt)
+    ));
+
+    if by_type.len() == 1 {
+        return Ok(img);
+    }
+
+    let mut html = String::from("<picture>\n");
+    for (mime, group) in &by_type[..by_type.len() - 1] {
+        html.push_str(&format!(
+            "  <source type=\"{}\" srcset=\"{}\" sizes=\"{}\">\n",
+            mime,
+            escape(&srcset(group)),
+            escape(sizes)
+        ));
+    }
+    html.push_str(&format!("  {}\n</picture>", img));
+
+    Ok(html)
+}
+
+fn srcset(items: &[&HistoryItem]) -> String {
+    items
+        .iter()
+        .map(|item| match item.width {
+            Some(width) => format!("{} {}w", item.url, width),
+            None => item.url.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The path part of a URL, so the extension can be read past query strings.
+fn url_path(url: &str) -> &str {
+    url.split(['?', '#']).next().unwrap_or(url)
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variant_widths_drop_zero_and_add_retina_sizes() {
+        assert_eq!(variant_widths(&[800, 0, 400, 800], false), vec![400, 800]);
+        assert_eq!(variant_widths(&[800, 0, 400], true), vec![400, 800, 1600]);
+        assert!(variant_widths(&[0], true).is_empty());
+    }
+
+    fn item(url: &str, width: Option<u32>) -> HistoryItem {
+        HistoryItem {
+            id: url.to_string(),
+            provider: "r2".to_string(),
+            original_name: "hero.jpg".to_string(),
+            url: url.to_string(),
+            created_at: 0,
+            thumbnail_base64: String::new(),
+            status: HistoryItem::STATUS_UPLOADED.to_string(),
+            public_id: None,
+            bucket: None,
+            object_key: None,
+            etag: None,
+            size_bytes: None,
+            width,
+            height: width.map(|width| width / 2),
+            group_id: Some("set".to_string()),
+            account_id: None,
+        }
+    }
+
+    #[test]
+    fn single_format_is_a_plain_img() {
+        let items = [
+            item("https://cdn.test/hero-800.jpg", Some(800)),
+            item("https://cdn.test/hero-400.jpg", Some(400)),
+        ];
+
+        assert_eq!(
+            render_html(&items, "100vw", "Hero").unwrap(),
+            "<img src=\"https://cdn.test/hero-800.jpg\" \
+             srcset=\"https://cdn.test/hero-400.jpg 400w, https://cdn.test/hero-800.jpg 800w\" \
+             sizes=\"100vw\" width=\"800\" height=\"400\" alt=\"Hero\" \
+             loading=\"lazy\" decoding=\"async\">"
+        );
+    }
+
+    #[test]
+    fn several_formats_make_a_picture_with_modern_sources_first() {
+        let items = [
+            item("https://cdn.test/hero-400.jpg", Some(400)),
+            item("https://cdn.test/hero-400.webp", Some(400)),
+            item("https://cdn.test/hero-400.avif?v=1", Some(400)),
+            item("https://cdn.test/hero-800.webp", Some(800)),
+        ];
+
+        let html = render_html(&items, "50vw", "Hero").unwrap();
+        let lines: Vec<&str> = html.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "<picture>");
+        assert_eq!(
+            lines[1],
+            "  <source type=\"image/avif\" srcset=\"https://cdn.test/hero-400.avif?v=1 400w\" sizes=\"50vw\">"
+        );
+        assert_eq!(
+            lines[2],
+            "  <source type=\"image/webp\" \
+             srcset=\"https://cdn.test/hero-400.webp 400w, https://cdn.test/hero-800.webp 800w\" \
+             sizes=\"50vw\">"
+        );
+        assert!(lines[3].starts_with("  <img src=\"https://cdn.test/hero-400.jpg\""));
+        assert_eq!(lines[4], "</picture>");
+    }
+
+    #[test]
+    fn attributes_are_escaped() {
+        let items = [item("https://cdn.test/a.png?x=1&y=\"2\"", Some(400))];
+
+        let html = render_html(&items, "(max-width: 600px) 100vw", "<Tom & \"Jerry\">").unwrap();
+        assert!(html.contains("src=\"https://cdn.test/a.png?x=1&amp;y=&quot;2&quot;\""));
+        assert!(html.contains("alt=\"&lt;Tom &amp; &quot;Jerry&quot;&gt;\""));
+        assert!(html.contains("sizes=\"(max-width: 600px) 100vw\""));
+    }
+
+    #[test]
+    fn srcset_without_widths_lists_bare_urls() {
+        let items = [item("https://cdn.test/hero.png", None)];
+
+        let html = render_html(&items, "100vw", "").unwrap();
+        assert!(html.contains("srcset=\"https://cdn.test/hero.png\""));
+        assert!(!html.contains(" width="));
+        assert!(render_html(&[], "100vw", "").is_err());
+    }
+}
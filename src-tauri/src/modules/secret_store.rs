@@ -0,0 +1,405 @@
+//! Where provider secrets live instead of `config.json`: the OS keyring
+//! (Keychain, Credential Manager, Secret Service) when one answers, otherwise
+//! `secrets.vault` next to the config, encrypted with a key derived from a
+//! passphrase (Argon2id + XChaCha20-Poly1305). Once a vault exists it stays in
+//! use, so secrets never end up split between the two.
+
+use crate::error::{AppError, ErrorCode};
+use crate::models::SecretBackend;
+use crate::modules::app_dirs::APP_IDENTIFIER;
+use argon2::Argon2;
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Stands in for a stored secret in the config handed to the webview. Saving
+/// it back leaves the secret unchanged.
+pub const REDACTED: &str = "********";
+
+/// Vault passphrase for the CLI and for sessions that never unlock it from Settings.
+pub const PASSPHRASE_ENV: &str = "OPTIBRIDGE_VAULT_PASSPHRASE";
+
+pub const VAULT_FILE: &str = "secrets.vault";
+
+const VAULT_VERSION: u32 = 1;
+
+pub enum SecretStore {
+    Keyring,
+    /// `passphrase` is `None` until the user unlocks the vault.
+    Vault {
+        path: PathBuf,
+        passphrase: Option<String>,
+    },
+}
+
+/// On-disk vault.
+#[derive(Serialize, Deserialize)]
+struct VaultFile {
+    version: u32,
+    #[serde(flatten)]
+    sealed: SealedSecrets,
+}
+
+/// Secrets encrypted under a passphrase, as kept in the vault and in exported
+/// config bundles. Key names are kept in the clear so Settings can show which
+/// secrets are set while the vault is locked.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SealedSecrets {
+    pub keys: Vec<String>,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl SealedSecrets {
+    /// Encrypts `secrets` under a fresh salt and nonce.
+    pub fn seal(passphrase: &str, secrets: &BTreeMap<String, String>) -> Result<Self, AppError> {
+        let mut salt = [0u8; 16];
+        let mut nonce = [0u8; 24];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+
+        let plaintext = serde_json::to_vec(secrets)
+            .map_err(|e| AppError::internal(format!("Failed to serialize secrets: {}", e)))?;
+        let ciphertext = cipher(passphrase, &salt)?
+            .encrypt(&XNonce::from(nonce), plaintext.as_slice())
+            .map_err(|_| AppError::new(ErrorCode::SecretStore, "Failed to encrypt secrets"))?;
+
+        Ok(Self {
+            keys: secrets.keys().cloned().collect(),
+            salt: general_purpose::STANDARD.encode(salt),
+            nonce: general_purpose::STANDARD.encode(nonce),
+            ciphertext: general_purpose::STANDARD.encode(ciphertext),
+        })
+    }
+
+    /// Decrypts the secrets; `Ok(None)` means the passphrase is wrong. An
+    /// `ErrorCode::SecretStore` error names the damaged part, for the caller
+    /// to put in context.
+    pub fn open(&self, passphrase: &str) -> Result<Option<BTreeMap<String, String>>, AppError> {
+        let damaged = |part: &str, detail: &dyn std::fmt::Display| {
+            AppError::new(
+                ErrorCode::SecretStore,
+                format!("damaged {}: {}", part, detail),
+            )
+        };
+        let decode = |part: &str, value: &str| {
+            general_purpose::STANDARD
+                .decode(value)
+                .map_err(|e| damaged(part, &e))
+        };
+        let salt = decode("salt", &self.salt)?;
+        let nonce: [u8; 24] = decode("nonce", &self.nonce)?
+            .try_into()
+            .map_err(|bytes: Vec<u8>| damaged("nonce", &format!("{} bytes", bytes.len())))?;
+        let ciphertext = decode("ciphertext", &self.ciphertext)?;
+
+        let Ok(plaintext) =
+            cipher(passphrase, &salt)?.decrypt(&XNonce::from(nonce), ciphertext.as_slice())
+        else {
+            return Ok(None);
+        };
+
+        serde_json::from_slice(&plaintext)
+            .map(Some)
+            .map_err(|e| damaged("secrets", &e))
+    }
+}
+
+impl SecretStore {
+    /// The keyring when a secret service answers, unless `vault_path` already exists.
+    pub fn detect(vault_path: PathBuf, passphrase: Option<String>) -> Self {
+        if !vault_path.exists() && keyring_available() {
+            return Self::Keyring;
+        }
+
+        Self::Vault {
+            path: vault_path,
+            passphrase,
+        }
+    }
+
+    pub fn backend(&self) -> SecretBackend {
+        match self {
+            Self::Keyring => SecretBackend::Keyring,
+            Self::Vault { .. } => SecretBackend::Vault,
+        }
+    }
+
+    /// A vault whose passphrase is not known; nothing can be read or written.
+    pub fn is_locked(&self) -> bool {
+        matches!(
+            self,
+            Self::Vault {
+                passphrase: None,
+                ..
+            }
+        )
+    }
+
+    /// Values of those `keys` that are stored.
+    pub fn read(&self, keys: &[String]) -> Result<BTreeMap<String, String>, AppError> {
+        match self {
+            Self::Keyring => {
+                let mut secrets = BTreeMap::new();
+                for key in keys {
+                    match keyring_entry(key)?.get_password() {
+                        Ok(value) => {
+                            secrets.insert(key.clone(), value);
+                        }
+                        Err(keyring::Error::NoEntry) => {}
+                        Err(e) => return Err(keyring_error("read", e)),
+                    }
+                }
+                Ok(secrets)
+            }
+            Self::Vault { path, passphrase } => {
+                let mut secrets = open_vault(path, passphrase.as_deref().ok_or_else(locked)?)?;
+                secrets.retain(|key, _| keys.contains(key));
+                Ok(secrets)
+            }
+        }
+    }
+
+    /// Names of the stored secrets among `keys`; works on a locked vault too.
+    pub fn stored_keys(&self, keys: &[String]) -> Result<Vec<String>, AppError> {
+        match self {
+            Self::Keyring => Ok(self.read(keys)?.into_keys().collect()),
+            Self::Vault { path, .. } => Ok(read_vault_file(path)?
+                .map(|file| file.sealed.keys)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|key| keys.contains(key))
+                .collect()),
+        }
+    }
+
+    /// Stores each `(key, value)` pair; an empty value deletes the secret.
+    pub fn write(&self, changes: &[(String, String)]) -> Result<(), AppError> {
+        match self {
+            Self::Keyring => {
+                for (key, value) in changes {
+                    let entry = keyring_entry(key)?;
+                    let result = if value.is_empty() {
+                        match entry.delete_credential() {
+                            Err(keyring::Error::NoEntry) => Ok(()),
+                            result => result,
+                        }
+                    } else {
+                        entry.set_password(value)
+                    };
+                    result.map_err(|e| keyring_error("write to", e))?;
+                }
+                Ok(())
+            }
+            Self::Vault { path, passphrase } => {
+                let passphrase = passphrase.as_deref().ok_or_else(locked)?;
+                let mut secrets = open_vault(path, passphrase)?;
+                for (key, value) in changes {
+                    if value.is_empty() {
+                        secrets.remove(key);
+                    } else {
+                        secrets.insert(key.clone(), value.clone());
+                    }
+                }
+                seal_vault(path, passphrase, &secrets)
+            }
+        }
+    }
+
+    pub fn vault_exists(&self) -> bool {
+        matches!(self, Self::Vault { path, .. } if path.exists())
+    }
+
+    /// Takes `passphrase` if it opens the vault. Any passphrase is accepted
+    /// while there is no vault yet; the first secret saved creates it.
+    pub fn unlock(&mut self, passphrase: String) -> Result<(), AppError> {
+        let Self::Vault {
+            path,
+            passphrase: current,
+        } = self
+        else {
+            return Err(AppError::new(
+                ErrorCode::Unsupported,
+                "Secrets are kept in the system keyring, there is no vault to unlock",
+            ));
+        };
+        if passphrase.is_empty() {
+            return Err(AppError::new(
+                ErrorCode::VaultLocked,
+                "The vault passphrase cannot be empty",
+            ));
+        }
+
+        open_vault(path, &passphrase)?;
+        *current = Some(passphrase);
+        Ok(())
+    }
+}
+
+pub fn env_passphrase() -> Option<String> {
+    std::env::var(PASSPHRASE_ENV)
+        .ok()
+        .filter(|value| !value.is_empty())
+}
+
+/// Probed once per process: a missing Secret Service only shows up as an
+/// error on first use.
+fn keyring_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+
+    *AVAILABLE.get_or_init(|| {
+        keyring_entry("availability-probe")
+            .map(|entry| matches!(entry.get_password(), Ok(_) | Err(keyring::Error::NoEntry)))
+            .unwrap_or(false)
+    })
+}
+
+fn keyring_entry(key: &str) -> Result<keyring::Entry, AppError> {
+    keyring::Entry::new(APP_IDENTIFIER, key).map_err(|e| keyring_error("open", e))
+}
+
+fn keyring_error(action: &str, error: keyring::Error) -> AppError {
+    AppError::new(
+        ErrorCode::SecretStore,
+        format!("Failed to {} the system keyring: {}", action, error),
+    )
+}
+
+fn locked() -> AppError {
+    AppError::new(
+        ErrorCode::VaultLocked,
+        format!(
+            "Secrets are kept in an encrypted vault; unlock it in Settings or set {}",
+            PASSPHRASE_ENV
+        ),
+    )
+}
+
+fn vault_error(message: impl std::fmt::Display) -> AppError {
+    AppError::new(
+        ErrorCode::SecretStore,
+        format!("Invalid secrets vault: {}", message),
+    )
+}
+
+fn read_vault_file(path: &Path) -> Result<Option<VaultFile>, AppError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content =
+        fs::read_to_string(path).map_err(|e| AppError::io("Failed to read secrets vault", e))?;
+    let file: VaultFile = serde_json::from_str(&content).map_err(vault_error)?;
+    if file.version != VAULT_VERSION {
+        return Err(vault_error(format!("unsupported version {}", file.version)));
+    }
+
+    Ok(Some(file))
+}
+
+/// Decrypts the vault; a missing one is empty.
+fn open_vault(path: &Path, passphrase: &str) -> Result<BTreeMap<String, String>, AppError> {
+    let Some(file) = read_vault_file(path)? else {
+        return Ok(BTreeMap::new());
+    };
+
+    file.sealed
+        .open(passphrase)
+        .map_err(vault_error)?
+        .ok_or_else(|| AppError::new(ErrorCode::VaultLocked, "Wrong vault passphrase"))
+}
+
+/// Encrypts `secrets`, replacing the file in one rename.
+fn seal_vault(
+    path: &Path,
+    passphrase: &str,
+    secrets: &BTreeMap<String, String>,
+) -> Result<(), AppError> {
+    let file = VaultFile {
+        version: VAULT_VERSION,
+        sealed: SealedSecrets::seal(passphrase, secrets)?,
+    };
+    let content = serde_json::to_string_pretty(&file)
+        .map_err(|e| AppError::internal(format!("Failed to serialize vault: {}", e)))?;
+
+    // Owner-only from the moment it exists. A leftover from a crash is
+    // replaced rather than reused, since its permissions are unknown
+    let temp_path = path.with_extension("vault.tmp");
+    let _ = fs::remove_file(&temp_path);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let written = options.open(&temp_path).and_then(|mut file| {
+        file.write_all(content.as_bytes())?;
+        file.sync_all()
+    });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp_path);
+        return Err(AppError::io("Failed to write secrets vault", e));
+    }
+
+    fs::rename(&temp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        AppError::io("Failed to write secrets vault", e)
+    })
+}
+
+fn cipher(passphrase: &str, salt: &[u8]) -> Result<XChaCha20Poly1305, AppError> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| {
+            AppError::new(
+                ErrorCode::SecretStore,
+                format!("Failed to derive vault key: {}", e),
+            )
+        })?;
+
+    Ok(XChaCha20Poly1305::new(&Key::from(key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vault_round_trips_and_is_owner_only() {
+        let dir = std::env::temp_dir().join(format!("opti-bridge-vault-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(VAULT_FILE);
+        // A leftover temp file from a crash must not keep its permissions
+        fs::write(path.with_extension("vault.tmp"), "stale").unwrap();
+
+        let secrets = BTreeMap::from([("s3_secret_key".to_string(), "hunter2".to_string())]);
+        seal_vault(&path, "passphrase", &secrets).unwrap();
+
+        assert_eq!(open_vault(&path, "passphrase").unwrap(), secrets);
+        assert_eq!(
+            open_vault(&path, "wrong").unwrap_err().code,
+            ErrorCode::VaultLocked
+        );
+        assert!(!path.with_extension("vault.tmp").exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
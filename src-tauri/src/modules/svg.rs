@@ -0,0 +1,372 @@
+use crate::error::AppError;
+use image::RgbaImage;
+use quick_xml::events::attributes::Attribute;
+use quick_xml::events::{BytesStart, BytesText, Event};
+use quick_xml::{Reader, Writer};
+use resvg::{tiny_skia, usvg};
+
+/// Elements dropped together with their content.
+const BLOCKED_ELEMENTS: &[&str] = &["script", "foreignObject", "metadata"];
+
+/// Elements whose whitespace is rendered and must survive minification.
+const TEXT_ELEMENTS: &[&str] = &["text", "tspan", "textPath"];
+
+/// SVG is XML text, so sniff the start of the document instead of magic bytes.
+pub fn is_svg(bytes: &[u8]) -> bool {
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(4096)]);
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+    head.starts_with('<') && head.contains("<svg")
+}
+
+/// Removes everything that can run code or load something from elsewhere when
+/// the file is opened straight from the CDN (scripts, event handlers, foreign
+/// objects, external links and stylesheets), along with comments, metadata
+/// and formatting whitespace.
+pub fn sanitize(bytes: &[u8]) -> Result<Vec<u8>, AppError> {
+    let text = std::str::from_utf8(bytes).map_err(|_| AppError::image("SVG is not valid UTF-8"))?;
+    let mut reader = Reader::from_str(text);
+    let mut writer = Writer::new(Vec::new());
+
+    let write = |writer: &mut Writer<Vec<u8>>, event: Event| {
+        writer
+            .write_event(event)
+            .map_err(|e| AppError::image(format!("Failed to write SVG: {}", e)))
+    };
+
+    let mut skip_depth = 0usize;
+    let mut text_depth = 0usize;
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| AppError::image(format!("Failed to parse SVG: {}", e)))?;
+
+        if skip_depth > 0 {
+            match event {
+                Event::Start(_) => skip_depth += 1,
+                Event::End(_) => skip_depth -= 1,
+                Event::Eof => break,
+                _ => {}
+            }
+            continue;
+        }
+
+        match event {
+            Event::Start(element) if is_blocked(&element) => skip_depth = 1,
+            Event::Start(element) if local_name(&element) == "style" => {
+                let css = reader
+                    .read_text(element.name())
+                    .map_err(|e| AppError::image(format!("Failed to parse SVG: {}", e)))?;
+                if !has_external_url(&css) {
+                    let end = element.to_end().into_owned();
+                    write(&mut writer, Event::Start(clean_element(&element)?))?;
+                    write(&mut writer, Event::Text(BytesText::from_escaped(css)))?;
+                    write(&mut writer, Event::End(end))?;
+                }
+            }
+            Event::Start(element) => {
+                if TEXT_ELEMENTS.contains(&local_name(&element).as_str()) {
+                    text_depth += 1;
+                }
+                write(&mut writer, Event::Start(clean_element(&element)?))?;
+            }
+            Event::Empty(element) if is_blocked(&element) => {}
+            Event::Empty(element) => write(&mut writer, Event::Empty(clean_element(&element)?))?,
+            Event::End(element) => {
+                let name = String::from_utf8_lossy(element.local_name().as_ref()).into_owned();
+                if TEXT_ELEMENTS.contains(&name.as_str()) {
+                    text_depth = text_depth.saturating_sub(1);
+                }
+                write(&mut writer, Event::End(element))?;
+            }
+            Event::Text(text) => {
+                let whitespace = text.iter().all(u8::is_ascii_whitespace);
+                if text_depth > 0 || !whitespace {
+                    write(&mut writer, Event::Text(text))?;
+                }
+            }
+            Event::CData(data) => write(&mut writer, Event::CData(data))?,
+            // DOCTYPE can declare entities; comments, declarations and
+            // processing instructions are dead weight
+            Event::Comment(_) | Event::Decl(_) | Event::PI(_) | Event::DocType(_) => {}
+            Event::Eof => break,
+        }
+    }
+
+    Ok(writer.into_inner())
+}
+
+/// Intrinsic size in pixels, rounded up.
+pub fn dimensions(bytes: &[u8]) -> Result<(u32, u32), AppError> {
+    let tree = parse(bytes, false)?;
+    let size = tree.size();
+    Ok((size.width().ceil() as u32, size.height().ceil() as u32))
+}
+
+/// Renders the SVG scaled to `width` pixels wide.
+pub fn rasterize(bytes: &[u8], width: u32) -> Result<RgbaImage, AppError> {
+    let tree = parse(bytes, true)?;
+    let size = tree.size();
+    let scale = width as f32 / size.width();
+    let height = (size.height() * scale).ceil().max(1.0) as u32;
+
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| AppError::image(format!("Cannot render SVG at {}x{}", width, height)))?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    // tiny-skia stores premultiplied alpha
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+
+    RgbaImage::from_raw(width, height, pixels)
+        .ok_or_else(|| AppError::image("Failed to convert rendered SVG"))
+}
+
+fn parse(bytes: &[u8], with_fonts: bool) -> Result<usvg::Tree, AppError> {
+    let mut options = usvg::Options::default();
+    // Only embedded data: images; never read files the SVG points at
+    options.image_href_resolver.resolve_string = Box::new(|_, _| None);
+    if with_fonts {
+        options.fontdb_mut().load_system_fonts();
+    }
+
+    usvg::Tree::from_data(bytes, &options)
+        .map_err(|e| AppError::image(format!("Failed to parse SVG: {}", e)))
+}
+
+fn local_name(element: &BytesStart) -> String {
+    String::from_utf8_lossy(element.local_name().as_ref()).into_owned()
+}
+
+fn is_blocked(element: &BytesStart) -> bool {
+    let name = local_name(element);
+    if BLOCKED_ELEMENTS.contains(&name.as_str()) {
+        return true;
+    }
+
+    // SMIL can rewrite links or handlers after sanitizing, e.g. <set attributeName="href" to="javascript:...">
+    if matches!(name.as_str(), "set" | "animate") {
+        return element.attributes().flatten().any(|attr| {
+            attr.key.local_name().as_ref() == b"attributeName"
+                && attr.unescape_value().is_ok_and(|target| {
+                    let target = target.to_ascii_lowercase();
+                    target.ends_with("href") || target.starts_with("on")
+                })
+        });
+    }
+
+    false
+}
+
+/// Copy of `element` without event handlers or attributes pointing outside the file.
+fn clean_element<'a>(element: &BytesStart<'a>) -> Result<BytesStart<'a>, AppError> {
+    let name = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+    let mut cleaned = BytesStart::new(name);
+
+    for attr in element.attributes() {
+        let attr = attr.map_err(|e| AppError::image(format!("Failed to parse SVG: {}", e)))?;
+        if is_safe_attribute(&attr) {
+            cleaned.push_attribute(attr);
+        }
+    }
+
+    Ok(cleaned)
+}
+
+fn is_safe_attribute(attr: &Attribute) -> bool {
+    let name = String::from_utf8_lossy(attr.key.local_name().as_ref()).to_ascii_lowercase();
+    if name.starts_with("on") {
+        return false;
+    }
+
+    let Ok(value) = attr.unescape_value() else {
+        return false;
+    };
+
+    if name == "href" {
+        return is_local_href(&value);
+    }
+
+    !has_external_url(&value)
+}
+
+/// Fragment links and embedded raster images. Control characters and
+/// whitespace are ignored the way browsers ignore them in URLs.
+fn is_local_href(value: &str) -> bool {
+    let value: String = value
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+
+    value.starts_with('#')
+        || (value.starts_with("data:image/") && !value.starts_with("data:image/svg"))
+}
+
+/// CSS (in `style` attributes, presentation attributes or `<style>`) that
+/// imports or points at anything other than a fragment or data URI.
+fn has_external_url(css: &str) -> bool {
+    let css = css.to_ascii_lowercase();
+    if css.contains("@import") {
+        return true;
+    }
+
+    css.split("url(").skip(1).any(|reference| {
+        let reference =
+            reference.trim_start_matches(|c: char| c.is_whitespace() || c == '"' || c == '\'');
+        !(reference.starts_with('#') || reference.starts_with("data:"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clean(svg: &str) -> String {
+        String::from_utf8(sanitize(svg.as_bytes()).unwrap()).unwrap()
+    }
+
+    fn wrap(body: &str) -> String {
+        format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">{}</svg>"#,
+            body
+        )
+    }
+
+    #[test]
+    fn removes_scripts_with_their_content() {
+        let output = clean(&wrap(
+            r#"<script>alert(document.cookie)</script><script href="https://evil.example/x.js"/><svg:script xmlns:svg="http://www.w3.org/2000/svg">alert(1)</svg:script><rect width="1"/>"#,
+        ));
+
+        assert!(!output.contains("script"), "{}", output);
+        assert!(!output.contains("alert"), "{}", output);
+        assert!(output.contains(r#"<rect width="1"/>"#), "{}", output);
+    }
+
+    #[test]
+    fn removes_event_handler_attributes() {
+        let output = clean(&wrap(
+            r#"<rect onload="alert(1)" ONCLICK="alert(2)" onMouseOver="alert(3)" width="10" fill="red"/>"#,
+        ));
+
+        assert!(!output.to_ascii_lowercase().contains("on"), "{}", output);
+        assert!(output.contains(r#"width="10""#), "{}", output);
+        assert!(output.contains(r#"fill="red""#), "{}", output);
+    }
+
+    #[test]
+    fn removes_script_and_document_hrefs() {
+        for href in [
+            "javascript:alert(1)",
+            "JavaScript:alert(1)",
+            " java\tscript:alert(1)",
+            "&#x6A;avascript:alert(1)",
+            "data:text/html;base64,PHNjcmlwdD5hbGVydCgxKTwvc2NyaXB0Pg==",
+            "data:image/svg+xml;base64,PHN2Zy8+",
+        ] {
+            for attribute in ["href", "xlink:href"] {
+                let output = clean(&wrap(&format!(
+                    r#"<a {}="{}"><text>x</text></a>"#,
+                    attribute, href
+                )));
+                assert!(
+                    !output.contains("href"),
+                    "{} {}: {}",
+                    attribute,
+                    href,
+                    output
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_fragment_and_raster_data_hrefs() {
+        let output = clean(&wrap(
+            r##"<a href="#top"/><use xlink:href="#shape"/><image href="data:image/png;base64,iVBORw0KGgo="/>"##,
+        ));
+
+        assert!(output.contains(r##"href="#top""##), "{}", output);
+        assert!(output.contains(r##"xlink:href="#shape""##), "{}", output);
+        assert!(
+            output.contains(r#"href="data:image/png;base64,iVBORw0KGgo=""#),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn removes_foreign_objects_with_their_content() {
+        let output = clean(&wrap(
+            r#"<foreignObject width="100" height="100"><body xmlns="http://www.w3.org/1999/xhtml"><iframe src="https://evil.example"/></body></foreignObject><circle r="1"/>"#,
+        ));
+
+        assert!(!output.contains("foreignObject"), "{}", output);
+        assert!(!output.contains("iframe"), "{}", output);
+        assert!(output.contains(r#"<circle r="1"/>"#), "{}", output);
+    }
+
+    #[test]
+    fn removes_external_use_and_image_references() {
+        let output = clean(&wrap(
+            r#"<use href="https://evil.example/sprite.svg#icon"/><use xlink:href="sprite.svg#icon"/><image href="http://evil.example/track.png" width="1"/><image xlink:href="file:///etc/passwd"/>"#,
+        ));
+
+        assert!(!output.contains("href"), "{}", output);
+        assert!(!output.contains("evil"), "{}", output);
+        assert!(output.contains(r#"<image width="1"/>"#), "{}", output);
+    }
+
+    #[test]
+    fn removes_external_css() {
+        let output = clean(&wrap(
+            r##"<style>@import url(https://evil.example/a.css);</style><style>rect { fill: url(#grad); }</style><rect style="fill: url('https://evil.example/x')" fill="url(#grad)"/>"##,
+        ));
+
+        assert!(!output.contains("evil"), "{}", output);
+        assert!(!output.contains("@import"), "{}", output);
+        assert!(
+            output.contains("<style>rect { fill: url(#grad); }</style>"),
+            "{}",
+            output
+        );
+        assert!(output.contains(r##"fill="url(#grad)""##), "{}", output);
+    }
+
+    #[test]
+    fn removes_animations_that_rewrite_links_or_handlers() {
+        let output = clean(&wrap(
+            r#"<a><set attributeName="href" to="javascript:alert(1)"/><animate attributeName="onclick" values="alert(1)"/><animate attributeName="opacity" values="0;1"/></a>"#,
+        ));
+
+        assert!(!output.contains("javascript"), "{}", output);
+        assert!(!output.contains("onclick"), "{}", output);
+        assert!(output.contains(r#"attributeName="opacity""#), "{}", output);
+    }
+
+    #[test]
+    fn drops_comments_and_formatting_but_keeps_text_whitespace() {
+        let output = clean(
+            "<?xml version=\"1.0\"?>\n<!DOCTYPE svg>\n<svg>\n  <!-- note -->\n  <metadata>x</metadata>\n  <text>a  b</text>\n</svg>",
+        );
+
+        assert_eq!(output, "<svg><text>a  b</text></svg>");
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        assert!(sanitize(&[0xff, 0xfe, 0x00]).is_err());
+        assert!(sanitize(b"<svg><rect></svg>").is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use crate::error::AppError;
+use crate::models::HistoryItem;
+use crate::modules::image_processor::{ImageProcessor, ImageVariant};
+use crate::modules::profiles::UploadTarget;
+use crate::uploaders::registry::UploaderRegistry;
+use crate::uploaders::retry::{self, RetryPolicy};
+use crate::uploaders::UploadHooks;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
+use uuid::Uuid;
+
+/// Uploads already processed bytes to `target` and returns the history entry
+/// describing the upload. The caller decides where to record it.
+pub async fn upload_processed(
+    registry: &UploaderRegistry,
+    target: &UploadTarget,
+    image_data: &[u8],
+    original_name: &str,
+) -> Result<HistoryItem, AppError> {
+    upload_processed_with(
+        registry,
+        target,
+        image_data,
+        original_name,
+        &UploadHooks::default(),
+    )
+    .await
+}
+
+/// `upload_processed`, reporting bytes sent and retries through `hooks`.
+/// Transient failures are retried with backoff; auth and validation errors
+/// fail on the first attempt. Upload errors carry the provider in their details.
+pub async fn upload_processed_with(
+    registry: &UploaderRegistry,
+    target: &UploadTarget,
+    image_data: &[u8],
+    original_name: &str,
+    hooks: &UploadHooks,
+) -> Result<HistoryItem, AppError> {
+    // Upload through the registered provider
+    let provider = target.provider.as_str();
+    let uploader = registry.build(provider, &target.config)?;
+    let object = retry::with_retry(&RetryPolicy::default(), &hooks.on_retry, || {
+        uploader.upload_with_progress(image_data, original_name, hooks.progress.clone())
+    })
+    .await
+    .map_err(|e| AppError::from(e).with_provider(provider))?;
+
+    // Create thumbnail for history. The upload already happened, so formats
+    // image can't decode (AVIF) are recorded without one instead of failing
+    let processor = ImageProcessor::from_config(&target.config);
+    let thumbnail_base64 = processor
+        .create_thumbnail(image_data, 200)
+        .map(|thumbnail_bytes| general_purpose::STANDARD.encode(thumbnail_bytes))
+        .unwrap_or_default();
+
+    // Not every provider reports dimensions, so fall back to the local bytes;
+    // like the thumbnail, they are left empty rather than orphan the upload
+    let (width, height) = match (object.width, object.height) {
+        (Some(width), Some(height)) => (Some(width), Some(height)),
+        _ => processor
+            .get_dimensions(image_data)
+            .ok()
+            .map_or((None, None), |(width, height)| (Some(width), Some(height))),
+    };
+
+    Ok(HistoryItem {
+        id: Uuid::new_v4().to_string(),
+        provider: provider.to_string(),
+        original_name: original_name.to_string(),
+        url: object.url,
+        created_at: Utc::now().timestamp(),
+        thumbnail_base64,
+        status: HistoryItem::STATUS_UPLOADED.to_string(),
+        public_id: object.public_id,
+        bucket: object.bucket,
+        object_key: object.object_key,
+        etag: object.etag,
+        size_bytes: object.size_bytes.or(Some(image_data.len() as i64)),
+        width,
+        height,
+        group_id: None,
+        account_id: target.account_id.clone(),
+    })
+}
+
+/// Uploads every variant of a responsive set under one `group_id`. Each entry
+/// is handed to `record` as soon as it is uploaded, so a failure part way
+/// through still leaves the finished variants in history.
+pub async fn upload_variants(
+    registry: &UploaderRegistry,
+    target: &UploadTarget,
+    variants: &[ImageVariant],
+    original_name: &str,
+    mut record: impl FnMut(&HistoryItem) -> Result<(), AppError>,
+) -> Result<Vec<HistoryItem>, AppError> {
+    let group_id = Uuid::new_v4().to_string();
+    let stem = original_name
+        .rsplit_once('.')
+        .map_or(original_name, |(stem, _)| stem);
+
+    let mut items = Vec::new();
+    for variant in variants {
+        let name = format!(
+            "{}-{}w.{}",
+            stem,
+            variant.width,
+            ImageProcessor::detect_extension(&variant.data)
+        );
+
+        let mut item = upload_processed(registry, target, &variant.data, &name).await?;
+        item.group_id = Some(group_id.clone());
+        record(&item)?;
+        items.push(item);
+    }
+
+    Ok(items)
+}
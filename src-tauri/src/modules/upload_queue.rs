@@ -0,0 +1,307 @@
+//! Batch uploads driven from Rust. Every file is processed and uploaded on its
+//! own task, at most `settings_upload_concurrency` across all batches at a time, and
+//! reports `queued → processing → uploading (⇄ retrying) → done | failed |
+//! cancelled` to the webview as `upload-progress` events carrying an `UploadEvent`.
+//! Items whose upload fails are moved to the offline queue.
+
+use crate::error::AppError;
+use crate::models::{BatchItem, UploadBatch, UploadEvent, UploadStatus};
+use crate::modules::database::Database;
+use crate::modules::image_processor::ImageProcessor;
+use crate::modules::profiles::UploadTarget;
+use crate::modules::{offline_queue, upload_pipeline};
+use crate::state::AppState;
+use crate::uploaders::retry::RetryNotice;
+use crate::uploaders::UploadHooks;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tauri::async_runtime::{self, JoinHandle};
+use tauri::{AppHandle, Manager};
+use tokio::sync::Semaphore;
+use uuid::Uuid;
+
+pub const PROGRESS_EVENT: &str = "upload-progress";
+
+/// Items that have not finished yet, kept so they can be cancelled, and the
+/// permits every item needs to start, shared by all batches.
+pub struct UploadQueue {
+    pending: Mutex<HashMap<String, PendingUpload>>,
+    permits: Arc<Semaphore>,
+    /// Permits currently handed out in total, used or not.
+    limit: Mutex<usize>,
+}
+
+impl Default for UploadQueue {
+    fn default() -> Self {
+        Self {
+            pending: Mutex::default(),
+            permits: Arc::new(Semaphore::new(0)),
+            limit: Mutex::new(0),
+        }
+    }
+}
+
+struct PendingUpload {
+    batch_id: String,
+    task: JoinHandle<()>,
+}
+
+impl UploadQueue {
+    /// Queues `paths` for upload to `target` and returns without waiting.
+    pub fn enqueue(
+        &self,
+        app_handle: &AppHandle,
+        target: &UploadTarget,
+        paths: Vec<String>,
+    ) -> UploadBatch {
+        let batch_id = Uuid::new_v4().to_string();
+        self.set_limit(target.config.settings_upload_concurrency.max(1) as usize);
+        let target = Arc::new(target.clone());
+
+        // Held while spawning so no task can finish before it is registered
+        let mut pending = self.pending();
+        let mut items = Vec::new();
+
+        for path in paths {
+            let job = Job {
+                app_handle: app_handle.clone(),
+                batch_id: batch_id.clone(),
+                item_id: Uuid::new_v4().to_string(),
+                target: target.clone(),
+                path,
+                attempt: Arc::new(AtomicU32::new(0)),
+            };
+
+            job.emit(job.event(UploadStatus::Queued));
+            items.push(BatchItem {
+                id: job.item_id.clone(),
+                name: file_name(&job.path),
+            });
+
+            let item_id = job.item_id.clone();
+            let task = async_runtime::spawn(run(job, self.permits.clone()));
+            pending.insert(
+                item_id,
+                PendingUpload {
+                    batch_id: batch_id.clone(),
+                    task,
+                },
+            );
+        }
+
+        UploadBatch { batch_id, items }
+    }
+
+    /// Stops an item wherever it is. An upload already in flight is abandoned,
+    /// so the provider may or may not end up with the object.
+    pub fn cancel(&self, app_handle: &AppHandle, item_id: &str) -> Result<(), AppError> {
+        let upload = self
+            .pending()
+            .remove(item_id)
+            .ok_or_else(|| AppError::not_found("Upload not found or already finished"))?;
+
+        cancelled(app_handle, item_id, upload);
+        Ok(())
+    }
+
+    /// Cancels every unfinished item of a batch and returns how many there were.
+    pub fn cancel_batch(&self, app_handle: &AppHandle, batch_id: &str) -> usize {
+        let uploads: Vec<(String, PendingUpload)> = {
+            let mut pending = self.pending();
+            let ids: Vec<String> = pending
+                .iter()
+                .filter(|(_, upload)| upload.batch_id == batch_id)
+                .map(|(id, _)| id.clone())
+                .collect();
+
+            ids.into_iter()
+                .filter_map(|id| pending.remove_entry(&id))
+                .collect()
+        };
+
+        let count = uploads.len();
+        for (item_id, upload) in uploads {
+            cancelled(app_handle, &item_id, upload);
+        }
+
+        count
+    }
+
+    fn finish(&self, item_id: &str) {
+        self.pending().remove(item_id);
+    }
+
+    fn pending(&self) -> MutexGuard<'_, HashMap<String, PendingUpload>> {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Matches the number of permits to `settings_upload_concurrency`, which
+    /// may have changed since the last batch. Surplus permits are taken out
+    /// of circulation as running uploads return them.
+    fn set_limit(&self, limit: usize) {
+        let mut current = self.limit.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if limit > *current {
+            self.permits.add_permits(limit - *current);
+        } else if limit < *current {
+            let surplus = (*current - limit) as u32;
+            let permits = self.permits.clone();
+            async_runtime::spawn(async move {
+                if let Ok(taken) = permits.acquire_many_owned(surplus).await {
+                    taken.forget();
+                }
+            });
+        }
+
+        *current = limit;
+    }
+}
+
+fn cancelled(app_handle: &AppHandle, item_id: &str, upload: PendingUpload) {
+    upload.task.abort();
+
+    let event = UploadEvent {
+        batch_id: upload.batch_id,
+        item_id: item_id.to_string(),
+        status: UploadStatus::Cancelled,
+        bytes_sent: 0,
+        bytes_total: 0,
+        attempt: 0,
+        url: None,
+        error: None,
+    };
+    let _ = app_handle.emit_all(PROGRESS_EVENT, event);
+}
+
+#[derive(Clone)]
+struct Job {
+    app_handle: AppHandle,
+    batch_id: String,
+    item_id: String,
+    target: Arc<UploadTarget>,
+    path: String,
+    /// Shared with the progress and retry hooks.
+    attempt: Arc<AtomicU32>,
+}
+
+impl Job {
+    fn event(&self, status: UploadStatus) -> UploadEvent {
+        UploadEvent {
+            batch_id: self.batch_id.clone(),
+            item_id: self.item_id.clone(),
+            status,
+            bytes_sent: 0,
+            bytes_total: 0,
+            attempt: self.attempt.load(Ordering::Relaxed),
+            url: None,
+            error: None,
+        }
+    }
+
+    fn emit(&self, event: UploadEvent) {
+        let _ = self.app_handle.emit_all(PROGRESS_EVENT, event);
+    }
+
+    fn emit_uploading(&self, bytes_sent: u64, bytes_total: u64) {
+        self.emit(UploadEvent {
+            bytes_sent,
+            bytes_total,
+            ..self.event(UploadStatus::Uploading)
+        });
+    }
+
+    fn emit_retrying(&self, notice: &RetryNotice) {
+        self.attempt.store(notice.attempt + 1, Ordering::Relaxed);
+
+        let mut error = AppError::from(notice.error.clone()).with_provider(&self.target.provider);
+        error.message = format!(
+            "{}; retrying in {:.1}s",
+            error.message,
+            notice.delay.as_secs_f32()
+        );
+        self.emit(UploadEvent {
+            error: Some(error),
+            ..self.event(UploadStatus::Retrying)
+        });
+    }
+}
+
+async fn run(job: Job, permits: Arc<Semaphore>) {
+    let result = upload(&job, &permits).await;
+
+    // A cancel that raced the last step already reported the item; the
+    // outcome below still tells the webview what actually happened
+    job.app_handle
+        .state::<AppState>()
+        .upload_queue
+        .finish(&job.item_id);
+
+    job.emit(match result {
+        Ok(url) => UploadEvent {
+            url: Some(url),
+            ..job.event(UploadStatus::Done)
+        },
+        Err(e) => UploadEvent {
+            error: Some(e),
+            ..job.event(UploadStatus::Failed)
+        },
+    });
+}
+
+async fn upload(job: &Job, permits: &Semaphore) -> Result<String, AppError> {
+    let _permit = permits
+        .acquire()
+        .await
+        .map_err(|e| AppError::internal(format!("Upload queue closed: {}", e)))?;
+
+    job.emit(job.event(UploadStatus::Processing));
+
+    // Encoding is CPU bound, keep it off the async workers
+    let processor = ImageProcessor::from_config(&job.target.config);
+    let path = job.path.clone();
+    let image_data = async_runtime::spawn_blocking(move || processor.process_from_path(&path))
+        .await
+        .map_err(|e| AppError::internal(format!("Processing failed: {}", e)))??;
+
+    let stem = Path::new(&job.path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "image".to_string());
+    let name = format!("{}.{}", stem, ImageProcessor::detect_extension(&image_data));
+
+    let total = image_data.len() as u64;
+    job.attempt.store(1, Ordering::Relaxed);
+    job.emit_uploading(0, total);
+
+    let progress_job = job.clone();
+    let retry_job = job.clone();
+    let hooks = UploadHooks {
+        progress: Arc::new(move |sent| progress_job.emit_uploading(sent, total)),
+        on_retry: Arc::new(move |notice| retry_job.emit_retrying(notice)),
+    };
+
+    let state = job.app_handle.state::<AppState>();
+    let history_item = upload_pipeline::upload_processed_with(
+        &state.uploaders,
+        &job.target,
+        &image_data,
+        &name,
+        &hooks,
+    )
+    .await
+    .map_err(|e| offline_queue::park(&job.app_handle, &job.target, &name, &image_data, e))?;
+
+    let db = Database::new(&job.app_handle)?;
+    db.insert_history(&history_item)?;
+
+    Ok(history_item.url)
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
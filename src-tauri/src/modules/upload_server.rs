@@ -0,0 +1,511 @@
+//! Loopback HTTP endpoint speaking the PicGo server protocol, so Typora and
+//! editor extensions configured for "PicGo (app)" can upload through OptiBridge.
+//!
+//! `POST /upload` accepts either `{"list": ["/path/a.png", ...]}` or a raw image
+//! body and answers `{"success": true, "result": [urls]}` or
+//! `{"success": false, "message": "..."}`. Bodies larger than
+//! `settings_server_max_upload_mb` are refused with 413.
+
+use crate::error::{self, AppError, ErrorCode};
+use crate::models::{Config, UploadServerStatus};
+use crate::modules::config_manager::ConfigManager;
+use crate::modules::database::Database;
+use crate::modules::image_processor::ImageProcessor;
+use crate::modules::{offline_queue, profiles, upload_pipeline};
+use crate::state::AppState;
+use hyper::body::HttpBody;
+use hyper::header::HeaderValue;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, Server, StatusCode};
+use serde::Deserialize;
+use serde_json::json;
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::{Ipv4Addr, SocketAddr, TcpListener};
+use std::path::Path;
+use std::sync::{MutexGuard, PoisonError};
+use tauri::{AppHandle, Manager};
+use tokio::sync::oneshot;
+
+pub const DEFAULT_PORT: u16 = 36677;
+
+pub struct UploadServer {
+    port: u16,
+    shutdown: oneshot::Sender<()>,
+}
+
+impl UploadServer {
+    /// Binds 127.0.0.1:`port` and serves requests on the Tauri async runtime.
+    pub fn start(app_handle: AppHandle, port: u16) -> Result<Self, AppError> {
+        let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+
+        // Bind synchronously so a busy port is reported to the caller
+        let listener = TcpListener::bind(addr)
+            .map_err(|e| AppError::io(&format!("Failed to bind upload server to {}", addr), e))?;
+        listener
+            .set_nonblocking(true)
+            .map_err(|e| AppError::io("Failed to configure upload server socket", e))?;
+
+        let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+
+        tauri::async_runtime::spawn(async move {
+            let server_handle = app_handle.clone();
+            let make_service = make_service_fn(move |_| {
+                let app_handle = app_handle.clone();
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |request| {
+                        handle(app_handle.clone(), request)
+                    }))
+                }
+            });
+
+            let server = match Server::from_tcp(listener) {
+                Ok(builder) => builder.serve(make_service),
+                Err(e) => {
+                    let message = format!("Upload server failed to start: {}", e);
+                    stopped(&server_handle, AppError::internal(message));
+                    return;
+                }
+            };
+
+            let graceful = server.with_graceful_shutdown(async {
+                shutdown_rx.await.ok();
+            });
+
+            if let Err(e) = graceful.await {
+                let message = format!("Upload server stopped with error: {}", e);
+                stopped(&server_handle, AppError::internal(message));
+            }
+        });
+
+        Ok(Self { port, shutdown })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn stop(self) {
+        let _ = self.shutdown.send(());
+    }
+}
+
+/// Starts, stops or restarts the server so it matches `config`. A failure
+/// to start is also kept for `status`.
+pub fn sync(app_handle: &AppHandle, config: &Config) -> Result<(), AppError> {
+    let state = app_handle.state::<AppState>();
+    let mut server = state
+        .upload_server
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    let wanted_port = config
+        .settings_server_enabled
+        .then_some(config.settings_server_port);
+    if server.as_ref().map(UploadServer::port) == wanted_port {
+        // Also clears an error from a launch that never got this far
+        *last_error(&state) = None;
+        return Ok(());
+    }
+
+    if let Some(running) = server.take() {
+        running.stop();
+    }
+
+    let started = match wanted_port {
+        Some(port) => UploadServer::start(app_handle.clone(), port).map(|running| {
+            *server = Some(running);
+        }),
+        None => Ok(()),
+    };
+    *last_error(&state) = started.as_ref().err().cloned();
+
+    started
+}
+
+/// Whether the server is running, and why it is not when it should be.
+pub fn status(app_handle: &AppHandle) -> UploadServerStatus {
+    let state = app_handle.state::<AppState>();
+    let port = state
+        .upload_server
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+        .map(UploadServer::port);
+
+    let error = last_error(&state).clone();
+
+    UploadServerStatus { port, error }
+}
+
+/// Records why the server could not even be considered, such as a config
+/// that failed to load at launch.
+pub fn not_started(app_handle: &AppHandle, error: AppError) {
+    let message = format!("Upload server not started: {}", error.message);
+    *last_error(&app_handle.state::<AppState>()) = Some(AppError { message, ..error });
+}
+
+fn last_error(state: &AppState) -> MutexGuard<'_, Option<AppError>> {
+    state
+        .upload_server_error
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Records an error that ended the server after it was started.
+fn stopped(app_handle: &AppHandle, error: AppError) {
+    let state = app_handle.state::<AppState>();
+    state
+        .upload_server
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take();
+    *last_error(&state) = Some(error.clone());
+    error::report(app_handle, &error);
+}
+
+#[derive(Deserialize)]
+struct PathList {
+    #[serde(default)]
+    list: Vec<String>,
+}
+
+/// What a request asks to upload.
+#[derive(Debug, PartialEq)]
+enum Images<'a> {
+    /// Local files named by a `{"list": [...]}` body.
+    Paths(Vec<String>),
+    /// The image itself as the body.
+    Raw(&'a [u8]),
+}
+
+fn images(is_json: bool, body: &[u8]) -> Result<Images<'_>, AppError> {
+    let images = if is_json {
+        let paths: PathList = serde_json::from_slice(body).map_err(|e| {
+            AppError::new(
+                ErrorCode::RequestRejected,
+                format!("Invalid JSON body: {}", e),
+            )
+        })?;
+        (!paths.list.is_empty()).then_some(Images::Paths(paths.list))
+    } else {
+        (!body.is_empty()).then_some(Images::Raw(body))
+    };
+
+    images.ok_or_else(|| AppError::new(ErrorCode::RequestRejected, "No images in request"))
+}
+
+async fn handle(
+    app_handle: AppHandle,
+    request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let load_config = || ConfigManager::new(&app_handle).and_then(|manager| manager.load_config());
+    let uploader = app_handle.clone();
+    let upload = |config: Config, is_json: bool, body: Vec<u8>| async move {
+        upload_request(&uploader, &config, is_json, &body).await
+    };
+
+    Ok(respond(request, load_config, upload).await)
+}
+
+/// The protocol around `upload`, which is handed the config, whether the body
+/// is a JSON path list, and the body.
+async fn respond<F, Fut>(
+    request: Request<Body>,
+    load_config: impl FnOnce() -> Result<Config, AppError>,
+    upload: F,
+) -> Response<Body>
+where
+    F: FnOnce(Config, bool, Vec<u8>) -> Fut,
+    Fut: Future<Output = Result<Vec<String>, AppError>>,
+{
+    // Browsers attach Origin to cross-site requests; editor clients do not.
+    // Refusing them keeps web pages from driving uploads of local files.
+    if request.headers().contains_key(header::ORIGIN) {
+        return failure(
+            StatusCode::FORBIDDEN,
+            "Cross-origin requests are not allowed",
+        );
+    }
+
+    if request.method() != Method::POST || request.uri().path() != "/upload" {
+        return failure(StatusCode::NOT_FOUND, "Use POST /upload");
+    }
+
+    // The limit is read per request so a saved change applies right away
+    let config = match load_config() {
+        Ok(config) => config,
+        Err(e) => return failure(StatusCode::OK, &e.message),
+    };
+    let max_bytes = u64::from(config.settings_server_max_upload_mb) * 1024 * 1024;
+
+    let is_json = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+
+    let body = match read_body(request.into_body(), max_bytes).await {
+        Ok(Some(body)) => body,
+        Ok(None) => {
+            return failure(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                &format!(
+                    "Request body is larger than {} MB",
+                    config.settings_server_max_upload_mb
+                ),
+            )
+        }
+        Err(e) => {
+            return failure(
+                StatusCode::BAD_REQUEST,
+                &format!("Failed to read request body: {}", e),
+            )
+        }
+    };
+
+    match upload(config, is_json, body).await {
+        Ok(urls) => json_response(StatusCode::OK, json!({ "success": true, "result": urls })),
+        Err(e) => failure(StatusCode::OK, &e.message),
+    }
+}
+
+/// Reads the body, giving up with `None` once it passes `max_bytes` so an
+/// oversized request is never held in memory.
+async fn read_body(mut body: Body, max_bytes: u64) -> Result<Option<Vec<u8>>, hyper::Error> {
+    if body.size_hint().lower() > max_bytes {
+        return Ok(None);
+    }
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        if (bytes.len() + chunk.len()) as u64 > max_bytes {
+            return Ok(None);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    Ok(Some(bytes))
+}
+
+async fn upload_request(
+    app_handle: &AppHandle,
+    config: &Config,
+    is_json: bool,
+    body: &[u8],
+) -> Result<Vec<String>, AppError> {
+    let state = app_handle.state::<AppState>();
+    // Uploads from other apps go through the default profile
+    let target = profiles::resolve(&state.uploaders, config, None)?;
+    let processor = ImageProcessor::from_config(&target.config);
+
+    // Each input becomes (processed bytes, file stem)
+    let mut inputs = Vec::new();
+    match images(is_json, body)? {
+        Images::Paths(paths) => {
+            for path in &paths {
+                let stem = Path::new(path)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "image".to_string());
+
+                inputs.push((processor.process_from_path(path)?, stem));
+            }
+        }
+        Images::Raw(bytes) => {
+            inputs.push((processor.process_from_bytes(bytes)?, "image".to_string()));
+        }
+    }
+
+    // Failed uploads go to the offline queue like those started in the app.
+    // Once one fails for lack of a connection the rest are queued untried.
+    let mut urls = Vec::new();
+    let mut failure: Option<AppError> = None;
+    let mut offline: Option<AppError> = None;
+
+    for (image_data, stem) in inputs {
+        let name = format!("{}.{}", stem, ImageProcessor::detect_extension(&image_data));
+        let uploaded = match &offline {
+            Some(e) => Err(e.clone()),
+            None => {
+                upload_pipeline::upload_processed(&state.uploaders, &target, &image_data, &name)
+                    .await
+            }
+        };
+
+        match uploaded {
+            Ok(history_item) => {
+                // The image is uploaded either way; the caller still gets its URL
+                let recorded =
+                    Database::new(app_handle).and_then(|db| db.insert_history(&history_item));
+                if let Err(e) = recorded {
+                    let message = format!(
+                        "{} was uploaded to {} but could not be added to history: {}",
+                        name, history_item.url, e.message
+                    );
+                    error::report(app_handle, &AppError { message, ..e });
+                }
+                urls.push(history_item.url);
+            }
+            Err(e) => {
+                if e.code == ErrorCode::Network {
+                    offline.get_or_insert_with(|| e.clone());
+                }
+                let parked = offline_queue::park(app_handle, &target, &name, &image_data, e);
+                failure.get_or_insert(parked);
+            }
+        }
+    }
+
+    match failure {
+        None => Ok(urls),
+        Some(e) if urls.is_empty() => Err(e),
+        Some(e) => Err(AppError {
+            message: format!("{} ({} of the images were uploaded)", e.message, urls.len()),
+            ..e
+        }),
+    }
+}
+
+fn failure(status: StatusCode, message: &str) -> Response<Body> {
+    json_response(status, json!({ "success": false, "message": message }))
+}
+
+fn json_response(status: StatusCode, body: serde_json::Value) -> Response<Body> {
+    let mut response = Response::new(Body::from(body.to_string()));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// What `upload` was called with: whether the body was JSON, and the body.
+    type Calls = Arc<Mutex<Vec<(bool, Vec<u8>)>>>;
+
+    fn post() -> hyper::http::request::Builder {
+        Request::builder().method(Method::POST).uri("/upload")
+    }
+
+    /// Sends `request` through `respond` with a 1 MB limit and an upload that
+    /// records its input and answers `result`.
+    async fn send(
+        request: Request<Body>,
+        result: Result<Vec<String>, AppError>,
+    ) -> (StatusCode, serde_json::Value, Calls) {
+        let calls: Calls = Arc::default();
+        let recorded = calls.clone();
+        let load_config = || {
+            Ok(Config {
+                settings_server_max_upload_mb: 1,
+                ..Config::default()
+            })
+        };
+        let upload = |_: Config, is_json: bool, body: Vec<u8>| async move {
+            recorded.lock().unwrap().push((is_json, body));
+            result
+        };
+
+        let response = respond(request, load_config, upload).await;
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap(), calls)
+    }
+
+    #[tokio::test]
+    async fn refuses_cross_origin_requests() {
+        let request = post()
+            .header(header::ORIGIN, "https://example.com")
+            .body(Body::from("image"))
+            .unwrap();
+
+        let (status, body, calls) = send(request, Ok(vec![])).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["success"], false);
+        assert!(calls.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn only_serves_post_upload() {
+        for (method, path) in [(Method::GET, "/upload"), (Method::POST, "/other")] {
+            let request = Request::builder()
+                .method(method)
+                .uri(path)
+                .body(Body::empty())
+                .unwrap();
+
+            let (status, body, calls) = send(request, Ok(vec![])).await;
+            assert_eq!(status, StatusCode::NOT_FOUND);
+            assert_eq!(body["message"], "Use POST /upload");
+            assert!(calls.lock().unwrap().is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn refuses_bodies_over_the_limit() {
+        let request = post().body(Body::from(vec![0u8; 1024 * 1024 + 1])).unwrap();
+
+        let (status, body, calls) = send(request, Ok(vec![])).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(body["message"], "Request body is larger than 1 MB");
+        assert!(calls.lock().unwrap().is_empty());
+
+        // Exactly at the limit is fine
+        let request = post().body(Body::from(vec![0u8; 1024 * 1024])).unwrap();
+        let (status, _, calls) = send(request, Ok(vec![])).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(calls.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn passes_json_and_raw_bodies_to_upload() {
+        let request = post()
+            .header(header::CONTENT_TYPE, "application/json; charset=utf-8")
+            .body(Body::from(r#"{"list": ["/tmp/a.png"]}"#))
+            .unwrap();
+        let urls = vec!["https://cdn.example.com/a.webp".to_string()];
+
+        let (status, body, calls) = send(request, Ok(urls)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            json!({ "success": true, "result": ["https://cdn.example.com/a.webp"] })
+        );
+        assert_eq!(
+            calls.lock().unwrap()[0],
+            (true, br#"{"list": ["/tmp/a.png"]}"#.to_vec())
+        );
+
+        let request = post()
+            .header(header::CONTENT_TYPE, "image/png")
+            .body(Body::from("png bytes"))
+            .unwrap();
+        let failed = Err(AppError::new(ErrorCode::Network, "Offline"));
+
+        let (status, body, calls) = send(request, failed).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({ "success": false, "message": "Offline" }));
+        assert_eq!(calls.lock().unwrap()[0], (false, b"png bytes".to_vec()));
+    }
+
+    #[test]
+    fn reads_path_lists_and_raw_images() {
+        assert_eq!(
+            images(true, br#"{"list": ["/a.png", "/b.jpg"]}"#).unwrap(),
+            Images::Paths(vec!["/a.png".to_string(), "/b.jpg".to_string()])
+        );
+        assert_eq!(images(false, b"bytes").unwrap(), Images::Raw(b"bytes"));
+
+        for (is_json, body) in [(true, &b"{}"[..]), (true, b"not json"), (false, b"")] {
+            let error = images(is_json, body).unwrap_err();
+            assert_eq!(error.code, ErrorCode::RequestRejected);
+        }
+    }
+}
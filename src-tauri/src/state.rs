@@ -1,15 +1,39 @@
-use std::collections::HashMap;
+use crate::error::AppError;
+use crate::modules::image_cache::ImageCache;
+use crate::modules::offline_queue::OfflineQueue;
+use crate::modules::upload_queue::UploadQueue;
+use crate::modules::upload_server::UploadServer;
+use crate::uploaders::registry::UploaderRegistry;
 use std::sync::Mutex;
 
 pub struct AppState {
-    pub image_cache: Mutex<HashMap<String, Vec<u8>>>,
+    pub image_cache: ImageCache,
+    pub uploaders: UploaderRegistry,
+    pub upload_server: Mutex<Option<UploadServer>>,
+    /// Why the upload server last failed to start or stopped, if it did.
+    pub upload_server_error: Mutex<Option<AppError>>,
+    pub upload_queue: UploadQueue,
+    pub offline_queue: OfflineQueue,
+    /// Passphrase of the secrets vault once unlocked from Settings.
+    pub vault_passphrase: Mutex<Option<String>>,
 }
 
 impl AppState {
     pub fn new() -> Self {
         Self {
-            image_cache: Mutex::new(HashMap::new()),
+            image_cache: ImageCache::default(),
+            uploaders: UploaderRegistry::with_defaults(),
+            upload_server: Mutex::new(None),
+            upload_server_error: Mutex::new(None),
+            upload_queue: UploadQueue::default(),
+            offline_queue: OfflineQueue::default(),
+            vault_passphrase: Mutex::new(None),
         }
     }
 }
 
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
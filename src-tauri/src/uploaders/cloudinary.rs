@@ -1,11 +1,26 @@
+use super::diagnostics;
+use super::error::{parse_retry_after, UploadError};
+use super::{content_type, progress_stream, UploadProgress, Uploader};
+use crate::models::{
+    Config, ConnectionCheck, ConnectionCheckKind, ProviderField, ProviderInfo, RemoteObject,
+    UploaderCapabilities,
+};
+use async_trait::async_trait;
 use reqwest::multipart;
 use sha1::{Digest, Sha1};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const API_BASE: &str = "https://api.cloudinary.com/v1_1";
+
+/// Where uploaded images are served from.
+const DELIVERY_BASE: &str = "https://res.cloudinary.com";
 
 pub struct CloudinaryUploader {
     cloud_name: String,
     api_key: String,
     api_secret: String,
+    api_base: String,
 }
 
 impl CloudinaryUploader {
@@ -14,27 +29,131 @@ impl CloudinaryUploader {
             cloud_name,
             api_key,
             api_secret,
+            api_base: API_BASE.to_string(),
+        }
+    }
+
+    /// Points the uploader at another API root, e.g. a local mock server.
+    pub fn with_api_base(mut self, api_base: impl Into<String>) -> Self {
+        self.api_base = api_base.into();
+        self
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(
+            config.cloudinary_cloud_name.clone(),
+            config.cloudinary_api_key.clone(),
+            config.cloudinary_api_secret.clone(),
+        )
+    }
+
+    pub fn provider_info() -> ProviderInfo {
+        ProviderInfo {
+            id: "cloudinary".to_string(),
+            name: "Cloudinary".to_string(),
+            fields: vec![
+                ProviderField::new("cloudinary_cloud_name", "Cloud Name"),
+                ProviderField::new("cloudinary_api_key", "API Key"),
+                ProviderField::new("cloudinary_api_secret", "API Secret").secret(),
+            ],
+            capabilities: UploaderCapabilities {
+                delete: true,
+                verify: true,
+            },
         }
     }
 
-    pub async fn upload(&self, image_data: &[u8], filename: &str) -> Result<String, String> {
-        // Generate timestamp
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+    /// Signs request parameters: sorted `key=value` pairs joined by `&`, followed by the secret.
+    fn sign(&self, params: &[(&str, &str)]) -> String {
+        let mut params = params.to_vec();
+        params.sort_by(|a, b| a.0.cmp(b.0));
+
+        let joined = params
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join("&");
 
-        // Generate signature
-        let signature_string = format!("timestamp={}{}", timestamp, self.api_secret);
         let mut hasher = Sha1::new();
-        hasher.update(signature_string.as_bytes());
-        let signature = hex::encode(hasher.finalize());
+        hasher.update(format!("{}{}", joined, self.api_secret).as_bytes());
+        hex::encode(hasher.finalize())
+    }
+
+    /// Extracts the public_id from a delivery URL such as
+    /// `https://res.cloudinary.com/<cloud>/image/upload/v1712345678/folder/name.webp`.
+    fn public_id_from_url(url: &str) -> Option<String> {
+        let (_, path) = url.split_once("/upload/")?;
+        let path = path.split(['?', '#']).next()?;
+
+        // Skip the optional version segment
+        let path = match path.split_once('/') {
+            Some((version, rest))
+                if version.len() > 1
+                    && version.starts_with('v')
+                    && version[1..].chars().all(|c| c.is_ascii_digit()) =>
+            {
+                rest
+            }
+            _ => path,
+        };
+
+        let public_id = match path.rsplit_once('.') {
+            Some((stem, extension)) if !extension.contains('/') => stem,
+            _ => path,
+        };
+
+        if public_id.is_empty() {
+            None
+        } else {
+            Some(public_id.to_string())
+        }
+    }
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Classifies an unsuccessful response, keeping Cloudinary's error text.
+async fn status_error(action: &str, response: reqwest::Response) -> UploadError {
+    let status = response.status().as_u16();
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_retry_after);
+    let error_text = response
+        .text()
+        .await
+        .unwrap_or_else(|_| "Unknown error".to_string());
+    UploadError::from_status(action, status, &error_text, retry_after)
+}
+
+#[async_trait]
+impl Uploader for CloudinaryUploader {
+    async fn upload(&self, image_data: &[u8], filename: &str) -> Result<RemoteObject, UploadError> {
+        self.upload_with_progress(image_data, filename, Arc::new(|_| {}))
+            .await
+    }
+
+    async fn upload_with_progress(
+        &self,
+        image_data: &[u8],
+        filename: &str,
+        progress: UploadProgress,
+    ) -> Result<RemoteObject, UploadError> {
+        let timestamp = unix_timestamp();
+        let signature = self.sign(&[("timestamp", &timestamp.to_string())]);
 
         // Build multipart form
-        let file_part = multipart::Part::bytes(image_data.to_vec())
+        let body = reqwest::Body::wrap_stream(progress_stream(Arc::from(image_data), progress));
+        let file_part = multipart::Part::stream_with_length(body, image_data.len() as u64)
             .file_name(filename.to_string())
-            .mime_str("image/webp")
-            .map_err(|e| format!("Failed to create file part: {}", e))?;
+            .mime_str(content_type(filename))
+            .map_err(|e| UploadError::other(format!("Failed to create file part: {}", e)))?;
 
         let form = multipart::Form::new()
             .part("file", file_part)
@@ -43,35 +162,143 @@ impl CloudinaryUploader {
             .text("signature", signature);
 
         // Upload
-        let url = format!(
-            "https://api.cloudinary.com/v1_1/{}/image/upload",
-            self.cloud_name
-        );
+        let url = format!("{}/{}/image/upload", self.api_base, self.cloud_name);
+
+        let client = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(10))
+            .timeout(Duration::from_secs(300))
+            .build()?;
+        let response = client.post(&url).multipart(form).send().await?;
+
+        if !response.status().is_success() {
+            return Err(status_error("Upload", response).await);
+        }
+
+        // Cloudinary has stored the image by now, so these are not retried
+        let json: serde_json::Value = response.json().await.map_err(|e| {
+            UploadError::unexpected_response(format!("Failed to parse response: {}", e))
+        })?;
+
+        let public_url = json["secure_url"]
+            .as_str()
+            .ok_or_else(|| UploadError::unexpected_response("No URL in response"))?
+            .to_string();
+
+        Ok(RemoteObject {
+            url: public_url,
+            public_id: json["public_id"].as_str().map(str::to_string),
+            etag: json["etag"].as_str().map(str::to_string),
+            size_bytes: json["bytes"].as_i64(),
+            width: json["width"].as_u64().map(|w| w as u32),
+            height: json["height"].as_u64().map(|h| h as u32),
+            ..RemoteObject::default()
+        })
+    }
+
+    async fn delete(&self, object: &RemoteObject) -> Result<(), UploadError> {
+        // Entries recorded before public_ids were stored only have the URL
+        let public_id = object
+            .public_id
+            .clone()
+            .or_else(|| Self::public_id_from_url(&object.url))
+            .ok_or_else(|| {
+                UploadError::other(format!(
+                    "Cannot determine Cloudinary public_id from {}",
+                    object.url
+                ))
+            })?;
+
+        let timestamp = unix_timestamp().to_string();
+        let signature = self.sign(&[("public_id", &public_id), ("timestamp", &timestamp)]);
+
+        let form = [
+            ("public_id", public_id.as_str()),
+            ("timestamp", timestamp.as_str()),
+            ("api_key", self.api_key.as_str()),
+            ("signature", signature.as_str()),
+        ];
+
+        let url = format!("{}/{}/image/destroy", self.api_base, self.cloud_name);
 
         let client = reqwest::Client::new();
         let response = client
             .post(&url)
-            .multipart(form)
+            .form(&form)
             .send()
             .await
-            .map_err(|e| format!("Upload request failed: {}", e))?;
+            .map_err(|e| UploadError::request("Delete", e))?;
 
         if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(format!("Upload failed: {}", error_text));
+            return Err(status_error("Delete", response).await);
+        }
+
+        let json: serde_json::Value = response.json().await.map_err(|e| {
+            UploadError::unexpected_response(format!("Failed to parse response: {}", e))
+        })?;
+
+        // "not found" means the asset is already gone, which is what we want
+        match json["result"].as_str() {
+            Some("ok") | Some("not found") => Ok(()),
+            other => Err(UploadError::other(format!(
+                "Delete failed: {}",
+                other.unwrap_or("Unknown error")
+            ))),
         }
+    }
+
+    async fn verify(&self) -> Result<(), UploadError> {
+        // The Admin API usage endpoint is read-only and authenticates with key/secret
+        let url = format!("{}/{}/usage", self.api_base, self.cloud_name);
 
-        let json: serde_json::Value = response
-            .json()
+        let client = reqwest::Client::new();
+        let response = client
+            .get(&url)
+            .basic_auth(&self.api_key, Some(&self.api_secret))
+            .send()
             .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?;
+            .map_err(|e| UploadError::request("Verification", e))?;
 
-        let public_url = json["secure_url"]
-            .as_str()
-            .ok_or("No URL in response")?
-            .to_string();
+        if !response.status().is_success() {
+            return Err(status_error("Verification", response).await);
+        }
 
-        Ok(public_url)
+        Ok(())
     }
-}
 
+    /// Read-only: the API host, the credentials through `verify`, and the
+    /// delivery domain. Nothing is uploaded, so `round_trip` has no effect.
+    async fn test_connection(&self, _round_trip: bool) -> Vec<ConnectionCheck> {
+        let mut checks = Vec::new();
+
+        if !diagnostics::check_host(&self.api_base, &mut checks).await {
+            diagnostics::skip(
+                &mut checks,
+                &[ConnectionCheckKind::Auth, ConnectionCheckKind::PublicUrl],
+                "The API is unreachable",
+            );
+            return checks;
+        }
+
+        checks.push(match self.verify().await {
+            Ok(()) => ConnectionCheck::passed(
+                ConnectionCheckKind::Auth,
+                format!("Credentials accepted for cloud {}", self.cloud_name),
+            ),
+            Err(e) if e.status == Some(404) => ConnectionCheck::failed(
+                ConnectionCheckKind::Auth,
+                format!("Cloud {} does not exist", self.cloud_name),
+            ),
+            Err(e) => diagnostics::failed(ConnectionCheckKind::Auth, &e),
+        });
+
+        checks.push(
+            diagnostics::check_public_domain(&format!("{}/{}/", DELIVERY_BASE, self.cloud_name))
+                .await,
+        );
+        checks
+    }
+
+    fn capabilities(&self) -> UploaderCapabilities {
+        Self::provider_info().capabilities
+    }
+}
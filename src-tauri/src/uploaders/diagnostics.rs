@@ -0,0 +1,217 @@
+//! Building blocks for `Uploader::test_connection`: each step records a
+//! `ConnectionCheck`, so the report says where a connection stops working
+//! instead of only that it does.
+
+use super::error::{UploadError, UploadErrorKind};
+use crate::models::{ConnectionCheck, ConnectionCheckKind};
+use reqwest::Url;
+use std::error::Error as _;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+pub(crate) fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(TIMEOUT)
+        .timeout(TIMEOUT)
+        .build()
+        .unwrap_or_default()
+}
+
+/// Resolves the host of `url` and, for https, completes a TLS handshake with
+/// it. Returns whether later steps can reach it.
+pub(crate) async fn check_host(url: &str, checks: &mut Vec<ConnectionCheck>) -> bool {
+    let parsed = match Url::parse(url) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            checks.push(ConnectionCheck::failed(
+                ConnectionCheckKind::Dns,
+                format!("{} is not a valid URL: {}", url, e),
+            ));
+            return false;
+        }
+    };
+    let host = parsed.host_str().unwrap_or_default().to_string();
+    let port = parsed.port_or_known_default().unwrap_or(443);
+
+    match tokio::time::timeout(TIMEOUT, tokio::net::lookup_host((host.as_str(), port))).await {
+        Ok(Ok(mut addresses)) => match addresses.next() {
+            Some(address) => checks.push(ConnectionCheck::passed(
+                ConnectionCheckKind::Dns,
+                format!("{} resolves to {}", host, address.ip()),
+            )),
+            None => {
+                checks.push(ConnectionCheck::failed(
+                    ConnectionCheckKind::Dns,
+                    format!("{} has no addresses", host),
+                ));
+                return false;
+            }
+        },
+        Ok(Err(e)) => {
+            checks.push(ConnectionCheck::failed(
+                ConnectionCheckKind::Dns,
+                format!("Failed to resolve {}: {}", host, e),
+            ));
+            return false;
+        }
+        Err(_) => {
+            checks.push(ConnectionCheck::failed(
+                ConnectionCheckKind::Dns,
+                format!("Resolving {} timed out", host),
+            ));
+            return false;
+        }
+    }
+
+    if parsed.scheme() != "https" {
+        checks.push(ConnectionCheck::skipped(
+            ConnectionCheckKind::Tls,
+            format!("{} is plain HTTP", host),
+        ));
+        return true;
+    }
+
+    // Any HTTP response, even an error status, means the handshake succeeded
+    let origin = format!("https://{}:{}/", host, port);
+    match client().head(&origin).send().await {
+        Ok(_) => {
+            checks.push(ConnectionCheck::passed(
+                ConnectionCheckKind::Tls,
+                format!("TLS handshake with {} succeeded", host),
+            ));
+            true
+        }
+        Err(e) => {
+            checks.push(ConnectionCheck::failed(
+                ConnectionCheckKind::Tls,
+                format!("Failed to connect to {}: {}", host, error_chain(&e)),
+            ));
+            false
+        }
+    }
+}
+
+/// Fetches `url` as a visitor would, expecting it to be served.
+pub(crate) async fn check_public_url(url: &str) -> ConnectionCheck {
+    match client().get(url).send().await {
+        Ok(response) if response.status().is_success() => ConnectionCheck::passed(
+            ConnectionCheckKind::PublicUrl,
+            format!("{} is publicly readable", url),
+        ),
+        Ok(response) => ConnectionCheck::failed(
+            ConnectionCheckKind::PublicUrl,
+            format!(
+                "{} answered HTTP {}; uploaded links will not open for others",
+                url,
+                response.status().as_u16()
+            ),
+        ),
+        Err(e) => ConnectionCheck::failed(
+            ConnectionCheckKind::PublicUrl,
+            format!("Failed to fetch {}: {}", url, error_chain(&e)),
+        ),
+    }
+}
+
+/// Checks that `domain` answers at all, for when no object was uploaded to fetch.
+pub(crate) async fn check_public_domain(domain: &str) -> ConnectionCheck {
+    match client().head(domain).send().await {
+        Ok(response) => ConnectionCheck::passed(
+            ConnectionCheckKind::PublicUrl,
+            format!(
+                "{} is reachable (HTTP {})",
+                domain,
+                response.status().as_u16()
+            ),
+        ),
+        Err(e) => ConnectionCheck::failed(
+            ConnectionCheckKind::PublicUrl,
+            format!("Failed to reach {}: {}", domain, error_chain(&e)),
+        ),
+    }
+}
+
+/// A failed step, explaining rejected credentials and missing permissions.
+pub(crate) fn failed(kind: ConnectionCheckKind, error: &UploadError) -> ConnectionCheck {
+    let detail = match (error.kind, error.status) {
+        (UploadErrorKind::Auth, Some(401)) => {
+            format!("Credentials were rejected: {}", error.message)
+        }
+        (UploadErrorKind::Auth, _) => format!(
+            "Access denied, check the key's permissions: {}",
+            error.message
+        ),
+        _ => error.message.clone(),
+    };
+
+    ConnectionCheck::failed(kind, detail)
+}
+
+/// Marks the steps that could not run after an earlier one failed.
+pub(crate) fn skip(checks: &mut Vec<ConnectionCheck>, kinds: &[ConnectionCheckKind], reason: &str) {
+    checks.extend(
+        kinds
+            .iter()
+            .map(|kind| ConnectionCheck::skipped(*kind, reason)),
+    );
+}
+
+/// reqwest hides the cause, such as a certificate error, behind "error sending
+/// request". Causes that already print their own source are not repeated.
+fn error_chain(error: &reqwest::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        let cause_message = cause.to_string();
+        if !message.contains(&cause_message) {
+            message = format!("{}: {}", message, cause_message);
+        }
+        source = cause.source();
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn error_chain_names_the_cause_once() {
+        // Nothing listens on a port that was just released
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let error = client()
+            .get(format!("http://127.0.0.1:{}/", port))
+            .send()
+            .await
+            .unwrap_err();
+
+        let chain = error_chain(&error);
+        assert!(chain.starts_with(&error.to_string()), "{}", chain);
+        assert!(chain.to_lowercase().contains("refused"), "{}", chain);
+        let parts: Vec<&str> = chain.split(": ").collect();
+        for (i, part) in parts.iter().enumerate() {
+            assert!(
+                !parts[i + 1..].contains(part),
+                "{} repeats in {}",
+                part,
+                chain
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn unreachable_public_url_fails() {
+        let check = check_public_url("http://127.0.0.1:9/probe.txt").await;
+        assert_eq!(check.kind, ConnectionCheckKind::PublicUrl);
+        assert!(
+            check.detail.starts_with("Failed to fetch"),
+            "{}",
+            check.detail
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::time::Duration;
+
+/// How an upload failure should be handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadErrorKind {
+    /// Timeouts, dropped connections, 408, 429 and 5xx: worth another attempt.
+    Transient,
+    /// Rejected credentials or missing permissions (401, 403).
+    Auth,
+    /// The provider refused the request itself (other 4xx).
+    Invalid,
+    /// A success status whose body could not be read or lacks the expected
+    /// fields. The object may exist already, so this is never retried.
+    UnexpectedResponse,
+    /// Anything else, such as a request that could not be built.
+    Other,
+}
+
+/// A failed provider request, classified so the caller knows whether to retry.
+#[derive(Debug, Clone)]
+pub struct UploadError {
+    pub kind: UploadErrorKind,
+    pub message: String,
+    pub status: Option<u16>,
+    /// The provider's error text, when it sent one.
+    pub body: Option<String>,
+    /// How long the provider asked us to wait, from `Retry-After`.
+    pub retry_after: Option<Duration>,
+}
+
+impl UploadError {
+    pub fn new(kind: UploadErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            status: None,
+            body: None,
+            retry_after: None,
+        }
+    }
+
+    pub fn other(message: impl Into<String>) -> Self {
+        Self::new(UploadErrorKind::Other, message)
+    }
+
+    pub fn unexpected_response(message: impl Into<String>) -> Self {
+        Self::new(UploadErrorKind::UnexpectedResponse, message)
+    }
+
+    /// Classifies an unsuccessful HTTP response. `action` names the request
+    /// in the message, as in "Upload failed with HTTP 403: ...".
+    pub fn from_status(
+        action: &str,
+        status: u16,
+        body: &str,
+        retry_after: Option<Duration>,
+    ) -> Self {
+        let kind = match status {
+            408 | 429 | 500..=599 => UploadErrorKind::Transient,
+            401 | 403 => UploadErrorKind::Auth,
+            400..=499 => UploadErrorKind::Invalid,
+            _ => UploadErrorKind::Other,
+        };
+
+        let body = body.trim();
+        let message = if body.is_empty() {
+            format!("{} failed with HTTP {}", action, status)
+        } else {
+            format!("{} failed with HTTP {}: {}", action, status, body)
+        };
+
+        Self {
+            kind,
+            message,
+            status: Some(status),
+            body: (!body.is_empty()).then(|| body.to_string()),
+            retry_after,
+        }
+    }
+
+    /// Classifies a request that got no usable response. `action` names it
+    /// in the message, as in `from_status`.
+    pub fn request(action: &str, error: reqwest::Error) -> Self {
+        if let Some(status) = error.status() {
+            return Self::from_status(action, status.as_u16(), &error.to_string(), None);
+        }
+
+        // `is_request` covers connections dropped while the body was being sent
+        let kind = if error.is_timeout() || error.is_connect() || error.is_request() {
+            UploadErrorKind::Transient
+        } else {
+            UploadErrorKind::Other
+        };
+
+        Self::new(kind, format!("{} request failed: {}", action, error))
+    }
+
+    pub fn is_transient(&self) -> bool {
+        self.kind == UploadErrorKind::Transient
+    }
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for UploadError {}
+
+impl From<reqwest::Error> for UploadError {
+    fn from(error: reqwest::Error) -> Self {
+        Self::request("Upload", error)
+    }
+}
+
+/// Parses a `Retry-After` value, given either as seconds or as an HTTP date.
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_after_in_seconds() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 5 "), Some(Duration::from_secs(5)));
+        assert_eq!(parse_retry_after("0"), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn retry_after_as_http_date() {
+        let date = (Utc::now() + chrono::Duration::seconds(90)).to_rfc2822();
+        let wait = parse_retry_after(&date).unwrap();
+        assert!(
+            wait > Duration::from_secs(85) && wait <= Duration::from_secs(90),
+            "{:?}",
+            wait
+        );
+
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn retry_after_rejects_anything_else() {
+        for value in ["", "-1", "1.5", "soon", "2015-10-21T07:28:00Z"] {
+            assert_eq!(parse_retry_after(value), None, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn classifies_statuses() {
+        for (status, kind) in [
+            (408, UploadErrorKind::Transient),
+            (429, UploadErrorKind::Transient),
+            (500, UploadErrorKind::Transient),
+            (503, UploadErrorKind::Transient),
+            (401, UploadErrorKind::Auth),
+            (403, UploadErrorKind::Auth),
+            (400, UploadErrorKind::Invalid),
+            (413, UploadErrorKind::Invalid),
+            (302, UploadErrorKind::Other),
+        ] {
+            assert_eq!(
+                UploadError::from_status("Upload", status, "", None).kind,
+                kind
+            );
+        }
+    }
+}
@@ -1,3 +1,118 @@
 pub mod cloudinary;
+pub mod diagnostics;
+pub mod error;
 pub mod r2;
+pub mod registry;
+pub mod retry;
+pub mod s3;
 
+use self::error::UploadError;
+use self::retry::RetryHook;
+use crate::models::{ConnectionCheck, ConnectionCheckKind, RemoteObject, UploaderCapabilities};
+use async_trait::async_trait;
+use futures_util::stream::{self, Stream};
+use std::sync::Arc;
+
+/// Size of the body chunks progress is reported for.
+const PROGRESS_CHUNK: usize = 64 * 1024;
+
+/// Called with the number of bytes handed to the HTTP client so far.
+pub type UploadProgress = Arc<dyn Fn(u64) + Send + Sync>;
+
+/// Callbacks for following an upload from the outside.
+#[derive(Clone)]
+pub struct UploadHooks {
+    pub progress: UploadProgress,
+    pub on_retry: RetryHook,
+}
+
+impl Default for UploadHooks {
+    fn default() -> Self {
+        Self {
+            progress: Arc::new(|_| {}),
+            on_retry: Arc::new(|_| {}),
+        }
+    }
+}
+
+#[async_trait]
+pub trait Uploader: Send + Sync {
+    /// Uploads the bytes and returns the public URL plus whatever identifiers
+    /// the provider reports for the stored object. Errors are classified so
+    /// the caller can retry transient ones.
+    async fn upload(&self, image_data: &[u8], filename: &str) -> Result<RemoteObject, UploadError>;
+
+    /// Like `upload`, reporting progress while the body is sent. Providers
+    /// that cannot stream report the whole size once the upload is done.
+    async fn upload_with_progress(
+        &self,
+        image_data: &[u8],
+        filename: &str,
+        progress: UploadProgress,
+    ) -> Result<RemoteObject, UploadError> {
+        let object = self.upload(image_data, filename).await?;
+        progress(image_data.len() as u64);
+        Ok(object)
+    }
+
+    /// Removes a previously uploaded object from the remote storage.
+    async fn delete(&self, _object: &RemoteObject) -> Result<(), UploadError> {
+        Err(UploadError::other(
+            "Delete is not supported by this provider",
+        ))
+    }
+
+    /// Checks that the configured credentials are accepted by the provider.
+    async fn verify(&self) -> Result<(), UploadError> {
+        Err(UploadError::other(
+            "Verification is not supported by this provider",
+        ))
+    }
+
+    /// Diagnoses the connection step by step without leaving anything behind.
+    /// With `round_trip`, providers that can also write, read back and delete
+    /// a small probe object. Defaults to `verify` alone.
+    async fn test_connection(&self, _round_trip: bool) -> Vec<ConnectionCheck> {
+        vec![match self.verify().await {
+            Ok(()) => ConnectionCheck::passed(ConnectionCheckKind::Auth, "Credentials accepted"),
+            Err(e) => diagnostics::failed(ConnectionCheckKind::Auth, &e),
+        }]
+    }
+
+    fn capabilities(&self) -> UploaderCapabilities;
+}
+
+/// MIME type for a file extension, as sent to providers and used for previews.
+pub fn mime_type(extension: &str) -> &'static str {
+    match extension.to_ascii_lowercase().as_str() {
+        "webp" => "image/webp",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "avif" => "image/avif",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        "tif" | "tiff" => "image/tiff",
+        "ico" => "image/x-icon",
+        "txt" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+/// The body as a stream of chunks, calling `progress` as each one is taken.
+pub(crate) fn progress_stream(
+    data: Arc<[u8]>,
+    progress: UploadProgress,
+) -> impl Stream<Item = Result<Vec<u8>, std::io::Error>> + Send + 'static {
+    let total = data.len();
+    stream::iter((0..total).step_by(PROGRESS_CHUNK).map(move |start| {
+        let end = (start + PROGRESS_CHUNK).min(total);
+        progress(end as u64);
+        Ok(data[start..end].to_vec())
+    }))
+}
+
+/// MIME type for an upload, derived from its file name.
+pub fn content_type(filename: &str) -> &'static str {
+    mime_type(filename.rsplit('.').next().unwrap_or_default())
+}
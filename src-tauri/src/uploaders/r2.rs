@@ -1,14 +1,15 @@
-use aws_sdk_s3::config::{Credentials, Region};
-use aws_sdk_s3::primitives::ByteStream;
-use aws_sdk_s3::Client;
-use uuid::Uuid;
+use super::error::UploadError;
+use super::s3::{S3Settings, S3Uploader};
+use super::{UploadProgress, Uploader};
+use crate::models::{
+    Config, ConnectionCheck, ProviderField, ProviderInfo, RemoteObject, UploaderCapabilities,
+};
+use async_trait::async_trait;
+use std::sync::Arc;
 
+/// Cloudflare R2: an S3 bucket in the `auto` region served from a public domain.
 pub struct R2Uploader {
-    access_key_id: String,
-    secret_access_key: String,
-    bucket_name: String,
-    endpoint: String,
-    public_domain: String,
+    inner: S3Uploader,
 }
 
 impl R2Uploader {
@@ -20,55 +21,89 @@ impl R2Uploader {
         public_domain: String,
     ) -> Self {
         Self {
-            access_key_id,
-            secret_access_key,
-            bucket_name,
-            endpoint,
-            public_domain,
+            inner: S3Uploader::new(S3Settings {
+                access_key_id,
+                secret_access_key,
+                bucket_name,
+                region: "auto".to_string(),
+                endpoint,
+                public_domain,
+                ..S3Settings::default()
+            }),
         }
     }
 
-    pub async fn upload(&self, image_data: &[u8], filename: &str) -> Result<String, String> {
-        // Generate unique filename
-        let extension = filename.split('.').last().unwrap_or("webp");
-        let unique_filename = format!("{}.{}", Uuid::new_v4(), extension);
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(
+            config.r2_access_key_id.clone(),
+            config.r2_secret_access_key.clone(),
+            config.r2_bucket_name.clone(),
+            config.r2_endpoint.clone(),
+            config.r2_public_domain.clone(),
+        )
+    }
 
-        // Create credentials
-        let creds = Credentials::new(
-            &self.access_key_id,
-            &self.secret_access_key,
-            None,
-            None,
-            "r2-uploader",
-        );
+    pub fn provider_info() -> ProviderInfo {
+        ProviderInfo {
+            id: "r2".to_string(),
+            name: "Cloudflare R2".to_string(),
+            fields: vec![
+                ProviderField::new("r2_access_key_id", "Access Key ID"),
+                ProviderField::new("r2_secret_access_key", "Secret Access Key").secret(),
+                ProviderField::new("r2_bucket_name", "Bucket Name"),
+                ProviderField::new("r2_endpoint", "Endpoint"),
+                ProviderField::new("r2_public_domain", "Public Domain"),
+            ],
+            capabilities: UploaderCapabilities {
+                delete: true,
+                verify: true,
+            },
+        }
+    }
+}
 
-        // Create config
-        let region = Region::new("auto");
-        let config = aws_sdk_s3::config::Builder::new()
-            .credentials_provider(creds)
-            .region(region)
-            .endpoint_url(&self.endpoint)
-            .build();
+#[async_trait]
+impl Uploader for R2Uploader {
+    async fn upload(&self, image_data: &[u8], filename: &str) -> Result<RemoteObject, UploadError> {
+        self.upload_with_progress(image_data, filename, Arc::new(|_| {}))
+            .await
+    }
 
-        let client = Client::from_conf(config);
+    async fn upload_with_progress(
+        &self,
+        image_data: &[u8],
+        filename: &str,
+        progress: UploadProgress,
+    ) -> Result<RemoteObject, UploadError> {
+        self.inner
+            .put(
+                &self.inner.client("r2-uploader"),
+                image_data,
+                filename,
+                progress,
+            )
+            .await
+    }
 
-        // Upload
-        let byte_stream = ByteStream::from(image_data.to_vec());
+    async fn delete(&self, object: &RemoteObject) -> Result<(), UploadError> {
+        self.inner
+            .delete_object(&self.inner.client("r2-uploader"), object)
+            .await
+    }
 
-        client
-            .put_object()
-            .bucket(&self.bucket_name)
-            .key(&unique_filename)
-            .body(byte_stream)
-            .content_type("image/webp")
-            .send()
+    async fn verify(&self) -> Result<(), UploadError> {
+        self.inner
+            .head_bucket(&self.inner.client("r2-uploader"))
             .await
-            .map_err(|e| format!("Upload failed: {}", e))?;
+    }
 
-        // Construct public URL
-        let public_url = format!("{}/{}", self.public_domain.trim_end_matches('/'), unique_filename);
+    async fn test_connection(&self, round_trip: bool) -> Vec<ConnectionCheck> {
+        self.inner
+            .diagnose(&self.inner.client("r2-uploader"), round_trip)
+            .await
+    }
 
-        Ok(public_url)
+    fn capabilities(&self) -> UploaderCapabilities {
+        Self::provider_info().capabilities
     }
 }
-
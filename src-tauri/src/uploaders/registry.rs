@@ -0,0 +1,110 @@
+use super::cloudinary::CloudinaryUploader;
+use super::r2::R2Uploader;
+use super::s3::S3Uploader;
+use super::Uploader;
+use crate::error::{AppError, ErrorCode};
+use crate::models::{Config, ProviderField, ProviderInfo};
+
+type UploaderFactory = fn(&Config) -> Box<dyn Uploader>;
+
+struct ProviderEntry {
+    info: ProviderInfo,
+    factory: UploaderFactory,
+}
+
+/// Provider ids mapped to their descriptor and a constructor reading `Config`.
+pub struct UploaderRegistry {
+    entries: Vec<ProviderEntry>,
+}
+
+impl UploaderRegistry {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register(CloudinaryUploader::provider_info(), |config| {
+            Box::new(CloudinaryUploader::from_config(config))
+        });
+        registry.register(R2Uploader::provider_info(), |config| {
+            Box::new(R2Uploader::from_config(config))
+        });
+        registry.register(S3Uploader::provider_info(), |config| {
+            Box::new(S3Uploader::from_config(config))
+        });
+        registry
+    }
+
+    /// Adds a provider, replacing any previous entry with the same id.
+    pub fn register(&mut self, info: ProviderInfo, factory: UploaderFactory) {
+        self.entries.retain(|entry| entry.info.id != info.id);
+        self.entries.push(ProviderEntry { info, factory });
+    }
+
+    pub fn providers(&self) -> Vec<ProviderInfo> {
+        self.entries
+            .iter()
+            .map(|entry| entry.info.clone())
+            .collect()
+    }
+
+    pub fn info(&self, provider: &str) -> Option<&ProviderInfo> {
+        self.entries
+            .iter()
+            .find(|entry| entry.info.id == provider)
+            .map(|entry| &entry.info)
+    }
+
+    pub fn build(&self, provider: &str, config: &Config) -> Result<Box<dyn Uploader>, AppError> {
+        let entry = self
+            .entries
+            .iter()
+            .find(|entry| entry.info.id == provider)
+            .ok_or_else(|| {
+                AppError::new(
+                    ErrorCode::UnknownProvider,
+                    format!("Invalid provider: {}", provider),
+                )
+            })?;
+
+        let missing = missing_fields(&entry.info.fields, config);
+        if !missing.is_empty() {
+            let labels: Vec<&str> = missing.iter().map(|field| field.label.as_str()).collect();
+            return Err(AppError::new(
+                ErrorCode::ProviderNotConfigured,
+                format!(
+                    "{} is not configured, missing: {}",
+                    entry.info.name,
+                    labels.join(", ")
+                ),
+            )
+            .with_provider(provider)
+            .with_fields(missing.iter().map(|field| field.key.clone()).collect()));
+        }
+
+        Ok((entry.factory)(config))
+    }
+}
+
+impl Default for UploaderRegistry {
+    fn default() -> Self {
+        Self::with_defaults()
+    }
+}
+
+fn missing_fields<'a>(fields: &'a [ProviderField], config: &Config) -> Vec<&'a ProviderField> {
+    let values = serde_json::to_value(config).unwrap_or_default();
+
+    fields
+        .iter()
+        .filter(|field| field.required)
+        .filter(|field| {
+            values[field.key.as_str()]
+                .as_str()
+                .is_none_or(|value| value.trim().is_empty())
+        })
+        .collect()
+}
@@ -0,0 +1,351 @@
+use super::error::UploadError;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Called after a failed attempt that will be retried.
+pub type RetryHook = Arc<dyn Fn(&RetryNotice) + Send + Sync>;
+
+/// A failed attempt and how long until the next one.
+pub struct RetryNotice<'a> {
+    /// 1-based number of the attempt that failed.
+    pub attempt: u32,
+    pub delay: Duration,
+    pub error: &'a UploadError,
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    /// Upper bound for backoff and for the wait a `Retry-After` asks for.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// How long to wait after `attempt` failed with `error`, or `None` to give up.
+    /// The provider's `Retry-After` wins, up to `max_delay`; otherwise the
+    /// window doubles each attempt and the wait is picked at random from its upper half.
+    pub fn delay(&self, attempt: u32, error: &UploadError) -> Option<Duration> {
+        if !error.is_transient() || attempt >= self.max_attempts {
+            return None;
+        }
+
+        // A longer wait is still worth trying; the attempt may just be throttled again
+        if let Some(wait) = error.retry_after {
+            return Some(wait.min(self.max_delay));
+        }
+
+        let window = self
+            .base_delay
+            .saturating_mul(1 << (attempt - 1).min(16))
+            .min(self.max_delay);
+        Some(window.mul_f64(0.5 + rand::random::<f64>() / 2.0))
+    }
+}
+
+/// Runs `operation` until it succeeds, fails permanently or runs out of
+/// attempts. The final error mentions how many attempts were made.
+pub async fn with_retry<T, F, Fut>(
+    policy: &RetryPolicy,
+    on_retry: &RetryHook,
+    mut operation: F,
+) -> Result<T, UploadError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, UploadError>>,
+{
+    let mut attempt = 1;
+    loop {
+        let error = match operation().await {
+            Ok(value) => return Ok(value),
+            Err(error) => error,
+        };
+
+        let Some(delay) = policy.delay(attempt, &error) else {
+            return Err(if attempt > 1 {
+                UploadError {
+                    message: format!("{} (after {} attempts)", error.message, attempt),
+                    ..error
+                }
+            } else {
+                error
+            });
+        };
+
+        on_retry(&RetryNotice {
+            attempt,
+            delay,
+            error: &error,
+        });
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uploaders::cloudinary::CloudinaryUploader;
+    use crate::uploaders::error::UploadErrorKind;
+    use crate::uploaders::Uploader;
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Response, Server};
+    use std::collections::VecDeque;
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+        }
+    }
+
+    fn transient(retry_after: Option<Duration>) -> UploadError {
+        UploadError::from_status("Upload", 503, "", retry_after)
+    }
+
+    #[test]
+    fn gives_up_on_permanent_errors() {
+        for status in [400, 401, 403, 404] {
+            let error = UploadError::from_status("Upload", status, "", None);
+            assert_eq!(policy().delay(1, &error), None, "HTTP {}", status);
+        }
+        assert_eq!(policy().delay(1, &UploadError::other("bad response")), None);
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        assert!(policy().delay(3, &transient(None)).is_some());
+        assert_eq!(policy().delay(4, &transient(None)), None);
+    }
+
+    #[test]
+    fn waits_as_long_as_retry_after_asks() {
+        let wait = Duration::from_millis(750);
+        assert_eq!(policy().delay(1, &transient(Some(wait))), Some(wait));
+        assert_eq!(
+            policy().delay(1, &transient(Some(Duration::ZERO))),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn caps_retry_after_at_max_delay() {
+        let error = transient(Some(Duration::from_secs(120)));
+        assert_eq!(policy().delay(1, &error), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn backoff_doubles_within_the_upper_half_of_each_window() {
+        for (attempt, window) in [(1, 100), (2, 200), (3, 400)] {
+            let window = Duration::from_millis(window);
+            for _ in 0..50 {
+                let delay = policy().delay(attempt, &transient(None)).unwrap();
+                assert!(
+                    delay >= window / 2 && delay <= window,
+                    "attempt {}: {:?}",
+                    attempt,
+                    delay
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn backoff_is_capped_at_max_delay() {
+        let policy = RetryPolicy {
+            max_attempts: 100,
+            ..policy()
+        };
+        for attempt in [5, 20, 64, 99] {
+            let delay = policy.delay(attempt, &transient(None)).unwrap();
+            assert!(
+                delay >= policy.max_delay / 2 && delay <= policy.max_delay,
+                "attempt {}: {:?}",
+                attempt,
+                delay
+            );
+        }
+    }
+
+    /// Status, `Retry-After` and body of each response, in order.
+    type Script = Vec<(u16, Option<&'static str>, &'static str)>;
+
+    /// Serves `script` on a local port, one response per request, and returns
+    /// its address and a count of the requests received.
+    async fn mock_server(script: Script) -> (SocketAddr, Arc<AtomicUsize>) {
+        let responses = Arc::new(Mutex::new(VecDeque::from(script)));
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+
+        let make_service = make_service_fn(move |_| {
+            let responses = responses.clone();
+            let counter = counter.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: hyper::Request<Body>| {
+                    let responses = responses.clone();
+                    let counter = counter.clone();
+                    async move {
+                        // Read the whole upload so the client sees the response, not a reset
+                        let _ = hyper::body::to_bytes(request.into_body()).await;
+                        counter.fetch_add(1, Ordering::SeqCst);
+
+                        let (status, retry_after, body) = responses
+                            .lock()
+                            .unwrap()
+                            .pop_front()
+                            .unwrap_or((500, None, "script exhausted"));
+                        let mut response = Response::builder().status(status);
+                        if let Some(retry_after) = retry_after {
+                            response = response.header("Retry-After", retry_after);
+                        }
+                        Ok::<_, Infallible>(response.body(Body::from(body)).unwrap())
+                    }
+                }))
+            }
+        });
+
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        (addr, requests)
+    }
+
+    /// Uploads through Cloudinary pointed at `addr`, recording each retry
+    /// notice as (failed attempt, delay, status).
+    async fn upload(
+        addr: SocketAddr,
+        policy: &RetryPolicy,
+    ) -> (
+        Result<crate::models::RemoteObject, UploadError>,
+        Vec<(u32, Duration, Option<u16>)>,
+    ) {
+        let uploader = CloudinaryUploader::new("demo".into(), "key".into(), "secret".into())
+            .with_api_base(format!("http://{}", addr));
+        let notices = Arc::new(Mutex::new(Vec::new()));
+        let recorded = notices.clone();
+        let on_retry: RetryHook = Arc::new(move |notice: &RetryNotice| {
+            recorded
+                .lock()
+                .unwrap()
+                .push((notice.attempt, notice.delay, notice.error.status));
+        });
+
+        let result = with_retry(policy, &on_retry, || uploader.upload(b"image", "a.png")).await;
+        let notices = notices.lock().unwrap().clone();
+        (result, notices)
+    }
+
+    const UPLOADED: &str = r#"{"secure_url": "https://res.cloudinary.com/demo/image/upload/v1/a.png", "public_id": "a"}"#;
+
+    #[tokio::test]
+    async fn retries_throttling_and_server_errors_until_success() {
+        let (addr, requests) = mock_server(vec![
+            (429, Some("0"), r#"{"error": {"message": "Rate limited"}}"#),
+            (503, None, "Service Unavailable"),
+            (200, None, UPLOADED),
+        ])
+        .await;
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(10),
+            ..policy()
+        };
+
+        let (result, notices) = upload(addr, &policy).await;
+
+        let object = result.unwrap();
+        assert_eq!(
+            object.url,
+            "https://res.cloudinary.com/demo/image/upload/v1/a.png"
+        );
+        assert_eq!(object.public_id.as_deref(), Some("a"));
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+        assert_eq!(notices.len(), 2);
+        assert_eq!(notices[0], (1, Duration::ZERO, Some(429)));
+        let (attempt, delay, status) = notices[1];
+        assert_eq!((attempt, status), (2, Some(503)));
+        assert!(delay >= Duration::from_millis(10) && delay <= Duration::from_millis(20));
+    }
+
+    #[tokio::test]
+    async fn long_retry_after_waits_max_delay_instead_of_giving_up() {
+        let (addr, requests) = mock_server(vec![
+            (429, Some("3600"), "Slow down"),
+            (200, None, UPLOADED),
+        ])
+        .await;
+        let policy = RetryPolicy {
+            max_delay: Duration::from_millis(20),
+            ..policy()
+        };
+
+        let (result, notices) = upload(addr, &policy).await;
+
+        assert!(result.is_ok());
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        assert_eq!(notices, vec![(1, Duration::from_millis(20), Some(429))]);
+    }
+
+    #[tokio::test]
+    async fn reports_attempts_when_retries_run_out() {
+        let (addr, requests) = mock_server(vec![(503, Some("0"), "Down"); 4]).await;
+
+        let (result, notices) = upload(addr, &policy()).await;
+
+        let error = result.unwrap_err();
+        assert_eq!(error.kind, UploadErrorKind::Transient);
+        assert_eq!(error.status, Some(503));
+        assert!(
+            error.message.ends_with("(after 4 attempts)"),
+            "{}",
+            error.message
+        );
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+        assert_eq!(notices.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_rejected_credentials() {
+        let (addr, requests) = mock_server(vec![(401, None, "Invalid API key")]).await;
+
+        let (result, notices) = upload(addr, &policy()).await;
+
+        let error = result.unwrap_err();
+        assert_eq!(error.kind, UploadErrorKind::Auth);
+        assert_eq!(
+            error.message,
+            "Upload failed with HTTP 401: Invalid API key"
+        );
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        assert!(notices.is_empty());
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_a_success_without_a_url() {
+        let (addr, requests) = mock_server(vec![(200, None, r#"{"public_id": "a"}"#)]).await;
+
+        let (result, notices) = upload(addr, &policy()).await;
+
+        let error = result.unwrap_err();
+        assert_eq!(error.kind, UploadErrorKind::UnexpectedResponse);
+        assert_eq!(error.message, "No URL in response");
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        assert!(notices.is_empty());
+    }
+}
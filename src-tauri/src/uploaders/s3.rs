@@ -0,0 +1,781 @@
+use super::diagnostics;
+use super::error::{parse_retry_after, UploadError, UploadErrorKind};
+use super::{content_type, progress_stream, UploadProgress, Uploader};
+use crate::models::{
+    Config, ConnectionCheck, ConnectionCheckKind, ProviderField, ProviderInfo, RemoteObject,
+    UploaderCapabilities,
+};
+use async_trait::async_trait;
+use aws_sdk_s3::config::http::HttpResponse;
+use aws_sdk_s3::config::retry::RetryConfig;
+use aws_sdk_s3::config::{Credentials, Region, RequestChecksumCalculation};
+use aws_sdk_s3::error::{DisplayErrorContext, ProvideErrorMetadata, SdkError};
+use aws_sdk_s3::primitives::{ByteStream, SdkBody};
+use aws_sdk_s3::types::ObjectCannedAcl;
+use aws_sdk_s3::Client;
+use std::sync::Arc;
+use uuid::Uuid;
+
+const DEFAULT_REGION: &str = "us-east-1";
+
+/// Body of the object written by the connection test's round trip.
+const PROBE: &[u8] = b"opti-bridge connection test";
+
+/// Connection and object settings for any S3-compatible service.
+#[derive(Debug, Clone, Default)]
+pub struct S3Settings {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub bucket_name: String,
+    /// Empty means `us-east-1`.
+    pub region: String,
+    /// Empty means the AWS endpoint for `region`.
+    pub endpoint: String,
+    pub force_path_style: bool,
+    /// Empty means URLs point straight at the bucket.
+    pub public_domain: String,
+    pub acl: String,
+    pub cache_control: String,
+}
+
+pub struct S3Uploader {
+    settings: S3Settings,
+}
+
+impl S3Uploader {
+    pub fn new(settings: S3Settings) -> Self {
+        Self { settings }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(S3Settings {
+            access_key_id: config.s3_access_key_id.clone(),
+            secret_access_key: config.s3_secret_access_key.clone(),
+            bucket_name: config.s3_bucket_name.clone(),
+            region: config.s3_region.clone(),
+            endpoint: config.s3_endpoint.clone(),
+            force_path_style: config.s3_force_path_style,
+            public_domain: config.s3_public_domain.clone(),
+            acl: config.s3_acl.clone(),
+            cache_control: config.s3_cache_control.clone(),
+        })
+    }
+
+    pub fn provider_info() -> ProviderInfo {
+        ProviderInfo {
+            id: "s3".to_string(),
+            name: "S3 Compatible".to_string(),
+            fields: vec![
+                ProviderField::new("s3_access_key_id", "Access Key ID"),
+                ProviderField::new("s3_secret_access_key", "Secret Access Key").secret(),
+                ProviderField::new("s3_bucket_name", "Bucket Name"),
+                ProviderField::new("s3_region", "Region").optional(),
+                ProviderField::new("s3_endpoint", "Endpoint").optional(),
+                ProviderField::new("s3_force_path_style", "Path-Style Addressing").toggle(),
+                ProviderField::new("s3_public_domain", "Public Domain").optional(),
+                ProviderField::new("s3_acl", "ACL").optional(),
+                ProviderField::new("s3_cache_control", "Cache-Control").optional(),
+            ],
+            capabilities: UploaderCapabilities {
+                delete: true,
+                verify: true,
+            },
+        }
+    }
+
+    fn region(&self) -> &str {
+        match self.settings.region.trim() {
+            "" => DEFAULT_REGION,
+            region => region,
+        }
+    }
+
+    /// The API endpoint requests go to.
+    fn endpoint_url(&self) -> String {
+        match self.settings.endpoint.trim() {
+            "" => format!("https://s3.{}.amazonaws.com", self.region()),
+            endpoint => endpoint.to_string(),
+        }
+    }
+
+    pub(crate) fn client(&self, credentials_name: &'static str) -> Client {
+        let creds = Credentials::new(
+            &self.settings.access_key_id,
+            &self.settings.secret_access_key,
+            None,
+            None,
+            credentials_name,
+        );
+
+        let mut builder = aws_sdk_s3::config::Builder::new()
+            .behavior_version_latest()
+            .credentials_provider(creds)
+            .region(Region::new(self.region().to_string()))
+            .force_path_style(self.settings.force_path_style)
+            // Streamed bodies would otherwise go out aws-chunked with a checksum
+            // trailer, which not every S3-compatible service accepts
+            .request_checksum_calculation(RequestChecksumCalculation::WhenRequired);
+
+        if !self.settings.endpoint.trim().is_empty() {
+            builder = builder.endpoint_url(self.settings.endpoint.trim());
+        }
+
+        Client::from_conf(builder.build())
+    }
+
+    /// Builds the public URL for `key`, preferring the CDN domain when one is set.
+    pub fn public_url(&self, key: &str) -> String {
+        let domain = self.settings.public_domain.trim();
+        if !domain.is_empty() {
+            return format!("{}/{}", domain.trim_end_matches('/'), key);
+        }
+
+        self.bucket_url(key)
+    }
+
+    /// The URL of `key` on the storage endpoint itself, in the configured addressing style.
+    fn bucket_url(&self, key: &str) -> String {
+        let bucket = &self.settings.bucket_name;
+        let endpoint = self.settings.endpoint.trim().trim_end_matches('/');
+
+        if endpoint.is_empty() {
+            return if self.settings.force_path_style {
+                format!(
+                    "https://s3.{}.amazonaws.com/{}/{}",
+                    self.region(),
+                    bucket,
+                    key
+                )
+            } else {
+                format!(
+                    "https://{}.s3.{}.amazonaws.com/{}",
+                    bucket,
+                    self.region(),
+                    key
+                )
+            };
+        }
+
+        if self.settings.force_path_style {
+            return format!("{}/{}/{}", endpoint, bucket, key);
+        }
+
+        match endpoint.split_once("://") {
+            Some((scheme, host)) => format!("{}://{}.{}/{}", scheme, bucket, host, key),
+            None => format!("https://{}.{}/{}", bucket, endpoint, key),
+        }
+    }
+
+    /// Recovers the object key from a URL built by `public_url`, or from the
+    /// bucket's endpoint URL as recorded before a public domain was set. Any
+    /// other URL gives `None`, since it may name an object of another bucket.
+    pub fn key_from_url(&self, url: &str) -> Option<String> {
+        let url = url.split(['?', '#']).next()?;
+        let key = url
+            .strip_prefix(&self.public_url(""))
+            .or_else(|| url.strip_prefix(&self.bucket_url("")))?;
+
+        if key.is_empty() {
+            None
+        } else {
+            Some(key.to_string())
+        }
+    }
+
+    /// Uploads under a random key, streaming the body so `progress` follows it.
+    pub(crate) async fn put(
+        &self,
+        client: &Client,
+        image_data: &[u8],
+        filename: &str,
+        progress: UploadProgress,
+    ) -> Result<RemoteObject, UploadError> {
+        // Generate unique filename
+        let extension = filename.rsplit('.').next().unwrap_or("webp");
+        let key = format!("{}.{}", Uuid::new_v4(), extension);
+
+        // Retries need a fresh body, so rebuild the stream from the shared bytes
+        let data: Arc<[u8]> = Arc::from(image_data);
+        let body = SdkBody::retryable(move || {
+            let stream = progress_stream(data.clone(), progress.clone());
+            SdkBody::from_body_0_4(hyper::Body::wrap_stream(stream))
+        });
+
+        let mut request = client
+            .put_object()
+            .bucket(&self.settings.bucket_name)
+            .key(&key)
+            .body(ByteStream::new(body))
+            .content_length(image_data.len() as i64)
+            .content_type(content_type(filename));
+
+        if !self.settings.acl.trim().is_empty() {
+            request = request.acl(ObjectCannedAcl::from(self.settings.acl.trim()));
+        }
+        if !self.settings.cache_control.trim().is_empty() {
+            request = request.cache_control(self.settings.cache_control.trim());
+        }
+
+        // Retries are left to the caller, which classifies and reports them
+        let output = request
+            .customize()
+            .config_override(
+                aws_sdk_s3::config::Builder::new().retry_config(RetryConfig::disabled()),
+            )
+            .send()
+            .await
+            .map_err(|e| classify("Upload", e))?;
+
+        Ok(RemoteObject {
+            url: self.public_url(&key),
+            bucket: Some(self.settings.bucket_name.clone()),
+            etag: output
+                .e_tag()
+                .map(|etag| etag.trim_matches('"').to_string()),
+            size_bytes: Some(image_data.len() as i64),
+            object_key: Some(key),
+            ..RemoteObject::default()
+        })
+    }
+
+    pub(crate) async fn delete_object(
+        &self,
+        client: &Client,
+        object: &RemoteObject,
+    ) -> Result<(), UploadError> {
+        // Entries recorded before keys were stored only have the URL
+        let key = object
+            .object_key
+            .clone()
+            .or_else(|| self.key_from_url(&object.url))
+            .ok_or_else(|| {
+                UploadError::other(format!(
+                    "Cannot determine object key: {} is not under this bucket's public domain or endpoint",
+                    object.url
+                ))
+            })?;
+        let bucket = object
+            .bucket
+            .as_deref()
+            .unwrap_or(&self.settings.bucket_name);
+
+        client
+            .delete_object()
+            .bucket(bucket)
+            .key(&key)
+            .send()
+            .await
+            .map_err(|e| classify("Delete", e))?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn head_bucket(&self, client: &Client) -> Result<(), UploadError> {
+        client
+            .head_bucket()
+            .bucket(&self.settings.bucket_name)
+            .send()
+            .await
+            .map_err(|e| classify("Verification", e))?;
+
+        Ok(())
+    }
+
+    async fn get_object(&self, client: &Client, key: &str) -> Result<Vec<u8>, UploadError> {
+        let output = client
+            .get_object()
+            .bucket(&self.settings.bucket_name)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| classify("Read", e))?;
+
+        let body = output.body.collect().await.map_err(|e| {
+            UploadError::new(UploadErrorKind::Transient, format!("Read failed: {}", e))
+        })?;
+
+        Ok(body.into_bytes().to_vec())
+    }
+
+    /// Connection test shared with R2: the endpoint, `HeadBucket`, then with
+    /// `round_trip` a probe object written, read back, fetched from its public
+    /// URL and deleted again.
+    pub(crate) async fn diagnose(&self, client: &Client, round_trip: bool) -> Vec<ConnectionCheck> {
+        let bucket = &self.settings.bucket_name;
+        let mut checks = Vec::new();
+
+        if !diagnostics::check_host(&self.endpoint_url(), &mut checks).await {
+            diagnostics::skip(
+                &mut checks,
+                &[
+                    ConnectionCheckKind::Auth,
+                    ConnectionCheckKind::Write,
+                    ConnectionCheckKind::Read,
+                    ConnectionCheckKind::PublicUrl,
+                    ConnectionCheckKind::Delete,
+                ],
+                "The endpoint is unreachable",
+            );
+            return checks;
+        }
+
+        if let Err(e) = self.head_bucket(client).await {
+            checks.push(match e.status {
+                Some(404) => ConnectionCheck::failed(
+                    ConnectionCheckKind::Auth,
+                    format!("Bucket {} does not exist", bucket),
+                ),
+                _ => diagnostics::failed(ConnectionCheckKind::Auth, &e),
+            });
+            diagnostics::skip(
+                &mut checks,
+                &[
+                    ConnectionCheckKind::Write,
+                    ConnectionCheckKind::Read,
+                    ConnectionCheckKind::PublicUrl,
+                    ConnectionCheckKind::Delete,
+                ],
+                "The bucket is not accessible",
+            );
+            return checks;
+        }
+        checks.push(ConnectionCheck::passed(
+            ConnectionCheckKind::Auth,
+            format!("Bucket {} is accessible", bucket),
+        ));
+
+        if !round_trip {
+            let reason = "Round trip not requested";
+            diagnostics::skip(
+                &mut checks,
+                &[ConnectionCheckKind::Write, ConnectionCheckKind::Read],
+                reason,
+            );
+            checks.push(match self.settings.public_domain.trim() {
+                "" => ConnectionCheck::skipped(
+                    ConnectionCheckKind::PublicUrl,
+                    "No public domain set; run the round trip to check that the bucket serves objects",
+                ),
+                domain => diagnostics::check_public_domain(domain).await,
+            });
+            diagnostics::skip(&mut checks, &[ConnectionCheckKind::Delete], reason);
+            return checks;
+        }
+
+        let object = match self
+            .put(client, PROBE, "opti-bridge-probe.txt", Arc::new(|_| {}))
+            .await
+        {
+            Ok(object) => object,
+            Err(e) => {
+                checks.push(diagnostics::failed(ConnectionCheckKind::Write, &e));
+                diagnostics::skip(
+                    &mut checks,
+                    &[
+                        ConnectionCheckKind::Read,
+                        ConnectionCheckKind::PublicUrl,
+                        ConnectionCheckKind::Delete,
+                    ],
+                    "Nothing was written",
+                );
+                return checks;
+            }
+        };
+        let key = object.object_key.clone().unwrap_or_default();
+        checks.push(ConnectionCheck::passed(
+            ConnectionCheckKind::Write,
+            format!("Wrote {} to {}", key, bucket),
+        ));
+
+        checks.push(match self.get_object(client, &key).await {
+            Ok(data) if data == PROBE => {
+                ConnectionCheck::passed(ConnectionCheckKind::Read, format!("Read {} back", key))
+            }
+            Ok(_) => ConnectionCheck::failed(
+                ConnectionCheckKind::Read,
+                format!("{} came back with different content", key),
+            ),
+            Err(e) => diagnostics::failed(ConnectionCheckKind::Read, &e),
+        });
+
+        checks.push(diagnostics::check_public_url(&object.url).await);
+
+        checks.push(match self.delete_object(client, &object).await {
+            Ok(()) => {
+                ConnectionCheck::passed(ConnectionCheckKind::Delete, format!("Deleted {}", key))
+            }
+            Err(e) => diagnostics::failed(ConnectionCheckKind::Delete, &e),
+        });
+
+        checks
+    }
+}
+
+/// Sorts SDK failures into transient ones (timeouts, dropped connections,
+/// throttling and server errors) and ones a retry cannot fix. `action` names
+/// the request in the message.
+fn classify<E>(action: &str, error: SdkError<E, HttpResponse>) -> UploadError
+where
+    E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
+{
+    let message = format!("{} failed: {}", action, DisplayErrorContext(&error));
+
+    match &error {
+        SdkError::ServiceError(context) => {
+            let response = context.raw();
+            let retry_after = response
+                .headers()
+                .get("retry-after")
+                .and_then(parse_retry_after);
+            // HEAD responses have no body, so there may be no code either
+            let detail = match (context.err().code(), context.err().message()) {
+                (Some(code), Some(message)) => format!("{}: {}", code, message),
+                (Some(code), None) => code.to_string(),
+                (None, _) => String::new(),
+            };
+            UploadError::from_status(action, response.status().as_u16(), &detail, retry_after)
+        }
+        SdkError::DispatchFailure(failure) if failure.is_io() || failure.is_timeout() => {
+            UploadError::new(UploadErrorKind::Transient, message)
+        }
+        SdkError::TimeoutError(_) | SdkError::ResponseError(_) => {
+            UploadError::new(UploadErrorKind::Transient, message)
+        }
+        _ => UploadError::other(message),
+    }
+}
+
+#[async_trait]
+impl Uploader for S3Uploader {
+    async fn upload(&self, image_data: &[u8], filename: &str) -> Result<RemoteObject, UploadError> {
+        self.upload_with_progress(image_data, filename, Arc::new(|_| {}))
+            .await
+    }
+
+    async fn upload_with_progress(
+        &self,
+        image_data: &[u8],
+        filename: &str,
+        progress: UploadProgress,
+    ) -> Result<RemoteObject, UploadError> {
+        self.put(&self.client("s3-uploader"), image_data, filename, progress)
+            .await
+    }
+
+    async fn delete(&self, object: &RemoteObject) -> Result<(), UploadError> {
+        self.delete_object(&self.client("s3-uploader"), object)
+            .await
+    }
+
+    async fn verify(&self) -> Result<(), UploadError> {
+        self.head_bucket(&self.client("s3-uploader")).await
+    }
+
+    async fn test_connection(&self, round_trip: bool) -> Vec<ConnectionCheck> {
+        self.diagnose(&self.client("s3-uploader"), round_trip).await
+    }
+
+    fn capabilities(&self) -> UploaderCapabilities {
+        Self::provider_info().capabilities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CheckStatus;
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Method, Response, Server};
+    use std::collections::HashMap;
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+    use std::sync::Mutex;
+
+    /// What the mock bucket answers, and what it was asked.
+    #[derive(Default)]
+    struct MockBucket {
+        head_status: u16,
+        public_status: u16,
+        objects: HashMap<String, Vec<u8>>,
+        /// Method, path and content type of each request.
+        requests: Vec<(Method, String, String)>,
+    }
+
+    /// Serves bucket `images` path style, and its public domain under `/public`.
+    async fn mock_bucket(
+        head_status: u16,
+        public_status: u16,
+    ) -> (SocketAddr, Arc<Mutex<MockBucket>>) {
+        let bucket = Arc::new(Mutex::new(MockBucket {
+            head_status,
+            public_status,
+            ..MockBucket::default()
+        }));
+        let state = bucket.clone();
+
+        let make_service = make_service_fn(move |_| {
+            let state = state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: hyper::Request<Body>| {
+                    let state = state.clone();
+                    async move {
+                        let method = request.method().clone();
+                        let path = request.uri().path().to_string();
+                        let content_type = request
+                            .headers()
+                            .get("content-type")
+                            .and_then(|value| value.to_str().ok())
+                            .unwrap_or_default()
+                            .to_string();
+                        let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+
+                        let mut bucket = state.lock().unwrap();
+                        bucket
+                            .requests
+                            .push((method.clone(), path.clone(), content_type));
+                        let key = path
+                            .strip_prefix("/images/")
+                            .filter(|key| !key.is_empty())
+                            .map(str::to_string);
+                        let (status, body) = match (method, key) {
+                            (Method::HEAD, None) if path.trim_end_matches('/') == "/images" => {
+                                (bucket.head_status, Vec::new())
+                            }
+                            (Method::PUT, Some(key)) => {
+                                bucket.objects.insert(key, body.to_vec());
+                                (200, Vec::new())
+                            }
+                            (Method::GET, Some(key)) => match bucket.objects.get(&key) {
+                                Some(object) => (200, object.clone()),
+                                None => (404, Vec::new()),
+                            },
+                            (Method::DELETE, Some(key)) => {
+                                bucket.objects.remove(&key);
+                                (204, Vec::new())
+                            }
+                            (Method::GET, None) if path.starts_with("/public/") => {
+                                (bucket.public_status, Vec::new())
+                            }
+                            _ => (400, Vec::new()),
+                        };
+                        Ok::<_, Infallible>(
+                            Response::builder()
+                                .status(status)
+                                .body(Body::from(body))
+                                .unwrap(),
+                        )
+                    }
+                }))
+            }
+        });
+
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        (addr, bucket)
+    }
+
+    async fn diagnose(
+        addr: SocketAddr,
+        round_trip: bool,
+    ) -> Vec<(ConnectionCheckKind, CheckStatus)> {
+        let uploader = S3Uploader::new(S3Settings {
+            access_key_id: "AKIA".to_string(),
+            secret_access_key: "secret".to_string(),
+            bucket_name: "images".to_string(),
+            endpoint: format!("http://{}", addr),
+            force_path_style: true,
+            public_domain: format!("http://{}/public", addr),
+            ..S3Settings::default()
+        });
+
+        uploader
+            .diagnose(&uploader.client("s3-test"), round_trip)
+            .await
+            .into_iter()
+            .map(|check| (check.kind, check.status))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn rejected_credentials_skip_the_later_steps() {
+        let (addr, bucket) = mock_bucket(403, 200).await;
+
+        assert_eq!(
+            diagnose(addr, true).await,
+            [
+                (ConnectionCheckKind::Dns, CheckStatus::Passed),
+                (ConnectionCheckKind::Tls, CheckStatus::Skipped),
+                (ConnectionCheckKind::Auth, CheckStatus::Failed),
+                (ConnectionCheckKind::Write, CheckStatus::Skipped),
+                (ConnectionCheckKind::Read, CheckStatus::Skipped),
+                (ConnectionCheckKind::PublicUrl, CheckStatus::Skipped),
+                (ConnectionCheckKind::Delete, CheckStatus::Skipped),
+            ]
+        );
+        let bucket = bucket.lock().unwrap();
+        assert!(bucket
+            .requests
+            .iter()
+            .all(|(method, _, _)| method == Method::HEAD));
+    }
+
+    #[tokio::test]
+    async fn round_trip_writes_reads_fetches_and_deletes_a_probe() {
+        let (addr, bucket) = mock_bucket(200, 200).await;
+
+        let checks = diagnose(addr, true).await;
+        assert!(
+            checks
+                .iter()
+                .all(|(kind, status)| *status == CheckStatus::Passed
+                    || (*kind == ConnectionCheckKind::Tls && *status == CheckStatus::Skipped)),
+            "{:?}",
+            checks
+        );
+        assert_eq!(checks.len(), 7);
+
+        let bucket = bucket.lock().unwrap();
+        assert!(bucket.objects.is_empty());
+        let (_, path, content_type) = bucket
+            .requests
+            .iter()
+            .find(|(method, _, _)| method == Method::PUT)
+            .unwrap();
+        assert!(path.ends_with(".txt"));
+        assert_eq!(content_type, "text/plain; charset=utf-8");
+    }
+
+    #[tokio::test]
+    async fn public_domain_not_serving_the_probe_fails_that_step_only() {
+        let (addr, bucket) = mock_bucket(200, 403).await;
+
+        let checks = diagnose(addr, true).await;
+        let status = |kind| checks.iter().find(|(k, _)| *k == kind).unwrap().1;
+        assert_eq!(status(ConnectionCheckKind::Write), CheckStatus::Passed);
+        assert_eq!(status(ConnectionCheckKind::Read), CheckStatus::Passed);
+        assert_eq!(status(ConnectionCheckKind::PublicUrl), CheckStatus::Failed);
+        // the probe is cleaned up all the same
+        assert_eq!(status(ConnectionCheckKind::Delete), CheckStatus::Passed);
+        assert!(bucket.lock().unwrap().objects.is_empty());
+    }
+
+    fn uploader(endpoint: &str, force_path_style: bool, public_domain: &str) -> S3Uploader {
+        S3Uploader::new(S3Settings {
+            bucket_name: "images".to_string(),
+            region: "eu-west-1".to_string(),
+            endpoint: endpoint.to_string(),
+            force_path_style,
+            public_domain: public_domain.to_string(),
+            ..S3Settings::default()
+        })
+    }
+
+    /// Builds the URL for a key and checks the key comes back out of it.
+    fn assert_round_trip(uploader: &S3Uploader, expected_url: &str) {
+        let url = uploader.public_url("a/b.webp");
+        assert_eq!(url, expected_url);
+        assert_eq!(uploader.key_from_url(&url).as_deref(), Some("a/b.webp"));
+    }
+
+    #[test]
+    fn aws_virtual_hosted() {
+        assert_round_trip(
+            &uploader("", false, ""),
+            "https://images.s3.eu-west-1.amazonaws.com/a/b.webp",
+        );
+    }
+
+    #[test]
+    fn aws_path_style() {
+        assert_round_trip(
+            &uploader("", true, ""),
+            "https://s3.eu-west-1.amazonaws.com/images/a/b.webp",
+        );
+    }
+
+    #[test]
+    fn aws_default_region() {
+        let uploader = S3Uploader::new(S3Settings {
+            bucket_name: "images".to_string(),
+            ..S3Settings::default()
+        });
+        assert_round_trip(
+            &uploader,
+            "https://images.s3.us-east-1.amazonaws.com/a/b.webp",
+        );
+    }
+
+    #[test]
+    fn custom_endpoint_virtual_hosted() {
+        assert_round_trip(
+            &uploader("https://storage.example.com/", false, ""),
+            "https://images.storage.example.com/a/b.webp",
+        );
+    }
+
+    #[test]
+    fn custom_endpoint_path_style() {
+        assert_round_trip(
+            &uploader("http://localhost:9000", true, ""),
+            "http://localhost:9000/images/a/b.webp",
+        );
+    }
+
+    #[test]
+    fn custom_endpoint_without_scheme() {
+        assert_round_trip(
+            &uploader("storage.example.com", false, ""),
+            "https://images.storage.example.com/a/b.webp",
+        );
+    }
+
+    #[test]
+    fn public_domain_wins_over_addressing() {
+        for force_path_style in [false, true] {
+            assert_round_trip(
+                &uploader(
+                    "http://localhost:9000",
+                    force_path_style,
+                    "https://cdn.example.com/",
+                ),
+                "https://cdn.example.com/a/b.webp",
+            );
+        }
+    }
+
+    #[test]
+    fn key_ignores_query_and_fragment() {
+        let uploader = uploader("", false, "https://cdn.example.com");
+        assert_eq!(
+            uploader
+                .key_from_url("https://cdn.example.com/a/b.webp?v=2#top")
+                .as_deref(),
+            Some("a/b.webp")
+        );
+    }
+
+    #[test]
+    fn key_from_endpoint_url_when_a_public_domain_is_set() {
+        let uploader = uploader("", false, "https://cdn.example.com");
+        assert_eq!(
+            uploader
+                .key_from_url("https://images.s3.eu-west-1.amazonaws.com/a/b.webp")
+                .as_deref(),
+            Some("a/b.webp")
+        );
+    }
+
+    #[test]
+    fn key_from_foreign_url_is_refused() {
+        let uploader = uploader("", false, "https://cdn.example.com");
+        for url in [
+            "https://old-cdn.example.org/x/1234.webp",
+            "https://other.s3.eu-west-1.amazonaws.com/1234.webp",
+            "https://cdn.example.com.evil.org/1234.webp",
+            "https://cdn.example.com/",
+        ] {
+            assert_eq!(uploader.key_from_url(url), None, "{}", url);
+        }
+    }
+}
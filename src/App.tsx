@@ -3,12 +3,34 @@ import { Toaster } from "@/components/common/Toaster";
 import UploadView from "@/pages/UploadView";
 import HistoryView from "@/pages/HistoryView";
 import SettingsView from "@/pages/SettingsView";
+import { useAppStore, AppTab } from "@/state/appStore";
+import { useToast } from "@/hooks/useToast";
+import { AppError, errorToast } from "@/lib/errors";
+import { safeListen } from "@/lib/tauri";
 import { Upload, History, Settings, Sparkles } from "lucide-react";
+import { useEffect } from "react";
 
 function App() {
+  const { activeTab, setActiveTab } = useAppStore();
+  const { toast } = useToast();
+
+  // Failures of work no command is waiting on, such as the upload server
+  useEffect(() => {
+    const unlisten = safeListen<AppError>("background-error", (error) => {
+      toast(errorToast("Something went wrong", error));
+    });
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, []);
+
   return (
     <div className="h-screen w-screen bg-background text-foreground flex overflow-hidden">
-      <Tabs defaultValue="upload" className="flex-1 flex overflow-hidden">
+      <Tabs
+        value={activeTab}
+        onValueChange={(value) => setActiveTab(value as AppTab)}
+        className="flex-1 flex overflow-hidden"
+      >
         {/* Modern Sidebar */}
         <div className="w-72 border-r border-border/50 bg-gradient-to-b from-background to-muted/20 flex flex-col shadow-sm">
           {/* Header */}
@@ -0,0 +1,157 @@
+import { useEffect, useState } from "react";
+import { safeInvoke, safeOpen, safeSave } from "@/lib/tauri";
+import { Button } from "@/components/common/Button";
+import { Input } from "@/components/common/Input";
+import { Label } from "@/components/common/Label";
+import { useToast } from "@/hooks/useToast";
+import { errorToast } from "@/lib/errors";
+import { Download, Loader2, Share2, Terminal, Upload } from "lucide-react";
+
+interface EnvOverride {
+  variable: string;
+  /** Config key, or `<account id>/<field>` */
+  field: string;
+}
+
+interface ConfigTransferProps {
+  /** Called after an import replaced the settings */
+  onImport: () => void;
+}
+
+const BUNDLE_FILTERS = [{ name: "OptiBridge settings", extensions: ["json"] }];
+
+/**
+ * Export and import of settings bundles, and the environment variables currently overriding settings
+ */
+export function ConfigTransfer({ onImport }: ConfigTransferProps) {
+  const { toast } = useToast();
+  const [overrides, setOverrides] = useState<EnvOverride[]>([]);
+  const [includeSecrets, setIncludeSecrets] = useState(false);
+  const [passphrase, setPassphrase] = useState("");
+  const [busy, setBusy] = useState<"export" | "import" | null>(null);
+
+  useEffect(() => {
+    safeInvoke<EnvOverride[]>("list_env_overrides")
+      .then(setOverrides)
+      .catch((error) => console.error("Failed to load environment overrides:", error));
+  }, []);
+
+  const handleExport = async () => {
+    const path = await safeSave({ defaultPath: "opti-bridge-settings.json", filters: BUNDLE_FILTERS });
+    if (!path) return;
+
+    setBusy("export");
+    try {
+      await safeInvoke("export_config", { path, passphrase: includeSecrets ? passphrase : null });
+      toast({
+        title: "Settings exported",
+        description: includeSecrets ? "Secrets are encrypted with the passphrase" : "Secrets were left out",
+      });
+    } catch (error) {
+      toast(errorToast("Export failed", error));
+    } finally {
+      setBusy(null);
+    }
+  };
+
+  const handleImport = async () => {
+    const selected = await safeOpen({ multiple: false, filters: BUNDLE_FILTERS });
+    const path = Array.isArray(selected) ? selected[0] : selected;
+    if (!path) return;
+
+    setBusy("import");
+    try {
+      const imported = await safeInvoke<number>("import_config", { path, passphrase: passphrase || null });
+      toast({
+        title: "Settings imported",
+        description: imported > 0 ? `Including ${imported} secrets` : "Secrets already stored here were kept",
+      });
+      onImport();
+    } catch (error) {
+      toast(errorToast("Import failed", error));
+    } finally {
+      setBusy(null);
+    }
+  };
+
+  return (
+    <div className="bg-card border border-border/50 rounded-xl p-6 shadow-sm space-y-6">
+      <div className="flex items-center gap-3">
+        <div className="p-2 rounded-lg bg-primary/10">
+          <Share2 className="h-5 w-5 text-primary" />
+        </div>
+        <div>
+          <h3 className="text-lg font-semibold">Share Settings</h3>
+          <p className="text-sm text-muted-foreground">
+            Export the saved settings to a file for teammates, or import one
+          </p>
+        </div>
+      </div>
+
+      <div className="grid md:grid-cols-2 gap-6">
+        <div className="space-y-2">
+          <Label htmlFor="bundle_passphrase">Bundle Passphrase</Label>
+          <Input
+            id="bundle_passphrase"
+            type="password"
+            value={passphrase}
+            onChange={(e) => setPassphrase(e.target.value)}
+          />
+          <p className="text-xs text-muted-foreground">
+            Encrypts exported secrets; needed to import a bundle that includes them
+          </p>
+        </div>
+
+        <div className="space-y-3">
+          <div className="flex items-center gap-3">
+            <input
+              id="bundle_include_secrets"
+              type="checkbox"
+              checked={includeSecrets}
+              onChange={(e) => setIncludeSecrets(e.target.checked)}
+              className="w-4 h-4 text-primary rounded"
+            />
+            <Label htmlFor="bundle_include_secrets" className="cursor-pointer">
+              Include secrets in the export
+            </Label>
+          </div>
+          <div className="flex gap-2">
+            <Button
+              variant="outline"
+              onClick={handleExport}
+              disabled={busy !== null || (includeSecrets && !passphrase)}
+            >
+              {busy === "export" ? <Loader2 className="h-4 w-4 mr-2 animate-spin" /> : <Download className="h-4 w-4 mr-2" />}
+              Export
+            </Button>
+            <Button variant="outline" onClick={handleImport} disabled={busy !== null}>
+              {busy === "import" ? <Loader2 className="h-4 w-4 mr-2 animate-spin" /> : <Upload className="h-4 w-4 mr-2" />}
+              Import
+            </Button>
+          </div>
+          <p className="text-xs text-muted-foreground">Importing replaces the current settings</p>
+        </div>
+      </div>
+
+      {overrides.length > 0 && (
+        <div className="space-y-2 p-4 rounded-lg border border-border/50">
+          <div className="flex items-center gap-2 text-sm font-medium">
+            <Terminal className="h-4 w-4" />
+            Set by environment variables
+          </div>
+          <p className="text-xs text-muted-foreground">
+            These settings are taken from the environment instead of the saved values, and are never saved or exported
+          </p>
+          <ul className="space-y-1 text-sm">
+            {overrides.map((override) => (
+              <li key={override.variable} className="flex gap-2">
+                <code className="text-xs">{override.variable}</code>
+                <span className="text-muted-foreground">{override.field}</span>
+              </li>
+            ))}
+          </ul>
+        </div>
+      )}
+    </div>
+  );
+}
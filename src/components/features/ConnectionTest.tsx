@@ -0,0 +1,87 @@
+import { useState } from "react";
+import { safeInvoke } from "@/lib/tauri";
+import { Button } from "@/components/common/Button";
+import { useToast } from "@/hooks/useToast";
+import { errorToast } from "@/lib/errors";
+import { CheckCircle2, CircleSlash, Loader2, PlugZap, XCircle } from "lucide-react";
+
+interface ConnectionCheck {
+  kind: "dns" | "tls" | "auth" | "write" | "read" | "delete" | "public_url";
+  status: "passed" | "failed" | "skipped";
+  detail: string;
+}
+
+interface ConnectionReport {
+  provider: string;
+  ok: boolean;
+  checks: ConnectionCheck[];
+}
+
+interface ConnectionTestProps {
+  /** Account id, or a provider id for that provider's own settings */
+  account: string;
+  /** Offer the write/read/delete probe; Cloudinary is only tested read-only */
+  roundTrip?: boolean;
+}
+
+const CHECK_LABELS: Record<ConnectionCheck["kind"], string> = {
+  dns: "DNS",
+  tls: "TLS",
+  auth: "Credentials",
+  write: "Write",
+  read: "Read",
+  delete: "Delete",
+  public_url: "Public URL",
+};
+
+/**
+ * Runs `test_provider` against the saved settings and lists each step's outcome
+ */
+export function ConnectionTest({ account, roundTrip = false }: ConnectionTestProps) {
+  const { toast } = useToast();
+  const [report, setReport] = useState<ConnectionReport | null>(null);
+  const [isTesting, setIsTesting] = useState(false);
+
+  const runTest = async (withRoundTrip: boolean) => {
+    setIsTesting(true);
+    try {
+      setReport(await safeInvoke<ConnectionReport>("test_provider", { account, roundTrip: withRoundTrip }));
+    } catch (error) {
+      setReport(null);
+      toast(errorToast("Test failed", error));
+    } finally {
+      setIsTesting(false);
+    }
+  };
+
+  return (
+    <div className="space-y-3 md:col-span-2">
+      <div className="flex items-center gap-2">
+        <Button variant="outline" size="sm" onClick={() => runTest(false)} disabled={isTesting}>
+          {isTesting ? <Loader2 className="h-4 w-4 mr-2 animate-spin" /> : <PlugZap className="h-4 w-4 mr-2" />}
+          Test Connection
+        </Button>
+        {roundTrip && (
+          <Button variant="outline" size="sm" onClick={() => runTest(true)} disabled={isTesting}>
+            Test Upload Round Trip
+          </Button>
+        )}
+        <p className="text-xs text-muted-foreground">Uses the saved settings</p>
+      </div>
+
+      {report && (
+        <ul className="space-y-1 text-sm">
+          {report.checks.map((check) => (
+            <li key={check.kind} className="flex items-start gap-2">
+              {check.status === "passed" && <CheckCircle2 className="h-4 w-4 mt-0.5 shrink-0 text-green-600" />}
+              {check.status === "failed" && <XCircle className="h-4 w-4 mt-0.5 shrink-0 text-destructive" />}
+              {check.status === "skipped" && <CircleSlash className="h-4 w-4 mt-0.5 shrink-0 text-muted-foreground" />}
+              <span className="font-medium w-24 shrink-0">{CHECK_LABELS[check.kind]}</span>
+              <span className="text-muted-foreground break-all">{check.detail}</span>
+            </li>
+          ))}
+        </ul>
+      )}
+    </div>
+  );
+}
@@ -0,0 +1,130 @@
+import { useEffect, useState } from "react";
+import { safeInvoke, safeListen } from "@/lib/tauri";
+import { Button } from "@/components/common/Button";
+import { useToast } from "@/hooks/useToast";
+import { AppError, errorToast, settingsTarget } from "@/lib/errors";
+import { useAppStore } from "@/state/appStore";
+import { CloudOff, Loader2, RefreshCw, Trash2 } from "lucide-react";
+
+interface PendingUpload {
+  id: string;
+  provider: string;
+  original_name: string;
+  size_bytes: number;
+  created_at: number;
+  attempts: number;
+  last_attempt_at: number | null;
+  last_error: AppError | null;
+  profile_id: string | null;
+}
+
+interface PendingUploadsProps {
+  /** Called whenever the queue changes, since retried items land in history */
+  onChange: () => void;
+}
+
+/**
+ * Uploads kept by the offline queue, refreshed on `pending-uploads-changed` events
+ */
+export function PendingUploads({ onChange }: PendingUploadsProps) {
+  const { toast } = useToast();
+  const { openSettings } = useAppStore();
+  const [items, setItems] = useState<PendingUpload[]>([]);
+  const [isRetrying, setIsRetrying] = useState(false);
+
+  const loadPending = async () => {
+    try {
+      setItems(await safeInvoke<PendingUpload[]>("list_pending_uploads"));
+    } catch (error) {
+      console.error("Failed to load pending uploads:", error);
+    }
+  };
+
+  useEffect(() => {
+    loadPending();
+    const unlisten = safeListen<null>("pending-uploads-changed", () => {
+      loadPending();
+      onChange();
+    });
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, []);
+
+  const handleRetry = async () => {
+    setIsRetrying(true);
+    try {
+      const result = await safeInvoke<{ uploaded: number; remaining: number }>("retry_pending_uploads");
+      toast({
+        title: result.remaining === 0 ? "All pending uploads done" : "Retry finished",
+        description: `${result.uploaded} uploaded, ${result.remaining} still pending`,
+      });
+    } catch (error) {
+      toast(errorToast("Retry failed", error));
+    } finally {
+      setIsRetrying(false);
+    }
+  };
+
+  const handleDiscard = async (id: string) => {
+    try {
+      await safeInvoke("discard_pending_upload", { id });
+    } catch (error) {
+      toast(errorToast("Discard failed", error));
+    }
+  };
+
+  if (items.length === 0) return null;
+
+  return (
+    <div className="bg-card border border-border/50 rounded-xl shadow-sm p-4 mb-6 space-y-3">
+      <div className="flex items-center justify-between">
+        <div className="flex items-center gap-2">
+          <CloudOff className="h-5 w-5 text-muted-foreground" />
+          <div>
+            <h3 className="font-semibold">Pending uploads</h3>
+            <p className="text-xs text-muted-foreground">
+              Network failures are retried automatically; retry the rest once the problem is fixed
+            </p>
+          </div>
+        </div>
+        <Button variant="outline" size="sm" onClick={handleRetry} disabled={isRetrying}>
+          {isRetrying ? <Loader2 className="mr-2 h-4 w-4 animate-spin" /> : <RefreshCw className="mr-2 h-4 w-4" />}
+          Retry All
+        </Button>
+      </div>
+
+      <div className="divide-y divide-border/50">
+        {items.map((item) => {
+          const fixTarget = settingsTarget(item.last_error);
+
+          return (
+            <div key={item.id} className="py-2 flex items-center gap-3">
+              <div className="flex-1 min-w-0">
+                <p className="font-medium truncate">{item.original_name}</p>
+                <p className="text-xs text-muted-foreground">
+                  {item.provider} · {(item.size_bytes / 1024).toFixed(1)} KB · queued{" "}
+                  {new Date(item.created_at * 1000).toLocaleString()}
+                  {item.attempts > 0 && ` · ${item.attempts} ${item.attempts === 1 ? "retry" : "retries"}`}
+                </p>
+                {item.last_error && (
+                  <p className="text-xs text-destructive truncate">
+                    {item.last_error.message}
+                    {fixTarget && (
+                      <button className="ml-2 underline" onClick={() => openSettings(fixTarget)}>
+                        Open Settings
+                      </button>
+                    )}
+                  </p>
+                )}
+              </div>
+              <Button size="sm" variant="outline" className="h-8 w-8 p-0" onClick={() => handleDiscard(item.id)}>
+                <Trash2 className="h-4 w-4" />
+              </Button>
+            </div>
+          );
+        })}
+      </div>
+    </div>
+  );
+}
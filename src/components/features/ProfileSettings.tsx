@@ -0,0 +1,302 @@
+import { useEffect, useState, FocusEvent } from "react";
+import { safeInvoke } from "@/lib/tauri";
+import { Button } from "@/components/common/Button";
+import { Input } from "@/components/common/Input";
+import { Label } from "@/components/common/Label";
+import { ConnectionTest } from "@/components/features/ConnectionTest";
+import { Plus, Trash2, Users } from "lucide-react";
+
+export interface ProviderAccount {
+  id: string;
+  name: string;
+  provider: string;
+  fields: Record<string, string | boolean>;
+}
+
+export interface UploadProfile {
+  id: string;
+  name: string;
+  account_id: string;
+  max_width: number | null;
+  output_format: "webp" | "png" | "jpeg" | "avif" | "original" | null;
+  quality: number | null;
+}
+
+interface ProviderInfo {
+  id: string;
+  name: string;
+  fields: { key: string; label: string; kind: "text" | "secret" | "toggle"; required: boolean }[];
+}
+
+interface ProfileSettingsProps {
+  accounts: ProviderAccount[];
+  profiles: UploadProfile[];
+  /** Empty for the default provider's own settings */
+  defaultProfile: string;
+  defaultProvider: string;
+  onAccountsChange: (accounts: ProviderAccount[]) => void;
+  onProfilesChange: (profiles: UploadProfile[]) => void;
+  onDefaultProfileChange: (id: string) => void;
+  onSecretFocus: (e: FocusEvent<HTMLInputElement>) => void;
+}
+
+const selectClassName = "flex h-10 w-full rounded-md border border-input bg-background px-3 py-2 text-sm";
+
+/**
+ * Named accounts per provider and the upload profiles built on them. Inputs
+ * are identified as `<account or profile id>/<field key>`, matching the keys
+ * errors point at.
+ */
+export function ProfileSettings({
+  accounts,
+  profiles,
+  defaultProfile,
+  defaultProvider,
+  onAccountsChange,
+  onProfilesChange,
+  onDefaultProfileChange,
+  onSecretFocus,
+}: ProfileSettingsProps) {
+  const [providers, setProviders] = useState<ProviderInfo[]>([]);
+
+  useEffect(() => {
+    safeInvoke<ProviderInfo[]>("list_providers")
+      .then(setProviders)
+      .catch((error) => console.error("Failed to load providers:", error));
+  }, []);
+
+  // A provider id stands for that provider's own settings
+  const accountOptions = [
+    ...providers.map((provider) => ({ id: provider.id, name: `${provider.name} (provider settings)` })),
+    ...accounts.map((account) => ({ id: account.id, name: account.name })),
+  ];
+
+  const updateAccount = (id: string, changes: Partial<ProviderAccount>) => {
+    onAccountsChange(accounts.map((account) => (account.id === id ? { ...account, ...changes } : account)));
+  };
+
+  const addAccount = () => {
+    const provider = providers[0]?.id ?? "cloudinary";
+    onAccountsChange([...accounts, { id: `account-${Date.now()}`, name: "New account", provider, fields: {} }]);
+  };
+
+  // Profiles using the account fall back to its provider's own settings
+  const removeAccount = (removed: ProviderAccount) => {
+    onAccountsChange(accounts.filter((account) => account.id !== removed.id));
+    onProfilesChange(
+      profiles.map((profile) =>
+        profile.account_id === removed.id ? { ...profile, account_id: removed.provider } : profile
+      )
+    );
+    if (defaultProfile === removed.id) onDefaultProfileChange("");
+  };
+
+  const updateProfile = (id: string, changes: Partial<UploadProfile>) => {
+    onProfilesChange(profiles.map((profile) => (profile.id === id ? { ...profile, ...changes } : profile)));
+  };
+
+  const addProfile = () => {
+    onProfilesChange([
+      ...profiles,
+      {
+        id: `profile-${Date.now()}`,
+        name: "New profile",
+        account_id: accounts[0]?.id ?? defaultProvider,
+        max_width: null,
+        output_format: null,
+        quality: null,
+      },
+    ]);
+  };
+
+  const removeProfile = (id: string) => {
+    onProfilesChange(profiles.filter((profile) => profile.id !== id));
+    if (defaultProfile === id) onDefaultProfileChange("");
+  };
+
+  const parseOptional = (value: string) => (value === "" ? null : parseInt(value));
+
+  return (
+    <div className="bg-card border border-border/50 rounded-xl p-6 shadow-sm">
+      <div className="flex items-center gap-3 mb-6">
+        <div className="p-2 rounded-lg bg-primary/10">
+          <Users className="h-5 w-5 text-primary" />
+        </div>
+        <div>
+          <h3 className="text-lg font-semibold">Accounts & Profiles</h3>
+          <p className="text-sm text-muted-foreground">
+            Extra accounts per provider, and profiles pairing an account with processing settings
+          </p>
+        </div>
+      </div>
+
+      <div className="space-y-6">
+        <div className="space-y-2">
+          <Label htmlFor="settings_default_profile">Default Profile</Label>
+          <select
+            id="settings_default_profile"
+            value={defaultProfile || defaultProvider}
+            onChange={(e) => onDefaultProfileChange(e.target.value)}
+            className={selectClassName}
+          >
+            {[...accountOptions, ...profiles].map((option) => (
+              <option key={option.id} value={option.id}>
+                {option.name}
+              </option>
+            ))}
+          </select>
+          <p className="text-xs text-muted-foreground">Used when an upload does not pick one, including the upload server</p>
+        </div>
+
+        <div className="space-y-3">
+          <div className="flex items-center justify-between">
+            <Label>Accounts</Label>
+            <Button variant="outline" size="sm" onClick={addAccount}>
+              <Plus className="h-4 w-4 mr-2" />
+              Add Account
+            </Button>
+          </div>
+          {accounts.map((account) => {
+            const provider = providers.find((info) => info.id === account.provider);
+            return (
+              <div key={account.id} className="space-y-3 p-4 rounded-lg border border-border/50">
+                <div className="flex gap-2">
+                  <Input
+                    value={account.name}
+                    onChange={(e) => updateAccount(account.id, { name: e.target.value })}
+                    placeholder="Name"
+                    className="flex-1"
+                  />
+                  <select
+                    value={account.provider}
+                    onChange={(e) => updateAccount(account.id, { provider: e.target.value, fields: {} })}
+                    className={`${selectClassName} w-48`}
+                  >
+                    {providers.map((info) => (
+                      <option key={info.id} value={info.id}>
+                        {info.name}
+                      </option>
+                    ))}
+                  </select>
+                  <Button variant="outline" size="icon" onClick={() => removeAccount(account)} className="shrink-0">
+                    <Trash2 className="h-4 w-4" />
+                  </Button>
+                </div>
+                <div className="grid md:grid-cols-2 gap-3">
+                  {provider?.fields.map((field) => {
+                    const id = `${account.id}/${field.key}`;
+                    const value = account.fields[field.key];
+                    const setValue = (next: string | boolean) =>
+                      updateAccount(account.id, { fields: { ...account.fields, [field.key]: next } });
+
+                    if (field.kind === "toggle") {
+                      return (
+                        <div key={field.key} className="flex items-center gap-3">
+                          <input
+                            id={id}
+                            type="checkbox"
+                            checked={value === true}
+                            onChange={(e) => setValue(e.target.checked)}
+                            className="w-4 h-4 text-primary rounded"
+                          />
+                          <Label htmlFor={id} className="cursor-pointer">
+                            {field.label}
+                          </Label>
+                        </div>
+                      );
+                    }
+
+                    return (
+                      <div key={field.key} className="space-y-1">
+                        <Label htmlFor={id}>{field.label}</Label>
+                        <Input
+                          id={id}
+                          type={field.kind === "secret" ? "password" : "text"}
+                          value={typeof value === "string" ? value : ""}
+                          onChange={(e) => setValue(e.target.value)}
+                          onFocus={field.kind === "secret" ? onSecretFocus : undefined}
+                        />
+                      </div>
+                    );
+                  })}
+                  <ConnectionTest account={account.id} roundTrip={account.provider !== "cloudinary"} />
+                </div>
+              </div>
+            );
+          })}
+        </div>
+
+        <div className="space-y-3">
+          <div className="flex items-center justify-between">
+            <Label>Profiles</Label>
+            <Button variant="outline" size="sm" onClick={addProfile}>
+              <Plus className="h-4 w-4 mr-2" />
+              Add Profile
+            </Button>
+          </div>
+          {profiles.map((profile) => (
+            <div key={profile.id} className="flex flex-wrap gap-2">
+              <Input
+                value={profile.name}
+                onChange={(e) => updateProfile(profile.id, { name: e.target.value })}
+                placeholder="Name"
+                className="w-40"
+              />
+              <select
+                value={profile.account_id}
+                onChange={(e) => updateProfile(profile.id, { account_id: e.target.value })}
+                className={`${selectClassName} w-56`}
+              >
+                {accountOptions.map((option) => (
+                  <option key={option.id} value={option.id}>
+                    {option.name}
+                  </option>
+                ))}
+              </select>
+              <Input
+                id={`${profile.id}/max_width`}
+                type="number"
+                value={profile.max_width ?? ""}
+                onChange={(e) => updateProfile(profile.id, { max_width: parseOptional(e.target.value) })}
+                placeholder="Max width"
+                min="100"
+                max="8192"
+                className="w-32"
+              />
+              <select
+                value={profile.output_format ?? ""}
+                onChange={(e) =>
+                  updateProfile(profile.id, {
+                    output_format: (e.target.value || null) as UploadProfile["output_format"],
+                  })
+                }
+                className={`${selectClassName} w-40`}
+              >
+                <option value="">Global format</option>
+                <option value="webp">WebP</option>
+                <option value="png">Optimized PNG</option>
+                <option value="jpeg">Progressive JPEG</option>
+                <option value="avif">AVIF</option>
+                <option value="original">Original</option>
+              </select>
+              <Input
+                id={`${profile.id}/quality`}
+                type="number"
+                value={profile.quality ?? ""}
+                onChange={(e) => updateProfile(profile.id, { quality: parseOptional(e.target.value) })}
+                placeholder="Quality"
+                min="1"
+                max="100"
+                className="w-28"
+              />
+              <Button variant="outline" size="icon" onClick={() => removeProfile(profile.id)} className="shrink-0">
+                <Trash2 className="h-4 w-4" />
+              </Button>
+            </div>
+          ))}
+          <p className="text-xs text-muted-foreground">Empty settings use the global processing settings</p>
+        </div>
+      </div>
+    </div>
+  );
+}
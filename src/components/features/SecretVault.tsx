@@ -0,0 +1,93 @@
+import { useEffect, useState } from "react";
+import { safeInvoke } from "@/lib/tauri";
+import { Button } from "@/components/common/Button";
+import { Input } from "@/components/common/Input";
+import { Label } from "@/components/common/Label";
+import { useToast } from "@/hooks/useToast";
+import { errorToast } from "@/lib/errors";
+import { Loader2, Lock } from "lucide-react";
+
+interface SecretStoreStatus {
+  backend: "keyring" | "vault";
+  locked: boolean;
+  vault_exists: boolean;
+}
+
+interface SecretVaultProps {
+  /** Called after unlocking, since secrets can be read and saved from then on */
+  onUnlock: () => void;
+}
+
+/**
+ * Passphrase prompt for the encrypted secrets file used when there is no system keyring
+ */
+export function SecretVault({ onUnlock }: SecretVaultProps) {
+  const { toast } = useToast();
+  const [status, setStatus] = useState<SecretStoreStatus | null>(null);
+  const [passphrase, setPassphrase] = useState("");
+  const [isUnlocking, setIsUnlocking] = useState(false);
+
+  const loadStatus = async () => {
+    try {
+      setStatus(await safeInvoke<SecretStoreStatus>("get_secret_store_status"));
+    } catch (error) {
+      console.error("Failed to load secret store status:", error);
+    }
+  };
+
+  useEffect(() => {
+    loadStatus();
+  }, []);
+
+  const handleUnlock = async () => {
+    setIsUnlocking(true);
+    try {
+      await safeInvoke("unlock_vault", { passphrase });
+      setPassphrase("");
+      await loadStatus();
+      onUnlock();
+    } catch (error) {
+      toast(errorToast("Unlock failed", error));
+    } finally {
+      setIsUnlocking(false);
+    }
+  };
+
+  if (!status || status.backend !== "vault" || !status.locked) return null;
+
+  return (
+    <div className="bg-card border border-border/50 rounded-xl shadow-sm p-6 space-y-4">
+      <div className="flex items-center gap-3">
+        <div className="p-2 rounded-lg bg-primary/10">
+          <Lock className="h-5 w-5 text-primary" />
+        </div>
+        <div>
+          <h3 className="text-lg font-semibold">{status.vault_exists ? "Unlock secrets" : "Protect secrets"}</h3>
+          <p className="text-xs text-muted-foreground">
+            No system keyring was found, so API secrets are kept in an encrypted file.{" "}
+            {status.vault_exists
+              ? "Enter its passphrase to use and change them."
+              : "Choose a passphrase for it before saving secrets."}
+          </p>
+        </div>
+      </div>
+
+      <div className="flex items-end gap-2">
+        <div className="flex-1 space-y-2">
+          <Label htmlFor="vault_passphrase">Passphrase</Label>
+          <Input
+            id="vault_passphrase"
+            type="password"
+            value={passphrase}
+            onChange={(e) => setPassphrase(e.target.value)}
+            onKeyDown={(e) => e.key === "Enter" && passphrase && handleUnlock()}
+          />
+        </div>
+        <Button onClick={handleUnlock} disabled={!passphrase || isUnlocking}>
+          {isUnlocking && <Loader2 className="mr-2 h-4 w-4 animate-spin" />}
+          {status.vault_exists ? "Unlock" : "Set Passphrase"}
+        </Button>
+      </div>
+    </div>
+  );
+}
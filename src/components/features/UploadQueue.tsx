@@ -0,0 +1,148 @@
+import { safeInvoke } from "@/lib/tauri";
+import { Button } from "@/components/common/Button";
+import { useToast } from "@/hooks/useToast";
+import { AppError, errorToast, settingsTarget } from "@/lib/errors";
+import { useAppStore } from "@/state/appStore";
+import { Check, Copy, Loader2, X, AlertCircle, Clock } from "lucide-react";
+
+export type UploadStatus = "queued" | "processing" | "uploading" | "retrying" | "done" | "failed" | "cancelled";
+
+export interface UploadEvent {
+  batch_id: string;
+  item_id: string;
+  status: UploadStatus;
+  bytes_sent: number;
+  bytes_total: number;
+  attempt: number;
+  url: string | null;
+  error: AppError | null;
+}
+
+export interface UploadBatch {
+  batch_id: string;
+  items: { id: string; name: string }[];
+}
+
+interface UploadQueueProps {
+  batch: UploadBatch;
+  events: Record<string, UploadEvent>;
+  onClose: () => void;
+}
+
+const FINISHED: UploadStatus[] = ["done", "failed", "cancelled"];
+
+/**
+ * Progress of a batch queued with `enqueue_uploads`, driven by `upload-progress` events
+ */
+export function UploadQueue({ batch, events, onClose }: UploadQueueProps) {
+  const { toast } = useToast();
+  const { openSettings } = useAppStore();
+
+  const statusOf = (id: string): UploadStatus => events[id]?.status ?? "queued";
+  const finished = batch.items.filter((item) => FINISHED.includes(statusOf(item.id))).length;
+  const allFinished = finished === batch.items.length;
+
+  const handleCancel = async (itemId: string) => {
+    try {
+      await safeInvoke("cancel_upload", { itemId });
+    } catch (error) {
+      toast(errorToast("Cancel failed", error));
+    }
+  };
+
+  const handleCancelAll = async () => {
+    await safeInvoke<number>("cancel_batch", { batchId: batch.batch_id });
+  };
+
+  const handleCopyAll = async () => {
+    const urls = batch.items.map((item) => events[item.id]?.url).filter(Boolean);
+    await navigator.clipboard.writeText(urls.join("\n"));
+    toast({
+      title: "Copied",
+      description: `${urls.length} URLs copied to clipboard`,
+    });
+  };
+
+  return (
+    <div className="bg-card border border-border/50 rounded-2xl shadow-lg p-6 space-y-4">
+      <div className="flex items-center justify-between">
+        <div>
+          <h2 className="text-xl font-semibold">Uploading {batch.items.length} images</h2>
+          <p className="text-sm text-muted-foreground">
+            {finished} of {batch.items.length} finished
+          </p>
+        </div>
+        <div className="flex gap-2">
+          {allFinished ? (
+            <>
+              <Button variant="outline" onClick={handleCopyAll}>
+                <Copy className="mr-2 h-4 w-4" />
+                Copy URLs
+              </Button>
+              <Button onClick={onClose}>Done</Button>
+            </>
+          ) : (
+            <Button variant="outline" onClick={handleCancelAll}>
+              <X className="mr-2 h-4 w-4" />
+              Cancel All
+            </Button>
+          )}
+        </div>
+      </div>
+
+      <div className="space-y-2">
+        {batch.items.map((item) => {
+          const event = events[item.id];
+          const status = statusOf(item.id);
+          const fixTarget = status === "failed" ? settingsTarget(event?.error) : null;
+          const percent =
+            status === "done"
+              ? 100
+              : event && event.bytes_total > 0
+                ? Math.round((event.bytes_sent / event.bytes_total) * 100)
+                : 0;
+
+          return (
+            <div key={item.id} className="p-3 rounded-lg border border-border/50 space-y-2">
+              <div className="flex items-center gap-3">
+                {status === "done" && <Check className="h-4 w-4 text-primary shrink-0" />}
+                {status === "failed" && <AlertCircle className="h-4 w-4 text-destructive shrink-0" />}
+                {status === "cancelled" && <X className="h-4 w-4 text-muted-foreground shrink-0" />}
+                {status === "queued" && <Clock className="h-4 w-4 text-muted-foreground shrink-0" />}
+                {(status === "processing" || status === "uploading" || status === "retrying") && (
+                  <Loader2 className="h-4 w-4 animate-spin shrink-0" />
+                )}
+                <span className="font-medium truncate flex-1">{item.name}</span>
+                <span className="text-xs text-muted-foreground capitalize">
+                  {status === "uploading" ? `uploading ${percent}%` : status}
+                  {event && event.attempt > 1 && !FINISHED.includes(status) && ` (attempt ${event.attempt})`}
+                </span>
+                {!FINISHED.includes(status) && (
+                  <Button size="sm" variant="outline" className="h-7 w-7 p-0" onClick={() => handleCancel(item.id)}>
+                    <X className="h-3 w-3" />
+                  </Button>
+                )}
+              </div>
+              {(status === "uploading" || status === "done") && (
+                <div className="h-1.5 rounded-full bg-muted overflow-hidden">
+                  <div className="h-full bg-primary transition-all" style={{ width: `${percent}%` }} />
+                </div>
+              )}
+              {event?.url && <p className="text-xs font-mono text-muted-foreground truncate">{event.url}</p>}
+              {event?.error && (
+                <p className={`text-xs ${status === "retrying" ? "text-muted-foreground" : "text-destructive"}`}>
+                  {event.error.message}
+                  {fixTarget && (
+                    <button className="ml-2 underline" onClick={() => openSettings(fixTarget)}>
+                      Open Settings
+                    </button>
+                  )}
+                </p>
+              )}
+            </div>
+          );
+        })}
+      </div>
+    </div>
+  );
+}
@@ -4,6 +4,7 @@ type ToastProps = {
   title?: string;
   description?: string;
   variant?: "default" | "destructive";
+  action?: { label: string; onClick: () => void };
 };
 
 type Toast = ToastProps & {
@@ -0,0 +1,88 @@
+/**
+ * Errors returned by Tauri commands, mirroring `AppError` in src-tauri/src/error.rs
+ */
+import { useAppStore } from "@/state/appStore";
+
+export type ErrorCode =
+  | "clipboard_empty"
+  | "clipboard_unavailable"
+  | "file_not_found"
+  | "disk_full"
+  | "io"
+  | "image_processing"
+  | "not_found"
+  | "unknown_provider"
+  | "provider_not_configured"
+  | "unsupported"
+  | "auth_failed"
+  | "request_rejected"
+  | "network"
+  | "provider_error"
+  | "invalid_config"
+  | "vault_locked"
+  | "secret_store"
+  | "database"
+  | "internal";
+
+export interface AppError {
+  code: ErrorCode;
+  message: string;
+  details?: {
+    status?: number;
+    body?: string;
+    provider?: string;
+    fields?: string[];
+    /** Why each of `fields` failed validation, keyed like `fields` */
+    field_errors?: Record<string, string>;
+    /** Set when the upload was saved to the offline queue */
+    pending_id?: string;
+  };
+}
+
+export function isAppError(error: unknown): error is AppError {
+  return typeof error === "object" && error !== null && "code" in error && "message" in error;
+}
+
+export function errorMessage(error: unknown): string {
+  if (isAppError(error)) return error.message;
+  if (error instanceof Error) return error.message;
+  return String(error);
+}
+
+/** The provider tab holding a `Config` key such as `r2_endpoint`, if any */
+export function fieldProvider(field: string): string | undefined {
+  const prefix = field.split("_")[0];
+  return ["cloudinary", "r2", "s3"].includes(prefix) ? prefix : undefined;
+}
+
+/** Where in Settings the user can fix `error`, if anywhere */
+export function settingsTarget(error: unknown): { provider?: string; field?: string } | null {
+  if (!isAppError(error)) return null;
+
+  const field = error.details?.fields?.[0];
+  const provider = error.details?.provider ?? (field ? fieldProvider(field) : undefined);
+  switch (error.code) {
+    case "provider_not_configured":
+    case "auth_failed":
+    case "invalid_config":
+      return provider || field ? { provider, field } : null;
+    case "vault_locked":
+      return { field: "vault_passphrase" };
+    default:
+      return null;
+  }
+}
+
+/** Toast props for a failed command, with a shortcut to Settings when that is where the fix is */
+export function errorToast(title: string, error: unknown) {
+  const target = settingsTarget(error);
+
+  return {
+    title,
+    description: errorMessage(error),
+    variant: "destructive" as const,
+    action: target
+      ? { label: "Open Settings", onClick: () => useAppStore.getState().openSettings(target) }
+      : undefined,
+  };
+}
@@ -20,6 +20,16 @@ export async function safeInvoke<T = any>(cmd: string, args?: Record<string, unk
   return invoke<T>(cmd, args);
 }
 
+// Safe event listener; resolves to the unlisten function
+export async function safeListen<T>(event: string, handler: (payload: T) => void): Promise<() => void> {
+  if (!isTauriAvailable()) {
+    return () => {};
+  }
+
+  const { listen } = await import("@tauri-apps/api/event");
+  return listen<T>(event, (e) => handler(e.payload));
+}
+
 // Safe dialog wrapper
 export async function safeOpen(options?: any) {
   if (!isTauriAvailable()) {
@@ -32,3 +42,15 @@ export async function safeOpen(options?: any) {
   return open(options);
 }
 
+
+// Safe save dialog wrapper; resolves to the chosen path, or null when cancelled
+export async function safeSave(options?: any): Promise<string | null> {
+  if (!isTauriAvailable()) {
+    throw new Error(
+      "Tauri is not available. Please run the app using 'npm run tauri:dev' instead of 'npm run dev'"
+    );
+  }
+
+  const { save } = await import("@tauri-apps/api/dialog");
+  return save(options);
+}
@@ -3,7 +3,9 @@ import { safeInvoke } from "@/lib/tauri";
 import { Button } from "@/components/common/Button";
 import { useAppStore } from "@/state/appStore";
 import { useToast } from "@/hooks/useToast";
-import { Copy, Trash2, Check, Image as ImageIcon, ExternalLink, Calendar, ChevronLeft, ChevronRight } from "lucide-react";
+import { errorToast } from "@/lib/errors";
+import { PendingUploads } from "@/components/features/PendingUploads";
+import { Copy, Trash2, Check, Image as ImageIcon, ExternalLink, Calendar, ChevronLeft, ChevronRight, Code } from "lucide-react";
 
 const ITEMS_PER_PAGE = 10;
 
@@ -20,38 +22,57 @@ export default function HistoryView() {
   const loadHistory = async () => {
     try {
       const result = await safeInvoke<any[]>("get_history");
-      setHistory(result);
+      setHistory(
+        result.map((item) => ({
+          id: item.id,
+          provider: item.provider,
+          originalName: item.original_name,
+          url: item.url,
+          createdAt: item.created_at,
+          thumbnailBase64: item.thumbnail_base64,
+          width: item.width,
+          groupId: item.group_id,
+        }))
+      );
     } catch (error) {
-      toast({
-        title: "Failed to load history",
-        description: String(error),
-        variant: "destructive",
-      });
+      toast(errorToast("Failed to load history", error));
     }
   };
 
-  const handleCopy = async (url: string, id: string) => {
+  const handleCopy = async (id: string) => {
     try {
-      await navigator.clipboard.writeText(url);
+      await safeInvoke<string>("copy_history_link", { id });
       setCopiedId(id);
       setTimeout(() => setCopiedId(null), 2000);
       
       toast({
         title: "Copied",
-        description: "URL copied to clipboard",
+        description: "Link copied to clipboard",
       });
     } catch (error) {
+      toast(errorToast("Copy failed", error));
+    }
+  };
+
+  const handleCopyHtml = async (groupId: string, id: string) => {
+    try {
+      const html = await safeInvoke<string>("render_image_set", { groupId });
+      await navigator.clipboard.writeText(html);
+      setCopiedId(id);
+      setTimeout(() => setCopiedId(null), 2000);
+
       toast({
-        title: "Copy failed",
-        description: String(error),
-        variant: "destructive",
+        title: "Copied",
+        description: "Responsive markup copied to clipboard",
       });
+    } catch (error) {
+      toast(errorToast("Copy failed", error));
     }
   };
 
-  const handleDelete = async (id: string, url: string, provider: string) => {
+  const handleDelete = async (id: string) => {
     try {
-      await safeInvoke("delete_history_item", { id, url, provider });
+      await safeInvoke("delete_history_item", { id });
       await loadHistory();
       
       // Reset to first page if current page becomes empty
@@ -65,11 +86,9 @@ export default function HistoryView() {
         description: "Item removed from history",
       });
     } catch (error) {
-      toast({
-        title: "Delete failed",
-        description: String(error),
-        variant: "destructive",
-      });
+      // The item stays in history marked as "delete_failed"
+      await loadHistory();
+      toast(errorToast("Delete failed", error));
     }
   };
 
@@ -90,14 +109,17 @@ export default function HistoryView() {
 
   if (history.length === 0) {
     return (
-      <div className="flex flex-col items-center justify-center min-h-full">
-        <div className="text-center space-y-4">
-          <div className="inline-flex p-4 rounded-full bg-muted">
-            <ImageIcon className="h-12 w-12 text-muted-foreground" />
-          </div>
-          <div>
-            <h3 className="text-xl font-semibold mb-2">No upload history yet</h3>
-            <p className="text-muted-foreground">Upload your first image to see it here</p>
+      <div className="flex flex-col min-h-full max-w-7xl mx-auto">
+        <PendingUploads onChange={loadHistory} />
+        <div className="flex-1 flex flex-col items-center justify-center">
+          <div className="text-center space-y-4">
+            <div className="inline-flex p-4 rounded-full bg-muted">
+              <ImageIcon className="h-12 w-12 text-muted-foreground" />
+            </div>
+            <div>
+              <h3 className="text-xl font-semibold mb-2">No upload history yet</h3>
+              <p className="text-muted-foreground">Upload your first image to see it here</p>
+            </div>
           </div>
         </div>
       </div>
@@ -106,6 +128,8 @@ export default function HistoryView() {
 
   return (
     <div className="max-w-7xl mx-auto">
+      <PendingUploads onChange={loadHistory} />
+
       {/* Header */}
       <div className="mb-6">
         <h2 className="text-2xl font-bold mb-2">Upload History</h2>
@@ -150,6 +174,11 @@ export default function HistoryView() {
                         <span className="inline-flex items-center gap-1 px-2 py-0.5 rounded-full bg-primary/10 text-primary font-medium">
                           {item.provider === "cloudinary" ? "Cloudinary" : "R2"}
                         </span>
+                        {item.groupId && (
+                          <span className="inline-flex items-center px-2 py-0.5 rounded-full bg-muted font-medium">
+                            Set · {item.width}w
+                          </span>
+                        )}
                         <span className="flex items-center gap-1">
                           <Calendar className="h-3 w-3" />
                           {new Date(item.createdAt * 1000).toLocaleString()}
@@ -169,7 +198,7 @@ export default function HistoryView() {
                   <Button
                     size="sm"
                     variant="outline"
-                    onClick={() => handleCopy(item.url, item.id)}
+                    onClick={() => handleCopy(item.id)}
                     className="h-9"
                   >
                     {copiedId === item.id ? (
@@ -184,6 +213,17 @@ export default function HistoryView() {
                       </>
                     )}
                   </Button>
+                  {item.groupId && (
+                    <Button
+                      size="sm"
+                      variant="outline"
+                      onClick={() => handleCopyHtml(item.groupId!, item.id)}
+                      className="h-9 w-9 p-0"
+                      title="Copy responsive markup"
+                    >
+                      <Code className="h-4 w-4" />
+                    </Button>
+                  )}
                   <Button
                     size="sm"
                     variant="outline"
@@ -195,7 +235,7 @@ export default function HistoryView() {
                   <Button
                     size="sm"
                     variant="destructive"
-                    onClick={() => handleDelete(item.id, item.url, item.provider)}
+                    onClick={() => handleDelete(item.id)}
                     className="h-9 w-9 p-0"
                   >
                     <Trash2 className="h-4 w-4" />
@@ -1,14 +1,31 @@
-import { useState, useEffect } from "react";
+import { useState, useEffect, FocusEvent } from "react";
 import { safeInvoke } from "@/lib/tauri";
 import { Button } from "@/components/common/Button";
 import { Input } from "@/components/common/Input";
 import { Label } from "@/components/common/Label";
 import { Tabs, TabsContent, TabsList, TabsTrigger } from "@/components/common/Tabs";
 import { useToast } from "@/hooks/useToast";
+import { AppError, errorToast, fieldProvider, isAppError } from "@/lib/errors";
+import { useAppStore } from "@/state/appStore";
 import { GreetTest } from "@/components/features/GreetTest";
-import { Cloud, Image as ImageIcon, Save, Loader2, Plus } from "lucide-react";
+import { SecretVault } from "@/components/features/SecretVault";
+import { ConnectionTest } from "@/components/features/ConnectionTest";
+import { ConfigTransfer } from "@/components/features/ConfigTransfer";
+import { ProfileSettings, ProviderAccount, UploadProfile } from "@/components/features/ProfileSettings";
+import { Cloud, Image as ImageIcon, Save, Loader2, Plus, Server, Layers, Link, Trash2 } from "lucide-react";
+
+/** Mirrors `secret_store::REDACTED`: a stored secret, left unchanged when saved back */
+const REDACTED = "********";
+
+interface LinkTemplate {
+  id: string;
+  name: string;
+  template: string;
+}
 
 interface ConfigData {
+  /** `config_schema::latest_version()`; the backend sets it on save */
+  version: number;
   cloudinary_cloud_name: string;
   cloudinary_api_key: string;
   cloudinary_api_secret: string;
@@ -17,13 +34,49 @@ interface ConfigData {
   r2_bucket_name: string;
   r2_endpoint: string;
   r2_public_domain: string;
+  s3_access_key_id: string;
+  s3_secret_access_key: string;
+  s3_bucket_name: string;
+  s3_region: string;
+  s3_endpoint: string;
+  s3_force_path_style: boolean;
+  s3_public_domain: string;
+  s3_acl: string;
+  s3_cache_control: string;
   settings_max_width: number;
   settings_auto_webp: boolean;
+  settings_output_format: "webp" | "png" | "jpeg" | "avif" | "original" | null;
+  settings_jpeg_quality: number;
+  settings_avif_quality: number;
+  settings_avif_speed: number;
+  settings_metadata_policy: "strip" | "keep_copyright" | "keep_all";
+  settings_animation_mode: "webp" | "original";
+  settings_svg_sanitize: boolean;
+  settings_svg_mode: "keep" | "rasterize";
+  settings_svg_raster_width: number;
+  settings_variant_widths: number[];
+  settings_variant_retina: boolean;
+  settings_variant_formats: ("webp" | "png" | "jpeg" | "avif" | "original")[];
+  settings_variant_sizes: string;
+  settings_link_template: string;
+  settings_custom_templates: LinkTemplate[];
+  settings_copy_link: boolean;
+  settings_upload_concurrency: number;
+  settings_webp_mode: "auto" | "lossy" | "lossless";
+  settings_webp_quality: number;
+  settings_default_provider: string;
+  settings_default_profile: string;
+  accounts: ProviderAccount[];
+  profiles: UploadProfile[];
+  settings_server_enabled: boolean;
+  settings_server_port: number;
+  settings_server_max_upload_mb: number;
 }
 
 export default function SettingsView() {
   const { toast } = useToast();
   const [config, setConfig] = useState<ConfigData>({
+    version: 2,
     cloudinary_cloud_name: "",
     cloudinary_api_key: "",
     cloudinary_api_secret: "",
@@ -32,44 +85,153 @@ export default function SettingsView() {
     r2_bucket_name: "",
     r2_endpoint: "",
     r2_public_domain: "",
+    s3_access_key_id: "",
+    s3_secret_access_key: "",
+    s3_bucket_name: "",
+    s3_region: "",
+    s3_endpoint: "",
+    s3_force_path_style: false,
+    s3_public_domain: "",
+    s3_acl: "",
+    s3_cache_control: "",
     settings_max_width: 1600,
     settings_auto_webp: true,
+    settings_output_format: "webp",
+    settings_jpeg_quality: 85,
+    settings_avif_quality: 70,
+    settings_avif_speed: 6,
+    settings_metadata_policy: "strip",
+    settings_animation_mode: "webp",
+    settings_svg_sanitize: true,
+    settings_svg_mode: "keep",
+    settings_svg_raster_width: 1200,
+    settings_variant_widths: [480, 960, 1600],
+    settings_variant_retina: true,
+    settings_variant_formats: ["webp", "jpeg"],
+    settings_variant_sizes: "100vw",
+    settings_link_template: "markdown",
+    settings_custom_templates: [],
+    settings_copy_link: true,
+    settings_upload_concurrency: 3,
+    settings_webp_mode: "auto",
+    settings_webp_quality: 85,
+    settings_default_provider: "cloudinary",
+    settings_default_profile: "",
+    accounts: [],
+    profiles: [],
+    settings_server_enabled: false,
+    settings_server_port: 36677,
+    settings_server_max_upload_mb: 50,
   });
 
   useEffect(() => {
     loadConfig();
   }, []);
 
+  const { settingsFocus, clearSettingsFocus, openSettings } = useAppStore();
+  const [providerTab, setProviderTab] = useState("cloudinary");
+
+  // Opened from an error's "Open Settings" action: show the provider and the field to fix
+  useEffect(() => {
+    if (!settingsFocus) return;
+
+    const { provider, field } = settingsFocus;
+    clearSettingsFocus();
+    if (provider) setProviderTab(provider);
+    if (field) {
+      // The provider tab renders its fields on the next frame
+      requestAnimationFrame(() => {
+        const input = document.getElementById(field);
+        input?.scrollIntoView({ behavior: "smooth", block: "center" });
+        input?.focus();
+      });
+    }
+  }, [settingsFocus, clearSettingsFocus]);
+
+  const [presetTemplates, setPresetTemplates] = useState<LinkTemplate[]>([]);
+  const [serverError, setServerError] = useState<AppError | null>(null);
+
+  const loadServerStatus = async () => {
+    try {
+      const status = await safeInvoke<{ port: number | null; error: AppError | null }>("get_upload_server_status");
+      setServerError(status.error);
+    } catch (error) {
+      console.error("Failed to load upload server status:", error);
+    }
+  };
+
   const loadConfig = async () => {
+    loadServerStatus();
     try {
       const result = await safeInvoke<ConfigData>("get_config");
       setConfig(result);
+
+      const templates = await safeInvoke<LinkTemplate[]>("get_link_templates");
+      const customIds = result.settings_custom_templates.map((template) => template.id);
+      setPresetTemplates(templates.filter((template) => !customIds.includes(template.id)));
     } catch (error) {
       console.error("Failed to load config:", error);
     }
   };
 
-  const handleChange = (key: keyof ConfigData, value: string | number | boolean) => {
+  const handleChange = (key: keyof ConfigData, value: ConfigData[keyof ConfigData]) => {
     setConfig((prev) => ({ ...prev, [key]: value }));
   };
 
+  const updateCustomTemplate = (id: string, changes: Partial<LinkTemplate>) => {
+    handleChange(
+      "settings_custom_templates",
+      config.settings_custom_templates.map((template) => (template.id === id ? { ...template, ...changes } : template))
+    );
+  };
+
+  const addCustomTemplate = () => {
+    handleChange("settings_custom_templates", [
+      ...config.settings_custom_templates,
+      { id: `custom-${Date.now()}`, name: "Custom", template: "![{alt}]({url})" },
+    ]);
+  };
+
+  const removeCustomTemplate = (id: string) => {
+    handleChange(
+      "settings_custom_templates",
+      config.settings_custom_templates.filter((template) => template.id !== id)
+    );
+    if (config.settings_link_template === id) {
+      handleChange("settings_link_template", "markdown");
+    }
+  };
+
+  const parseList = (value: string) =>
+    value
+      .split(",")
+      .map((entry) => entry.trim().toLowerCase())
+      .filter(Boolean);
+
+  // A stored secret comes back as REDACTED; select it so typing replaces it instead of appending
+  const selectRedacted = (e: FocusEvent<HTMLInputElement>) => {
+    if (e.target.value === REDACTED) e.target.select();
+  };
+
   const [isSaving, setIsSaving] = useState(false);
+  // Fields the last save rejected, with the reason for each
+  const [fieldErrors, setFieldErrors] = useState<Record<string, string>>({});
 
   const handleSave = async () => {
     setIsSaving(true);
     try {
       await safeInvoke("save_config", { config });
-      
+      setFieldErrors({});
+      loadServerStatus();
+
       toast({
         title: "Settings saved",
         description: "Configuration updated successfully",
       });
     } catch (error) {
-      toast({
-        title: "Save failed",
-        description: String(error),
-        variant: "destructive",
-      });
+      setFieldErrors((isAppError(error) && error.details?.field_errors) || {});
+      loadServerStatus();
+      toast(errorToast("Save failed", error));
     } finally {
       setIsSaving(false);
     }
@@ -88,9 +250,11 @@ export default function SettingsView() {
         <GreetTest />
       </div>
 
+      <SecretVault onUnlock={loadConfig} />
+
       {/* Cloud Providers Tabs */}
       <div className="bg-card border border-border/50 rounded-xl shadow-sm overflow-hidden">
-        <Tabs defaultValue="cloudinary" className="w-full">
+        <Tabs value={providerTab} onValueChange={setProviderTab} className="w-full">
           <div className="border-b border-border/50 px-6 pt-4">
             <div className="flex items-center justify-between mb-4">
               <div className="flex items-center gap-3">
@@ -124,12 +288,15 @@ export default function SettingsView() {
                 <Cloud className="h-4 w-4 mr-2" />
                 Cloudflare R2
               </TabsTrigger>
-              {/* Future: Easy to add more providers */}
-              {/* <TabsTrigger value="s3">
+              <TabsTrigger 
+                value="s3"
+                className="data-[state=active]:bg-primary data-[state=active]:text-primary-foreground"
+              >
                 <Cloud className="h-4 w-4 mr-2" />
-                AWS S3
+                S3 Compatible
               </TabsTrigger>
-              <TabsTrigger value="gcs">
+              {/* Future: Easy to add more providers */}
+              {/* <TabsTrigger value="gcs">
                 <Cloud className="h-4 w-4 mr-2" />
                 Google Cloud
               </TabsTrigger> */}
@@ -166,9 +333,12 @@ export default function SettingsView() {
                   type="password"
                   value={config.cloudinary_api_secret}
                   onChange={(e) => handleChange("cloudinary_api_secret", e.target.value)}
+                  onFocus={selectRedacted}
                   placeholder="••••••••••••••••"
                 />
               </div>
+
+              <ConnectionTest account="cloudinary" />
             </div>
           </TabsContent>
 
@@ -192,6 +362,7 @@ export default function SettingsView() {
                   type="password"
                   value={config.r2_secret_access_key}
                   onChange={(e) => handleChange("r2_secret_access_key", e.target.value)}
+                  onFocus={selectRedacted}
                   placeholder="••••••••••••••••"
                 />
               </div>
@@ -225,18 +396,127 @@ export default function SettingsView() {
                   placeholder="https://cdn.example.com"
                 />
               </div>
+
+              <ConnectionTest account="r2" roundTrip />
             </div>
           </TabsContent>
 
-          {/* Future: Easy to add more provider tabs */}
-          {/* <TabsContent value="s3" className="p-6 m-0">
+          {/* S3 Compatible Content (AWS S3, MinIO, Wasabi, DigitalOcean Spaces) */}
+          <TabsContent value="s3" className="p-6 m-0">
             <div className="grid md:grid-cols-2 gap-4">
-              ... S3 fields ...
+              <div className="space-y-2">
+                <Label htmlFor="s3_access_key_id">Access Key ID</Label>
+                <Input
+                  id="s3_access_key_id"
+                  value={config.s3_access_key_id}
+                  onChange={(e) => handleChange("s3_access_key_id", e.target.value)}
+                  placeholder="your-access-key-id"
+                />
+              </div>
+
+              <div className="space-y-2">
+                <Label htmlFor="s3_secret_access_key">Secret Access Key</Label>
+                <Input
+                  id="s3_secret_access_key"
+                  type="password"
+                  value={config.s3_secret_access_key}
+                  onChange={(e) => handleChange("s3_secret_access_key", e.target.value)}
+                  onFocus={selectRedacted}
+                  placeholder="••••••••••••••••"
+                />
+              </div>
+
+              <div className="space-y-2">
+                <Label htmlFor="s3_bucket_name">Bucket Name</Label>
+                <Input
+                  id="s3_bucket_name"
+                  value={config.s3_bucket_name}
+                  onChange={(e) => handleChange("s3_bucket_name", e.target.value)}
+                  placeholder="my-bucket"
+                />
+              </div>
+
+              <div className="space-y-2">
+                <Label htmlFor="s3_region">Region</Label>
+                <Input
+                  id="s3_region"
+                  value={config.s3_region}
+                  onChange={(e) => handleChange("s3_region", e.target.value)}
+                  placeholder="us-east-1"
+                />
+              </div>
+
+              <div className="space-y-2">
+                <Label htmlFor="s3_endpoint">Endpoint</Label>
+                <Input
+                  id="s3_endpoint"
+                  value={config.s3_endpoint}
+                  onChange={(e) => handleChange("s3_endpoint", e.target.value)}
+                  placeholder="Leave empty for AWS, e.g. http://localhost:9000"
+                />
+              </div>
+
+              <div className="space-y-2">
+                <Label htmlFor="s3_public_domain">Public Domain</Label>
+                <Input
+                  id="s3_public_domain"
+                  value={config.s3_public_domain}
+                  onChange={(e) => handleChange("s3_public_domain", e.target.value)}
+                  placeholder="Leave empty to use the bucket URL"
+                />
+              </div>
+
+              <div className="space-y-2">
+                <Label htmlFor="s3_acl">ACL</Label>
+                <Input
+                  id="s3_acl"
+                  value={config.s3_acl}
+                  onChange={(e) => handleChange("s3_acl", e.target.value)}
+                  placeholder="public-read"
+                />
+              </div>
+
+              <div className="space-y-2">
+                <Label htmlFor="s3_cache_control">Cache-Control</Label>
+                <Input
+                  id="s3_cache_control"
+                  value={config.s3_cache_control}
+                  onChange={(e) => handleChange("s3_cache_control", e.target.value)}
+                  placeholder="public, max-age=31536000"
+                />
+              </div>
+
+              <div className="flex items-start gap-3 p-4 rounded-lg border border-border/50 bg-muted/30 md:col-span-2">
+                <input
+                  id="s3_force_path_style"
+                  type="checkbox"
+                  checked={config.s3_force_path_style}
+                  onChange={(e) => handleChange("s3_force_path_style", e.target.checked)}
+                  className="w-4 h-4 text-primary rounded mt-1"
+                />
+                <Label htmlFor="s3_force_path_style" className="cursor-pointer flex-1">
+                  <span className="font-medium block mb-1">Path-style addressing</span>
+                  <p className="text-xs text-muted-foreground">Use endpoint/bucket/key URLs instead of bucket.endpoint/key (required for MinIO)</p>
+                </Label>
+              </div>
+
+              <ConnectionTest account="s3" roundTrip />
             </div>
-          </TabsContent> */}
+          </TabsContent>
         </Tabs>
       </div>
 
+      <ProfileSettings
+        accounts={config.accounts}
+        profiles={config.profiles}
+        defaultProfile={config.settings_default_profile}
+        defaultProvider={config.settings_default_provider}
+        onAccountsChange={(accounts) => handleChange("accounts", accounts)}
+        onProfilesChange={(profiles) => handleChange("profiles", profiles)}
+        onDefaultProfileChange={(id) => handleChange("settings_default_profile", id)}
+        onSecretFocus={selectRedacted}
+      />
+
       {/* Processing Settings */}
       <div className="bg-card border border-border/50 rounded-xl p-6 shadow-sm">
         <div className="flex items-center gap-3 mb-6">
@@ -258,29 +538,417 @@ export default function SettingsView() {
               value={config.settings_max_width}
               onChange={(e) => handleChange("settings_max_width", parseInt(e.target.value))}
               min="100"
-              max="4000"
+              max="8192"
             />
             <p className="text-xs text-muted-foreground">Images wider than this will be resized</p>
           </div>
 
+          <div className="space-y-2">
+            <Label htmlFor="settings_upload_concurrency">Parallel Uploads</Label>
+            <Input
+              id="settings_upload_concurrency"
+              type="number"
+              value={config.settings_upload_concurrency}
+              onChange={(e) => handleChange("settings_upload_concurrency", parseInt(e.target.value))}
+              min="1"
+              max="16"
+            />
+            <p className="text-xs text-muted-foreground">How many queued images are processed and sent at once, across all uploads</p>
+          </div>
+
+          <div className="space-y-2">
+            <Label htmlFor="settings_output_format">Output Format</Label>
+            <select
+              id="settings_output_format"
+              value={config.settings_output_format ?? (config.settings_auto_webp ? "webp" : "original")}
+              onChange={(e) => {
+                handleChange("settings_output_format", e.target.value);
+                handleChange("settings_auto_webp", e.target.value === "webp");
+              }}
+              className="flex h-10 w-full rounded-md border border-input bg-background px-3 py-2 text-sm"
+            >
+              <option value="webp">WebP</option>
+              <option value="png">Optimized PNG</option>
+              <option value="jpeg">Progressive JPEG</option>
+              <option value="avif">AVIF</option>
+              <option value="original">Keep original format</option>
+            </select>
+            <p className="text-xs text-muted-foreground">Format images are converted to before upload</p>
+          </div>
+
+          <div className="space-y-2">
+            <Label htmlFor="settings_jpeg_quality">JPEG Quality</Label>
+            <Input
+              id="settings_jpeg_quality"
+              type="number"
+              value={config.settings_jpeg_quality}
+              onChange={(e) => handleChange("settings_jpeg_quality", parseInt(e.target.value))}
+              min="1"
+              max="100"
+            />
+            <p className="text-xs text-muted-foreground">Used for progressive JPEG output (1-100)</p>
+          </div>
+
+          <div className="space-y-2">
+            <Label htmlFor="settings_webp_mode">WebP Encoding</Label>
+            <select
+              id="settings_webp_mode"
+              value={config.settings_webp_mode}
+              onChange={(e) => handleChange("settings_webp_mode", e.target.value)}
+              className="flex h-10 w-full rounded-md border border-input bg-background px-3 py-2 text-sm"
+            >
+              <option value="auto">Auto (lossless for screenshots, lossy for photos)</option>
+              <option value="lossy">Lossy</option>
+              <option value="lossless">Lossless</option>
+            </select>
+          </div>
+
+          <div className="space-y-2">
+            <Label htmlFor="settings_webp_quality">WebP Quality</Label>
+            <Input
+              id="settings_webp_quality"
+              type="number"
+              value={config.settings_webp_quality}
+              onChange={(e) => handleChange("settings_webp_quality", parseInt(e.target.value))}
+              min="0"
+              max="100"
+              disabled={config.settings_webp_mode === "lossless"}
+            />
+            <p className="text-xs text-muted-foreground">Used for lossy encoding (0-100)</p>
+          </div>
+
+          <div className="space-y-2">
+            <Label htmlFor="settings_avif_quality">AVIF Quality</Label>
+            <Input
+              id="settings_avif_quality"
+              type="number"
+              value={config.settings_avif_quality}
+              onChange={(e) => handleChange("settings_avif_quality", parseInt(e.target.value))}
+              min="1"
+              max="100"
+            />
+            <p className="text-xs text-muted-foreground">Used for AVIF output (1-100)</p>
+          </div>
+
+          <div className="space-y-2">
+            <Label htmlFor="settings_avif_speed">AVIF Speed</Label>
+            <Input
+              id="settings_avif_speed"
+              type="number"
+              value={config.settings_avif_speed}
+              onChange={(e) => handleChange("settings_avif_speed", parseInt(e.target.value))}
+              min="1"
+              max="10"
+            />
+            <p className="text-xs text-muted-foreground">1 is slowest with the smallest files, 10 is fastest</p>
+          </div>
+
+          <div className="space-y-2">
+            <Label htmlFor="settings_metadata_policy">Metadata</Label>
+            <select
+              id="settings_metadata_policy"
+              value={config.settings_metadata_policy}
+              onChange={(e) => handleChange("settings_metadata_policy", e.target.value)}
+              className="flex h-10 w-full rounded-md border border-input bg-background px-3 py-2 text-sm"
+            >
+              <option value="strip">Strip all (removes GPS and camera details)</option>
+              <option value="keep_copyright">Keep author and copyright only</option>
+              <option value="keep_all">Keep everything</option>
+            </select>
+            <p className="text-xs text-muted-foreground">Photos are always rotated upright first. AVIF output never carries metadata</p>
+          </div>
+
+          <div className="space-y-2">
+            <Label htmlFor="settings_animation_mode">Animated GIF / WebP</Label>
+            <select
+              id="settings_animation_mode"
+              value={config.settings_animation_mode}
+              onChange={(e) => handleChange("settings_animation_mode", e.target.value)}
+              className="flex h-10 w-full rounded-md border border-input bg-background px-3 py-2 text-sm"
+            >
+              <option value="webp">Convert to animated WebP</option>
+              <option value="original">Upload unchanged</option>
+            </select>
+            <p className="text-xs text-muted-foreground">Applies to animations regardless of the output format</p>
+          </div>
+
+          <div className="space-y-2">
+            <Label htmlFor="settings_svg_mode">SVG</Label>
+            <select
+              id="settings_svg_mode"
+              value={config.settings_svg_mode}
+              onChange={(e) => handleChange("settings_svg_mode", e.target.value)}
+              className="flex h-10 w-full rounded-md border border-input bg-background px-3 py-2 text-sm"
+            >
+              <option value="keep">Upload as SVG</option>
+              <option value="rasterize">Rasterize to the output format</option>
+            </select>
+            <p className="text-xs text-muted-foreground">Rasterize for platforms that reject SVG</p>
+          </div>
+
+          <div className="space-y-2">
+            <Label htmlFor="settings_svg_raster_width">SVG Raster Width (px)</Label>
+            <Input
+              id="settings_svg_raster_width"
+              type="number"
+              value={config.settings_svg_raster_width}
+              onChange={(e) => handleChange("settings_svg_raster_width", parseInt(e.target.value))}
+              min="1"
+              disabled={config.settings_svg_mode !== "rasterize"}
+            />
+          </div>
+
+          <div className="flex items-start gap-3 p-4 rounded-lg border border-border/50 bg-muted/30 md:col-span-2">
+            <input
+              id="settings_svg_sanitize"
+              type="checkbox"
+              checked={config.settings_svg_sanitize}
+              onChange={(e) => handleChange("settings_svg_sanitize", e.target.checked)}
+              className="w-4 h-4 text-primary rounded mt-1"
+            />
+            <Label htmlFor="settings_svg_sanitize" className="cursor-pointer flex-1">
+              <span className="font-medium block mb-1">Sanitize and minify SVGs</span>
+              <p className="text-xs text-muted-foreground">Strips scripts, event handlers, external references, comments and metadata</p>
+            </Label>
+          </div>
+        </div>
+      </div>
+
+      {/* Responsive Set Settings */}
+      <div className="bg-card border border-border/50 rounded-xl p-6 shadow-sm">
+        <div className="flex items-center gap-3 mb-6">
+          <div className="p-2 rounded-lg bg-primary/10">
+            <Layers className="h-5 w-5 text-primary" />
+          </div>
+          <div>
+            <h3 className="text-lg font-semibold">Responsive Sets</h3>
+            <p className="text-sm text-muted-foreground">Widths and formats uploaded by "Upload Responsive Set"</p>
+          </div>
+        </div>
+
+        <div className="grid md:grid-cols-2 gap-6">
+          <div className="space-y-2">
+            <Label htmlFor="settings_variant_widths">Widths (px, comma separated)</Label>
+            <Input
+              id="settings_variant_widths"
+              key={config.settings_variant_widths.join(",")}
+              defaultValue={config.settings_variant_widths.join(", ")}
+              onBlur={(e) =>
+                handleChange(
+                  "settings_variant_widths",
+                  parseList(e.target.value)
+                    .map((width) => parseInt(width))
+                    .filter((width) => width > 0)
+                )
+              }
+              placeholder="480, 960, 1600"
+            />
+          </div>
+
+          <div className="space-y-2">
+            <Label htmlFor="settings_variant_formats">Formats (comma separated, fallback last)</Label>
+            <Input
+              id="settings_variant_formats"
+              key={config.settings_variant_formats.join(",")}
+              defaultValue={config.settings_variant_formats.join(", ")}
+              onBlur={(e) =>
+                handleChange(
+                  "settings_variant_formats",
+                  parseList(e.target.value).filter((format) =>
+                    ["webp", "png", "jpeg", "avif", "original"].includes(format)
+                  )
+                )
+              }
+              placeholder="avif, webp, jpeg"
+            />
+          </div>
+
+          <div className="space-y-2 md:col-span-2">
+            <Label htmlFor="settings_variant_sizes">Sizes Attribute</Label>
+            <Input
+              id="settings_variant_sizes"
+              value={config.settings_variant_sizes}
+              onChange={(e) => handleChange("settings_variant_sizes", e.target.value)}
+              placeholder="(min-width: 768px) 720px, 100vw"
+            />
+          </div>
+
+          <div className="flex items-start gap-3 p-4 rounded-lg border border-border/50 bg-muted/30 md:col-span-2">
+            <input
+              id="settings_variant_retina"
+              type="checkbox"
+              checked={config.settings_variant_retina}
+              onChange={(e) => handleChange("settings_variant_retina", e.target.checked)}
+              className="w-4 h-4 text-primary rounded mt-1"
+            />
+            <Label htmlFor="settings_variant_retina" className="cursor-pointer flex-1">
+              <span className="font-medium block mb-1">Include 2x retina widths</span>
+              <p className="text-xs text-muted-foreground">Also uploads each width doubled, as long as the source is large enough</p>
+            </Label>
+          </div>
+        </div>
+      </div>
+
+      {/* Link Format Settings */}
+      <div className="bg-card border border-border/50 rounded-xl p-6 shadow-sm">
+        <div className="flex items-center gap-3 mb-6">
+          <div className="p-2 rounded-lg bg-primary/10">
+            <Link className="h-5 w-5 text-primary" />
+          </div>
+          <div>
+            <h3 className="text-lg font-semibold">Link Format</h3>
+            <p className="text-sm text-muted-foreground">How links are rendered after an upload and when copied from history</p>
+          </div>
+        </div>
+
+        <div className="grid md:grid-cols-2 gap-6">
+          <div className="space-y-2">
+            <Label htmlFor="settings_link_template">Template</Label>
+            <select
+              id="settings_link_template"
+              value={config.settings_link_template}
+              onChange={(e) => handleChange("settings_link_template", e.target.value)}
+              className="flex h-10 w-full rounded-md border border-input bg-background px-3 py-2 text-sm"
+            >
+              {[...presetTemplates, ...config.settings_custom_templates].map((template) => (
+                <option key={template.id} value={template.id}>
+                  {template.name}
+                </option>
+              ))}
+            </select>
+            <p className="text-xs text-muted-foreground">Placeholders: {"{url}"}, {"{filename}"}, {"{width}"}, {"{height}"}, {"{alt}"}</p>
+          </div>
+
           <div className="flex items-start gap-3 p-4 rounded-lg border border-border/50 bg-muted/30">
             <input
-              id="settings_auto_webp"
+              id="settings_copy_link"
+              type="checkbox"
+              checked={config.settings_copy_link}
+              onChange={(e) => handleChange("settings_copy_link", e.target.checked)}
+              className="w-4 h-4 text-primary rounded mt-1"
+            />
+            <Label htmlFor="settings_copy_link" className="cursor-pointer flex-1">
+              <span className="font-medium block mb-1">Copy link after upload</span>
+              <p className="text-xs text-muted-foreground">Puts the rendered link on the clipboard as soon as the upload finishes</p>
+            </Label>
+          </div>
+
+          <div className="space-y-3 md:col-span-2">
+            <div className="flex items-center justify-between">
+              <Label>Custom Templates</Label>
+              <Button variant="outline" size="sm" onClick={addCustomTemplate}>
+                <Plus className="h-4 w-4 mr-2" />
+                Add Template
+              </Button>
+            </div>
+            {config.settings_custom_templates.map((template) => (
+              <div key={template.id} className="flex gap-2">
+                <Input
+                  value={template.name}
+                  onChange={(e) => updateCustomTemplate(template.id, { name: e.target.value })}
+                  placeholder="Name"
+                  className="w-40"
+                />
+                <Input
+                  value={template.template}
+                  onChange={(e) => updateCustomTemplate(template.id, { template: e.target.value })}
+                  placeholder="[![{alt}]({url})]({url})"
+                  className="flex-1 font-mono"
+                />
+                <Button
+                  variant="outline"
+                  size="icon"
+                  onClick={() => removeCustomTemplate(template.id)}
+                  className="shrink-0"
+                >
+                  <Trash2 className="h-4 w-4" />
+                </Button>
+              </div>
+            ))}
+          </div>
+        </div>
+      </div>
+
+      {/* Upload Server Settings */}
+      <div className="bg-card border border-border/50 rounded-xl p-6 shadow-sm">
+        <div className="flex items-center gap-3 mb-6">
+          <div className="p-2 rounded-lg bg-primary/10">
+            <Server className="h-5 w-5 text-primary" />
+          </div>
+          <div>
+            <h3 className="text-lg font-semibold">Upload Server</h3>
+            <p className="text-sm text-muted-foreground">PicGo-compatible endpoint for Typora and editor extensions</p>
+          </div>
+        </div>
+
+        <div className="grid md:grid-cols-2 gap-6">
+          <div className="flex items-start gap-3 p-4 rounded-lg border border-border/50 bg-muted/30 md:col-span-2">
+            <input
+              id="settings_server_enabled"
               type="checkbox"
-              checked={config.settings_auto_webp}
-              onChange={(e) => handleChange("settings_auto_webp", e.target.checked)}
+              checked={config.settings_server_enabled}
+              onChange={(e) => handleChange("settings_server_enabled", e.target.checked)}
               className="w-4 h-4 text-primary rounded mt-1"
             />
-            <Label htmlFor="settings_auto_webp" className="cursor-pointer flex-1">
-              <span className="font-medium block mb-1">Auto convert to WebP</span>
-              <p className="text-xs text-muted-foreground">Automatically convert images to WebP format for better compression</p>
+            <Label htmlFor="settings_server_enabled" className="cursor-pointer flex-1">
+              <span className="font-medium block mb-1">Enable local upload server</span>
+              <p className="text-xs text-muted-foreground">
+                Listens on http://127.0.0.1:{config.settings_server_port}/upload (loopback only)
+              </p>
+              {config.settings_server_enabled && serverError && (
+                <p className="text-xs text-destructive mt-1">Not running: {serverError.message}</p>
+              )}
             </Label>
           </div>
+
+          <div className="space-y-2">
+            <Label htmlFor="settings_server_port">Port</Label>
+            <Input
+              id="settings_server_port"
+              type="number"
+              value={config.settings_server_port}
+              onChange={(e) => handleChange("settings_server_port", parseInt(e.target.value))}
+              min="1024"
+              max="65535"
+            />
+          </div>
+
+          <div className="space-y-2">
+            <Label htmlFor="settings_server_max_upload_mb">Max Request Size (MB)</Label>
+            <Input
+              id="settings_server_max_upload_mb"
+              type="number"
+              value={config.settings_server_max_upload_mb}
+              onChange={(e) => handleChange("settings_server_max_upload_mb", parseInt(e.target.value))}
+              min="1"
+              max="1024"
+            />
+            <p className="text-xs text-muted-foreground">Larger requests are refused with 413</p>
+          </div>
+
+          <div className="space-y-2">
+            <Label>Profile</Label>
+            <p className="text-sm text-muted-foreground">Uploads go through the default profile, set under Accounts & Profiles</p>
+          </div>
         </div>
       </div>
 
+      <ConfigTransfer onImport={loadConfig} />
+
       {/* Save Button */}
       <div className="sticky bottom-0 bg-background/80 backdrop-blur-sm border-t border-border/50 p-4 -mx-8 -mb-8 mt-6">
+        {Object.keys(fieldErrors).length > 0 && (
+          <ul className="mb-3 space-y-1 text-sm text-destructive">
+            {Object.entries(fieldErrors).map(([field, message]) => (
+              <li key={field}>
+                <button className="underline" onClick={() => openSettings({ provider: fieldProvider(field), field })}>
+                  {field}
+                </button>
+                : {message}
+              </li>
+            ))}
+          </ul>
+        )}
         <Button 
           onClick={handleSave} 
           className="w-full h-11 text-base font-medium"
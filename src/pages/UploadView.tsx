@@ -1,11 +1,21 @@
-import { useState, useCallback } from "react";
+import { useState, useCallback, useEffect } from "react";
 import { useDropzone } from "react-dropzone";
-import { safeInvoke, safeOpen } from "@/lib/tauri";
+import { safeInvoke, safeListen, safeOpen } from "@/lib/tauri";
 import { Button } from "@/components/common/Button";
 import { Label } from "@/components/common/Label";
 import { useAppStore } from "@/state/appStore";
 import { useToast } from "@/hooks/useToast";
-import { Upload, Clipboard, Copy, Check, Image as ImageIcon, Loader2, Cloud, CloudOff } from "lucide-react";
+import { AppError, errorToast, isAppError } from "@/lib/errors";
+import { UploadQueue, UploadBatch, UploadEvent } from "@/components/features/UploadQueue";
+import { Upload, Clipboard, Copy, Check, Image as ImageIcon, Loader2, Cloud, CloudOff, Layers } from "lucide-react";
+
+interface ProfileInfo {
+  id: string;
+  name: string;
+  provider: string;
+  account_id: string;
+  is_default: boolean;
+}
 
 export default function UploadView() {
   const {
@@ -21,22 +31,57 @@ export default function UploadView() {
   } = useAppStore();
 
   const { toast } = useToast();
-  const [selectedProvider, setSelectedProvider] = useState<"cloudinary" | "r2">("cloudinary");
+  const [profiles, setProfiles] = useState<ProfileInfo[]>([]);
+  const [selectedProfile, setSelectedProfile] = useState<string | null>(null);
   const [copied, setCopied] = useState(false);
+  const [batch, setBatch] = useState<UploadBatch | null>(null);
+  const [queueEvents, setQueueEvents] = useState<Record<string, UploadEvent>>({});
+
+  useEffect(() => {
+    safeInvoke<ProfileInfo[]>("list_profiles")
+      .then((result) => {
+        setProfiles(result);
+        setSelectedProfile((current) => current ?? result.find((profile) => profile.is_default)?.id ?? null);
+      })
+      .catch((error) => console.error("Failed to load profiles:", error));
+  }, []);
+
+  // Subscribed before anything is queued so no event is missed
+  useEffect(() => {
+    const unlisten = safeListen<UploadEvent>("upload-progress", (event) => {
+      setQueueEvents((prev) => ({ ...prev, [event.item_id]: event }));
+    });
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, []);
+
+  const handleEnqueue = async (paths: string[]) => {
+    try {
+      const result = await safeInvoke<UploadBatch>("enqueue_uploads", { paths, profile: selectedProfile });
+      setBatch(result);
+    } catch (error) {
+      toast(errorToast("Upload failed", error));
+    }
+  };
 
   const handleProcessImage = async (filePath: string, fileName: string) => {
     try {
       setIsProcessing(true);
       const result = await safeInvoke<{
         preview_base64: string;
+        mime_type: string;
         size_info: string;
         temp_id: string;
+        format_notice?: string;
       }>("process_image_from_file", { path: filePath });
 
       setProcessedImage({
         tempId: result.temp_id,
         previewBase64: result.preview_base64,
+        mimeType: result.mime_type,
         sizeInfo: result.size_info,
+        formatNotice: result.format_notice,
         originalName: fileName,
       });
       
@@ -45,11 +90,7 @@ export default function UploadView() {
         description: "Ready to upload",
       });
     } catch (error) {
-      toast({
-        title: "Processing failed",
-        description: String(error),
-        variant: "destructive",
-      });
+      toast(errorToast("Processing failed", error));
     } finally {
       setIsProcessing(false);
     }
@@ -60,14 +101,18 @@ export default function UploadView() {
       setIsProcessing(true);
       const result = await safeInvoke<{
         preview_base64: string;
+        mime_type: string;
         size_info: string;
         temp_id: string;
+        format_notice?: string;
       }>("process_image_from_clipboard");
 
       setProcessedImage({
         tempId: result.temp_id,
         previewBase64: result.preview_base64,
+        mimeType: result.mime_type,
         sizeInfo: result.size_info,
+        formatNotice: result.format_notice,
         originalName: "clipboard",
       });
       
@@ -76,11 +121,14 @@ export default function UploadView() {
         description: "Ready to upload",
       });
     } catch (error) {
-      toast({
-        title: "Processing failed",
-        description: String(error),
-        variant: "destructive",
-      });
+      if (isAppError(error) && error.code === "clipboard_empty") {
+        toast({
+          title: "Nothing to paste",
+          description: "Copy an image first, then try again",
+        });
+      } else {
+        toast(errorToast("Processing failed", error));
+      }
     } finally {
       setIsProcessing(false);
     }
@@ -91,23 +139,64 @@ export default function UploadView() {
 
     try {
       setIsUploading(true);
-      const result = await safeInvoke<{ url: string }>("upload_image", {
+      const result = await safeInvoke<{ url: string; text: string; copied: boolean; copy_error?: AppError; history_error?: AppError; attempts: number }>("upload_image", {
         tempId: processedImage.tempId,
-        provider: selectedProvider,
+        profile: selectedProfile,
+        originalName: processedImage.originalName,
       });
 
-      setUploadedUrl(result.url);
+      setUploadedUrl(result.text);
+      setCopied(result.copied);
       
-      toast({
-        title: "Upload successful",
-        description: "Image uploaded to cloud",
-      });
+      const retried = result.attempts > 1 ? ` after ${result.attempts} attempts` : "";
+      if (result.history_error) {
+        toast(errorToast("Uploaded, but not saved to history", result.history_error));
+      }
+      if (result.copy_error) {
+        toast(errorToast("Uploaded, but the link was not copied", result.copy_error));
+      } else if (!result.history_error) {
+        toast({
+          title: "Upload successful",
+          description: (result.copied ? "Link copied to clipboard" : "Image uploaded to cloud") + retried,
+        });
+      }
     } catch (error) {
-      toast({
-        title: "Upload failed",
-        description: String(error),
-        variant: "destructive",
+      if (isAppError(error) && error.details?.pending_id) {
+        // The offline queue took the image over
+        resetUploadState();
+        toast({ ...errorToast("Saved for later", error), variant: "default" });
+      } else {
+        toast(errorToast("Upload failed", error));
+      }
+    } finally {
+      setIsUploading(false);
+    }
+  };
+
+  const handleUploadSet = async () => {
+    if (!processedImage) return;
+
+    try {
+      setIsUploading(true);
+      const result = await safeInvoke<{ group_id: string; html: string; urls: string[]; copied: boolean; copy_error?: AppError }>("upload_image_set", {
+        tempId: processedImage.tempId,
+        profile: selectedProfile,
+        alt: processedImage.originalName.replace(/\.[^.]+$/, ""),
       });
+
+      setUploadedUrl(result.html);
+      setCopied(result.copied);
+
+      if (result.copy_error) {
+        toast(errorToast(`${result.urls.length} variants uploaded, but the markup was not copied`, result.copy_error));
+      } else {
+        toast({
+          title: "Upload successful",
+          description: `${result.urls.length} variants uploaded${result.copied ? ", markup copied to clipboard" : ""}`,
+        });
+      }
+    } catch (error) {
+      toast(errorToast("Upload failed", error));
     } finally {
       setIsUploading(false);
     }
@@ -123,42 +212,41 @@ export default function UploadView() {
       
       toast({
         title: "Copied",
-        description: "URL copied to clipboard",
+        description: "Link copied to clipboard",
       });
     } catch (error) {
-      toast({
-        title: "Copy failed",
-        description: String(error),
-        variant: "destructive",
-      });
+      toast(errorToast("Copy failed", error));
     }
   };
 
   const handleFileDialog = async () => {
     try {
       const selected = await safeOpen({
-        multiple: false,
+        multiple: true,
         filters: [{
           name: "Image",
-          extensions: ["png", "jpg", "jpeg", "webp", "gif"],
+          extensions: ["png", "jpg", "jpeg", "webp", "gif", "svg"],
         }],
       });
 
-      if (selected && typeof selected === "string") {
-        const fileName = selected.split(/[\\/]/).pop() || "unknown";
-        await handleProcessImage(selected, fileName);
+      const paths = Array.isArray(selected) ? selected : selected ? [selected] : [];
+      if (paths.length > 1) {
+        await handleEnqueue(paths);
+      } else if (paths.length === 1) {
+        const fileName = paths[0].split(/[\\/]/).pop() || "unknown";
+        await handleProcessImage(paths[0], fileName);
       }
     } catch (error) {
-      toast({
-        title: "Error",
-        description: String(error),
-        variant: "destructive",
-      });
+      toast(errorToast("Error", error));
     }
   };
 
   const onDrop = useCallback(async (acceptedFiles: File[]) => {
-    if (acceptedFiles.length > 0) {
+    // @ts-ignore - Tauri provides path property
+    const paths: string[] = acceptedFiles.map((file) => file.path).filter(Boolean);
+    if (paths.length > 1) {
+      await handleEnqueue(paths);
+    } else if (acceptedFiles.length > 0) {
       const file = acceptedFiles[0];
       // @ts-ignore - Tauri provides path property
       const filePath = file.path;
@@ -171,17 +259,38 @@ export default function UploadView() {
   const { getRootProps, getInputProps, isDragActive } = useDropzone({
     onDrop,
     accept: {
-      "image/*": [".png", ".jpg", ".jpeg", ".webp", ".gif"],
+      "image/*": [".png", ".jpg", ".jpeg", ".webp", ".gif", ".svg"],
     },
-    multiple: false,
+    multiple: true,
     noClick: true,
   });
 
   const handleReset = () => {
+    // An image that was never uploaded would otherwise sit in the cache until it expires
+    if (processedImage && !uploadedUrl) {
+      safeInvoke("discard_image", { tempId: processedImage.tempId }).catch((error) =>
+        console.error("Failed to discard image:", error)
+      );
+    }
     resetUploadState();
     setCopied(false);
   };
 
+  if (batch) {
+    return (
+      <div className="max-w-3xl mx-auto">
+        <UploadQueue
+          batch={batch}
+          events={queueEvents}
+          onClose={() => {
+            setBatch(null);
+            setQueueEvents({});
+          }}
+        />
+      </div>
+    );
+  }
+
   if (uploadedUrl) {
     return (
       <div className="flex items-center justify-center min-h-full">
@@ -196,7 +305,7 @@ export default function UploadView() {
             </div>
             
             <div className="space-y-2">
-              <Label className="text-sm font-medium">Image URL</Label>
+              <Label className="text-sm font-medium">Link</Label>
               <div className="flex gap-2">
                 <input
                   type="text"
@@ -240,7 +349,7 @@ export default function UploadView() {
             <div className="bg-card border border-border/50 rounded-2xl shadow-lg overflow-hidden animate-in fade-in-50 slide-in-from-left-4 duration-500">
               <div className="aspect-video bg-gradient-to-br from-muted/50 to-muted flex items-center justify-center p-8">
                 <img
-                  src={`data:image/webp;base64,${processedImage.previewBase64}`}
+                  src={`data:${processedImage.mimeType};base64,${processedImage.previewBase64}`}
                   alt="Preview"
                   className="max-w-full max-h-full object-contain rounded-lg shadow-lg"
                 />
@@ -265,36 +374,35 @@ export default function UploadView() {
                   <p className="text-muted-foreground mb-1">Size</p>
                   <p className="font-medium">{processedImage.sizeInfo}</p>
                 </div>
+                {processedImage.formatNotice && (
+                  <p className="text-xs text-muted-foreground">{processedImage.formatNotice}</p>
+                )}
               </div>
             </div>
 
-            {/* Provider Selection */}
+            {/* Profile Selection */}
             <div className="bg-card border border-border/50 rounded-xl p-5 shadow-sm">
               <h3 className="font-semibold mb-4 flex items-center gap-2">
                 <Cloud className="h-4 w-4" />
-                Upload Provider
+                Upload Profile
               </h3>
               <div className="space-y-3">
-                <label className="flex items-center gap-3 p-3 rounded-lg border border-border/50 cursor-pointer hover:bg-muted/50 transition-colors">
-                  <input
-                    type="radio"
-                    value="cloudinary"
-                    checked={selectedProvider === "cloudinary"}
-                    onChange={(e) => setSelectedProvider(e.target.value as "cloudinary")}
-                    className="w-4 h-4 text-primary"
-                  />
-                  <span className="font-medium">Cloudinary</span>
-                </label>
-                <label className="flex items-center gap-3 p-3 rounded-lg border border-border/50 cursor-pointer hover:bg-muted/50 transition-colors">
-                  <input
-                    type="radio"
-                    value="r2"
-                    checked={selectedProvider === "r2"}
-                    onChange={(e) => setSelectedProvider(e.target.value as "r2")}
-                    className="w-4 h-4 text-primary"
-                  />
-                  <span className="font-medium">Cloudflare R2</span>
-                </label>
+                {profiles.map((profile) => (
+                  <label
+                    key={profile.id}
+                    className="flex items-center gap-3 p-3 rounded-lg border border-border/50 cursor-pointer hover:bg-muted/50 transition-colors"
+                  >
+                    <input
+                      type="radio"
+                      value={profile.id}
+                      checked={selectedProfile === profile.id}
+                      onChange={(e) => setSelectedProfile(e.target.value)}
+                      className="w-4 h-4 text-primary"
+                    />
+                    <span className="font-medium">{profile.name}</span>
+                    {profile.is_default && <span className="text-xs text-muted-foreground">Default</span>}
+                  </label>
+                ))}
               </div>
             </div>
 
@@ -317,6 +425,15 @@ export default function UploadView() {
                   </>
                 )}
               </Button>
+              <Button
+                onClick={handleUploadSet}
+                disabled={isUploading}
+                variant="outline"
+                className="w-full h-11"
+              >
+                <Layers className="mr-2 h-4 w-4" />
+                Upload Responsive Set
+              </Button>
               <Button
                 onClick={handleReset}
                 variant="outline"
@@ -357,13 +474,13 @@ export default function UploadView() {
             </div>
             <div className="space-y-2">
               <p className="text-2xl font-semibold">
-                {isDragActive ? "Drop image here" : "Drag & drop an image"}
+                {isDragActive ? "Drop images here" : "Drag & drop images"}
               </p>
               <p className="text-muted-foreground">
                 or choose from your computer
               </p>
               <p className="text-xs text-muted-foreground pt-2">
-                Supports PNG, JPG, JPEG, WebP, GIF
+                Supports PNG, JPG, JPEG, WebP, GIF, SVG
               </p>
             </div>
           </div>
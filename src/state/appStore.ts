@@ -3,7 +3,10 @@ import { create } from "zustand";
 export interface ProcessedImage {
   tempId: string;
   previewBase64: string;
+  mimeType: string;
   sizeInfo: string;
+  /** Why the output is not in the configured format, e.g. for animations. */
+  formatNotice?: string;
   originalName: string;
 }
 
@@ -14,15 +17,30 @@ export interface HistoryItem {
   url: string;
   createdAt: number;
   thumbnailBase64: string;
+  width: number | null;
+  groupId: string | null;
+}
+
+export type AppTab = "upload" | "history" | "settings";
+
+/** Settings section to bring into view, set by `openSettings` */
+export interface SettingsFocus {
+  provider?: string;
+  field?: string;
 }
 
 interface AppState {
+  activeTab: AppTab;
+  settingsFocus: SettingsFocus | null;
   processedImage: ProcessedImage | null;
   uploadedUrl: string | null;
   history: HistoryItem[];
   isProcessing: boolean;
   isUploading: boolean;
   
+  setActiveTab: (tab: AppTab) => void;
+  openSettings: (focus: SettingsFocus) => void;
+  clearSettingsFocus: () => void;
   setProcessedImage: (image: ProcessedImage | null) => void;
   setUploadedUrl: (url: string | null) => void;
   setHistory: (history: HistoryItem[]) => void;
@@ -32,12 +50,17 @@ interface AppState {
 }
 
 export const useAppStore = create<AppState>((set) => ({
+  activeTab: "upload",
+  settingsFocus: null,
   processedImage: null,
   uploadedUrl: null,
   history: [],
   isProcessing: false,
   isUploading: false,
   
+  setActiveTab: (tab) => set({ activeTab: tab }),
+  openSettings: (focus) => set({ activeTab: "settings", settingsFocus: focus }),
+  clearSettingsFocus: () => set({ settingsFocus: null }),
   setProcessedImage: (image) => set({ processedImage: image }),
   setUploadedUrl: (url) => set({ uploadedUrl: url }),
   setHistory: (history) => set({ history }),